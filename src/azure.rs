@@ -0,0 +1,59 @@
+use crate::input::Stream;
+use crate::{error, s3};
+
+pub const SCHEME: &str = "az://";
+
+/// Version of Blob service REST API the requests are made against
+const API_VERSION: &str = "2021-08-06";
+
+///
+/// Turns `az://account/container/blob` into the URL of the blob,
+/// the SAS token from `AZURE_STORAGE_SAS_TOKEN` is appended as a query, if any.
+fn blob_url(url: &str) -> Result<String, error::MrgError> {
+    let parts: Option<(&str, &str, &str)> = url.strip_prefix(SCHEME).and_then(|rest| {
+        let (account, rest) = rest.split_once('/')?;
+        let (container, blob) = rest.split_once('/')?;
+        Some((account, container, blob))
+    });
+    let (account, container, blob) = parts
+        .filter(|(account, container, blob)| {
+            !account.is_empty() && !container.is_empty() && !blob.is_empty()
+        })
+        .ok_or(error::MrgError {
+            msg: format!(
+                "'{}' is not a valid Azure Blob URL, expected az://account/container/blob",
+                url
+            ),
+        })?;
+    let mut blob_url = format!(
+        "https://{}.blob.core.windows.net/{}/{}",
+        account,
+        container,
+        s3::uri_encode(blob)
+    );
+    if let Ok(sas) = std::env::var("AZURE_STORAGE_SAS_TOKEN") {
+        blob_url.push('?');
+        blob_url.push_str(sas.trim_start_matches('?'));
+    }
+    Ok(blob_url)
+}
+
+///
+/// Streams the blob with a single GET request. Public blobs are read anonymously,
+/// private ones require `AZURE_STORAGE_SAS_TOKEN`.
+pub fn open(url: &str) -> Result<Stream, error::MrgError> {
+    let call = ureq::get(&blob_url(url)?).set("x-ms-version", API_VERSION);
+    Ok(call.call()?.into_reader())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn blob_url() {
+        assert_eq!(
+            "https://account.blob.core.windows.net/logs/2024/app%201.json.gz",
+            crate::azure::blob_url("az://account/logs/2024/app 1.json.gz").unwrap()
+        );
+        assert!(crate::azure::blob_url("az://account/logs").is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::input::{Format, Input, Stream};
+use crate::{error, timestamp, transform};
+
+/// Opened inputs of a batch
+type Inputs = Vec<Input<BufReader<Stream>>>;
+
+///
+/// Directory holding intermediate runs, it's removed along with its content when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Result<Self, error::MrgError> {
+        let path = std::env::temp_dir().join(format!("jmrg-{}", std::process::id()));
+        std::fs::create_dir_all(&path)?;
+        Ok(TempDir(path))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+///
+/// Splits the line of the intermediate run into the timestamp and the record
+pub fn parse(raw_line: &str) -> Result<(timestamp::Timestamp, String), serde_json::Error> {
+    let (ts, record) = raw_line
+        .split_once('\t')
+        .ok_or(serde::de::Error::custom("line of the run has no timestamp"))?;
+    let ts = ts
+        .parse::<timestamp::Timestamp>()
+        .map_err(serde::de::Error::custom)?;
+    Ok((ts, record.to_string()))
+}
+
+///
+/// Splits the quoted path of the input the record comes from off the record of the run
+fn split_origin(record: &str) -> std::io::Result<(String, &str)> {
+    let mut quoted = serde_json::Deserializer::from_str(record).into_iter::<String>();
+    let origin = quoted.next().transpose()?.unwrap_or_default();
+    let raw_line = record[quoted.byte_offset()..]
+        .strip_prefix('\t')
+        .ok_or(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "record of the run has no origin",
+        ))?;
+    Ok((origin, raw_line))
+}
+
+///
+/// Merges the inputs into a new run, the timestamps are kept so that they are not parsed again.
+/// Records of the inputs are preceded by the quoted paths of the inputs, so that they are
+/// transformed once they are emitted, records of the runs have the paths already.
+fn spill(
+    parser: &timestamp::Parser,
+    bounds: timestamp::Bounds,
+    ins: Inputs,
+    runs: bool,
+    path: &Path,
+) -> Result<(), error::MrgError> {
+    let mut out = BufWriter::with_capacity(crate::BUF_SIZE, File::create(path)?);
+    crate::merge(parser, bounds, ins, |ts, origin, raw_line| match runs {
+        true => writeln!(out, "{}\t{}", ts, raw_line),
+        false => writeln!(
+            out,
+            "{}\t{}\t{}",
+            ts,
+            serde_json::Value::from(origin),
+            raw_line
+        ),
+    })?;
+    out.flush()?;
+    Ok(())
+}
+
+fn open_runs(runs: &[PathBuf]) -> Result<Inputs, error::MrgError> {
+    runs.iter()
+        .map(|run| {
+            let stream: Stream = Box::new(File::open(run)?);
+            Ok(Input {
+                path: Arc::from(run.to_string_lossy()),
+                format: Format::Run,
+                reader: BufReader::with_capacity(crate::BUF_SIZE, stream),
+            })
+        })
+        .collect()
+}
+
+///
+/// Merges more inputs than can be open at once. Inputs are merged in batches into
+/// intermediate runs stored in the temporary directory, runs are merged the same way
+/// until there are few enough of them to be merged into the output.
+///
+/// # Arguments
+///
+/// * `parser`: extracts timestamps out of the records
+/// * `bounds`: records with timestamps out of them are skipped
+/// * `paths`: inputs to merge
+/// * `open`: opens the batch of inputs
+/// * `max_open`: how many inputs can be open at once, at least 2
+/// * `transform`: changes made to the records
+/// * `out`: the merged stream
+pub fn run<Out: crate::output::Sink>(
+    parser: &timestamp::Parser,
+    bounds: timestamp::Bounds,
+    paths: &[String],
+    open: &dyn Fn(&[String]) -> Result<Inputs, error::MrgError>,
+    max_open: usize,
+    transform: &mut transform::Transform,
+    out: &mut Out,
+) -> Result<(), error::MrgError> {
+    let dir = TempDir::new()?;
+    let mut runs: Vec<PathBuf> = Vec::with_capacity(paths.len().div_ceil(max_open));
+    for batch in paths.chunks(max_open) {
+        let run = dir.0.join(format!("{}", runs.len()));
+        spill(parser, bounds, open(batch)?, false, &run)?;
+        runs.push(run);
+    }
+    let mut generation = 0;
+    while runs.len() > max_open {
+        generation += 1;
+        let mut merged: Vec<PathBuf> = Vec::with_capacity(runs.len().div_ceil(max_open));
+        for batch in runs.chunks(max_open) {
+            let run = dir.0.join(format!("{}-{}", generation, merged.len()));
+            spill(parser, Default::default(), open_runs(batch)?, true, &run)?;
+            batch.iter().try_for_each(std::fs::remove_file)?;
+            merged.push(run);
+        }
+        runs = merged;
+    }
+    crate::merge(
+        parser,
+        Default::default(),
+        open_runs(&runs)?,
+        |ts, _, record| {
+            let (origin, raw_line) = split_origin(record)?;
+            out.emit(ts, &transform.apply(ts, &origin, raw_line))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            (15_000_000, r#"{"t":15}"#.to_string()),
+            crate::cascade::parse("15000000\t{\"t\":15}").unwrap()
+        );
+        assert!(crate::cascade::parse(r#"{"t":15}"#).is_err());
+    }
+
+    #[test]
+    fn run() {
+        let parser =
+            crate::timestamp::Parser::new(std::collections::HashSet::from([String::from("t")]));
+        let paths: Vec<String> = ["3", "1", "4", "1", "5", "9", "2", "6"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let open = |batch: &[String]| {
+            Ok(batch
+                .iter()
+                .map(|t| {
+                    let stream: crate::input::Stream =
+                        Box::new(std::io::Cursor::new(format!("{{\"t\":{}}}\n", t)));
+                    crate::input::Input {
+                        path: std::sync::Arc::from(t.as_str()),
+                        format: crate::input::Format::Ndjson,
+                        reader: BufReader::new(stream),
+                    }
+                })
+                .collect())
+        };
+        let mut out: Vec<u8> = Vec::new();
+        crate::cascade::run(
+            &parser,
+            crate::timestamp::Bounds {
+                since: Some(2_000_000),
+                until: None,
+            },
+            &paths,
+            &open,
+            2,
+            &mut Default::default(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            "{\"t\":2}\n{\"t\":3}\n{\"t\":4}\n{\"t\":5}\n{\"t\":6}\n{\"t\":9}\n",
+            String::from_utf8(out).unwrap()
+        );
+        let mut out: Vec<u8> = Vec::new();
+        let mut transform = crate::transform::Transform {
+            prefix: true,
+            ..Default::default()
+        };
+        crate::cascade::run(
+            &parser,
+            Default::default(),
+            &paths[..3],
+            &open,
+            2,
+            &mut transform,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            "1: {\"t\":1}\n3: {\"t\":3}\n4: {\"t\":4}\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::io::{BufWriter, Stdout, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[cfg(feature = "arrow")]
+use arrow_ipc::writer::StreamWriter;
+use arrow_json::reader::Decoder;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "parquet")]
+use parquet::basic::Compression;
+#[cfg(feature = "parquet")]
+use parquet::file::properties::WriterProperties;
+
+use crate::error;
+use crate::output::{Sink, Target};
+use crate::timestamp::Timestamp;
+use crate::transform::OutputFormat;
+
+/// Records in a row group, the schema is inferred from the first of them unless it's given
+const BATCH_SIZE: usize = 8192;
+
+///
+/// Reads the schema from the JSON object of fields and their Arrow types,
+/// e.g. `{"ts": "Int64", "level": "Utf8", "latency": "Float64"}`
+pub fn read_schema(path: &str) -> Result<SchemaRef, error::MrgError> {
+    let invalid = |reason: String| error::MrgError {
+        msg: format!("'{}' is not a valid schema: {}", path, reason),
+    };
+    let content = std::fs::read_to_string(path)?;
+    let fields = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content)
+        .map_err(|e| invalid(e.to_string()))?
+        .into_iter()
+        .map(|(name, data_type)| {
+            let data_type = data_type
+                .as_str()
+                .ok_or_else(|| invalid(format!("the type of '{}' is not a string", name)))?;
+            let data_type = DataType::from_str(data_type).map_err(|e| invalid(e.to_string()))?;
+            Ok(Field::new(name, data_type, true))
+        })
+        .collect::<Result<Vec<Field>, error::MrgError>>()?;
+    Ok(Arc::new(Schema::new(fields)))
+}
+
+fn other(e: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::other(format!("cannot write columnar output: {}", e))
+}
+
+///
+/// Where the columnar output goes, files take their places once they are complete
+enum Destination {
+    File(Box<Target>),
+    Stdout(BufWriter<Stdout>),
+}
+
+impl Destination {
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            Destination::File(target) => target.finish(),
+            Destination::Stdout(mut writer) => writer.flush(),
+        }
+    }
+}
+
+impl Write for Destination {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Destination::File(target) => target.write(buf),
+            Destination::Stdout(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Destination::File(target) => target.flush(),
+            Destination::Stdout(writer) => writer.flush(),
+        }
+    }
+}
+
+///
+/// Writer of record batches in one of the columnar formats
+enum Writer {
+    #[cfg(feature = "parquet")]
+    Parquet(ArrowWriter<Destination>),
+    #[cfg(feature = "arrow")]
+    Arrow(StreamWriter<Destination>),
+}
+
+impl Writer {
+    fn create(
+        format: OutputFormat,
+        destination: Destination,
+        schema: SchemaRef,
+    ) -> std::io::Result<Self> {
+        match format {
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(destination, schema, Some(properties));
+                Ok(Writer::Parquet(writer.map_err(other)?))
+            }
+            #[cfg(feature = "arrow")]
+            OutputFormat::Arrow => Ok(Writer::Arrow(
+                StreamWriter::try_new(destination, &schema).map_err(other)?,
+            )),
+            format => Err(other(format!(
+                "{:?} requires jmrg to be built with the corresponding cargo feature",
+                format
+            ))),
+        }
+    }
+
+    fn write(&mut self, batch: &arrow_array::RecordBatch) -> std::io::Result<()> {
+        match self {
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(writer) => writer.write(batch).map_err(other),
+            #[cfg(feature = "arrow")]
+            Writer::Arrow(writer) => writer.write(batch).map_err(other),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(writer) => writer.inner_mut().flush(),
+            #[cfg(feature = "arrow")]
+            Writer::Arrow(writer) => writer.get_mut().flush(),
+        }
+    }
+
+    ///
+    /// Writes the footer or the end of the stream
+    fn into_inner(self) -> std::io::Result<Destination> {
+        match self {
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(writer) => writer.into_inner().map_err(other),
+            #[cfg(feature = "arrow")]
+            Writer::Arrow(writer) => writer.into_inner().map_err(other),
+        }
+    }
+}
+
+///
+/// Parquet file or Arrow IPC stream the records are written to as rows, the lines which are not JSON objects are skipped
+pub struct Columnar {
+    format: OutputFormat,
+    /// the file or the standard output until the schema is known
+    destination: Option<Destination>,
+    schema: Option<SchemaRef>,
+    writer: Option<(Writer, Decoder)>,
+    /// records which are not written yet
+    rows: Vec<serde_json::Map<String, serde_json::Value>>,
+    skipped: u64,
+}
+
+impl Columnar {
+    ///
+    /// # Arguments
+    ///
+    /// * `format`: parquet or arrow
+    /// * `path`: the file to write, it's replaced once the merge is complete, the standard output unless given
+    /// * `schema`: columns of the output, they are inferred from the first records unless given
+    pub fn create(
+        format: OutputFormat,
+        path: Option<&str>,
+        schema: Option<SchemaRef>,
+    ) -> Result<Self, error::MrgError> {
+        let destination = match path {
+            Some(path) => {
+                Destination::File(Box::new(Target::create(Path::new(path), None, b'\n')?))
+            }
+            None => {
+                Destination::Stdout(BufWriter::with_capacity(crate::BUF_SIZE, std::io::stdout()))
+            }
+        };
+        Ok(Columnar {
+            format,
+            destination: Some(destination),
+            schema,
+            writer: None,
+            rows: Vec::new(),
+            skipped: 0,
+        })
+    }
+
+    ///
+    /// Writes the records gathered so far as a row group
+    fn write_rows(&mut self) -> std::io::Result<()> {
+        if self.writer.is_none() {
+            let schema = match &self.schema {
+                Some(schema) => schema.clone(),
+                None => Arc::new(
+                    arrow_json::reader::infer_json_schema_from_iterator(
+                        self.rows
+                            .iter()
+                            .map(|row| Ok(serde_json::Value::from(row.clone()))),
+                    )
+                    .map_err(other)?,
+                ),
+            };
+            let decoder = arrow_json::ReaderBuilder::new(schema.clone())
+                .with_batch_size(BATCH_SIZE)
+                .with_coerce_primitive(true)
+                .build_decoder()
+                .map_err(other)?;
+            let destination = self
+                .destination
+                .take()
+                .ok_or_else(|| other("the output is closed"))?;
+            let writer = Writer::create(self.format, destination, schema)?;
+            self.writer = Some((writer, decoder));
+        }
+        if let Some((writer, decoder)) = &mut self.writer {
+            decoder.serialize(&self.rows).map_err(other)?;
+            self.rows.clear();
+            if let Some(batch) = decoder.flush().map_err(other)? {
+                writer.write(&batch)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Writes the rest of the records and the footer, the file takes its place
+    pub fn finish(mut self) -> Result<(), error::MrgError> {
+        self.write_rows()?;
+        if self.skipped > 0 {
+            eprintln!(
+                "{} lines which are not JSON objects are not written to {:?}",
+                self.skipped, self.format
+            );
+        }
+        match self.writer {
+            Some((writer, _)) => Ok(writer.into_inner()?.finish()?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Sink for Columnar {
+    fn emit(&mut self, _: Timestamp, line: &str) -> std::io::Result<()> {
+        match serde_json::from_str(line) {
+            Ok(row) => self.rows.push(row),
+            Err(_) => self.skipped += 1,
+        }
+        match self.rows.len() >= BATCH_SIZE {
+            true => self.write_rows(),
+            false => Ok(()),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        match &mut self.writer {
+            Some((writer, _)) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::output::Sink;
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet() {
+        let dir = std::env::temp_dir().join(format!("jmrg-parquet-{}", std::process::id()));
+        let path = dir.join("merged.parquet");
+        let mut output = crate::columnar::Columnar::create(
+            crate::transform::OutputFormat::Parquet,
+            path.to_str(),
+            None,
+        )
+        .unwrap();
+        output
+            .emit(1, r#"{"ts":1,"level":"info","msg":"a"}"#)
+            .unwrap();
+        output.emit(2, "not json").unwrap();
+        output
+            .emit(3, r#"{"ts":3,"msg":"b","latency":0.5}"#)
+            .unwrap();
+        output.finish().unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let reader = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(1, batches.len());
+        assert_eq!(2, batches[0].num_rows());
+        let schema = batches[0].schema();
+        let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(vec!["ts", "level", "msg", "latency"], names);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn arrow() {
+        let dir = std::env::temp_dir().join(format!("jmrg-arrow-{}", std::process::id()));
+        let path = dir.join("merged.arrows");
+        let schema = Arc::new(arrow_schema::Schema::new(vec![
+            arrow_schema::Field::new("ts", arrow_schema::DataType::Int64, true),
+            arrow_schema::Field::new("msg", arrow_schema::DataType::Utf8, true),
+        ]));
+        let mut output = crate::columnar::Columnar::create(
+            crate::transform::OutputFormat::Arrow,
+            path.to_str(),
+            Some(schema.clone()),
+        )
+        .unwrap();
+        output
+            .emit(1, r#"{"ts":1,"msg":"a","extra":true}"#)
+            .unwrap();
+        output.emit(2, r#"{"ts":2,"msg":3}"#).unwrap();
+        output.finish().unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let reader = arrow_ipc::reader::StreamReader::try_new(file, None).unwrap();
+        assert_eq!(schema, reader.schema());
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(1, batches.len());
+        assert_eq!(2, batches[0].num_rows());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_schema() {
+        let dir = std::env::temp_dir().join(format!("jmrg-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("schema.json");
+        std::fs::write(&path, r#"{"ts": "Int64", "msg": "Utf8"}"#).unwrap();
+        let schema = crate::columnar::read_schema(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            Arc::new(arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("ts", arrow_schema::DataType::Int64, true),
+                arrow_schema::Field::new("msg", arrow_schema::DataType::Utf8, true),
+            ])),
+            schema
+        );
+        std::fs::write(&path, r#"{"ts": "Integer"}"#).unwrap();
+        assert!(crate::columnar::read_schema(path.to_str().unwrap()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
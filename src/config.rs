@@ -1,8 +1,327 @@
-use crate::error;
+use std::collections::{HashMap, HashSet};
+
+use crate::{error, filter, input, locale, output, s3, timestamp, transform};
+
+/// Keys looked for when none are given, in the order of precedence
+const DEFAULT_KEYS: [&str; 6] = [
+    "timestamp",
+    "@timestamp",
+    "time",
+    "ts",
+    "datetime",
+    "eventTime",
+];
 
 pub struct Arguments {
     pub keys: Vec<String>,
+    /// keys in the order of precedence
+    pub key_priority: Vec<String>,
+    /// zone of datetimes without offset
+    pub zone: Option<timestamp::Zone>,
+    /// year of syslog timestamps, which lack it
+    pub year: Option<i32>,
+    /// language of month and weekday names
+    pub locale: Option<locale::Locale>,
+    /// leap seconds and the end of the day are rejected
+    pub strict_datetime: bool,
+    /// unit of epoch timestamps
+    pub unit: timestamp::Unit,
+    /// units of the keys which differ from `unit`
+    pub units: HashMap<String, timestamp::Unit>,
+    /// keys of the inputs which differ from `keys`, by path of the input
+    pub keys_for: HashMap<String, HashSet<String>>,
+    /// shifts of the timestamps of the inputs, by path of the input
+    pub offsets: HashMap<String, timestamp::Timestamp>,
+    /// the largest clock skew of the inputs estimated, if it's estimated
+    pub auto_skew: Option<timestamp::Timestamp>,
+    /// emitted timestamps never go back in time
+    pub monotonic: bool,
+    /// records without the keys take the timestamp of the preceding record over
+    pub inherit_ts: bool,
+    /// timestamp of the records without the keys at the beginning of the inputs
+    pub fallback: Option<timestamp::Fallback>,
+    /// zone the timestamps are rewritten as datetimes in, if they are rewritten
+    pub humanize_ts: Option<timestamp::Zone>,
+    /// fields joined into the timestamp
+    pub compose: Vec<String>,
+    /// extracts the timestamp out of free text of a field
+    pub ts_regex: Option<timestamp::TsRegex>,
+    /// number of records the timestamp key is detected by, if it's detected
+    pub auto_key: Option<usize>,
     pub paths: Vec<String>,
+    /// file with more paths, each of them is terminated by `manifest_separator`
+    pub files_from: Option<String>,
+    pub manifest_separator: u8,
+    /// records which don't pass it are skipped
+    pub filter: Option<filter::Filter>,
+    /// records before it are skipped
+    pub since: Option<timestamp::Timestamp>,
+    /// records at it and after it are skipped
+    pub until: Option<timestamp::Timestamp>,
+    pub walk: input::Walk,
+    pub follow: bool,
+    pub reorder_window: i64,
+    /// files the merged records are written to instead of the standard output, `-` stands for it
+    pub output: Vec<output::Destination>,
+    /// compression of the output, if it's not inferred
+    pub compress: Option<input::Codec>,
+    /// the output is split into files by windows of this length, if it's split
+    pub split_by: Option<timestamp::Timestamp>,
+    /// the output is split into files of this many bytes, if it's split
+    pub split_size: Option<u64>,
+    /// field of the records the timestamp is written into, if it's written
+    pub normalize_ts: Option<transform::Normalize>,
+    /// field of the records the label of their input is written into, if it's written
+    pub tag_source: Option<String>,
+    /// labels of the inputs which differ from their paths, by path of the input
+    pub labels: HashMap<String, String>,
+    /// lines are prefixed with the labels of their inputs
+    pub prefix: bool,
+    /// field of the records the number of the line in the output is written into, if it's written
+    pub add_seq: Option<String>,
+    /// records are indented and separated by empty lines
+    pub pretty: bool,
+    /// records are re-serialized without insignificant whitespace
+    pub compact: bool,
+    /// fields of the records go first in this order and the rest are sorted, if they are ordered
+    pub canonical: Option<Vec<String>>,
+    pub output_format: transform::OutputFormat,
+    /// file of the columns of parquet and arrow output
+    pub schema: Option<String>,
+    /// table of sqlite output
+    pub table: String,
+    /// index of es-bulk actions
+    pub index: Option<transform::Index>,
+    /// services the records are forwarded to along with the files
+    pub sink: Vec<output::Remote>,
+    /// fields of the records whose values become Loki labels
+    pub loki_labels: Vec<String>,
+    /// fields written as columns of CSV and TSV output
+    pub columns: Option<Vec<String>>,
+    /// records are rendered through it instead of the output format, if it's given
+    pub template: Option<transform::Template>,
+    pub color: transform::ColorChoice,
+    /// fields of the records which are kept, if the rest is dropped
+    pub select: Option<Vec<String>>,
+    /// fields removed from the records
+    pub drop: Vec<String>,
+    /// separator of the names of nested fields, if they are flattened
+    pub flatten: Option<String>,
+    /// fields added to every record
+    pub add_fields: Vec<(String, String)>,
+    /// fields added to the records of the inputs, by path of the input
+    pub add_fields_for: HashMap<String, Vec<(String, String)>>,
+    /// field of the records the name of the host is written into, if it's written
+    pub add_host: Option<String>,
+    /// field of the records the metadata of the merge is written into, if it's written
+    pub add_meta: Option<String>,
+    /// fields of the records whose values are replaced
+    pub redact: Vec<String>,
+    /// text of the string values which is replaced
+    pub redact_patterns: Vec<regex::Regex>,
+    /// fields of the records whose values are replaced with keyed hashes
+    pub hash_fields: Vec<String>,
+    /// key of the hashes
+    pub hash_salt: Option<String>,
+    /// string values of the records longer than this are truncated
+    pub max_field_len: Option<usize>,
+    /// records are wrapped into a single JSON array
+    pub array: bool,
+    /// records are followed by NUL instead of a newline
+    pub print0: bool,
+    /// inputs are merged in batches of this size when there are more of them
+    pub max_open_files: Option<usize>,
+    pub format: input::Format,
+    /// compressions of the inputs which are not inferred
+    pub codecs: HashMap<String, input::Codec>,
+    /// compression of all the other inputs, if it's not inferred
+    pub codec: Option<input::Codec>,
+}
+
+fn get_strings(matches: &clap::ArgMatches, id: &str) -> Vec<String> {
+    matches
+        .get_many::<String>(id)
+        .map(|values| values.map(|s: &String| s.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn get_patterns(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<Vec<glob::Pattern>, error::MrgError> {
+    Ok(get_strings(matches, id)
+        .iter()
+        .map(|p| glob::Pattern::new(p))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+///
+/// Parses `path=codec` pairs, the last `=` separates the codec so that paths may contain it
+fn get_codecs(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<HashMap<String, input::Codec>, error::MrgError> {
+    get_strings(matches, id)
+        .iter()
+        .map(|hint| match hint.rsplit_once('=') {
+            Some((path, codec)) => Ok((path.to_string(), input::Codec::parse(codec)?)),
+            None => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid format hint, expected file=format",
+                    hint
+                ),
+            }),
+        })
+        .collect()
+}
+
+///
+/// Parses `key=unit` pairs
+fn get_units(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<HashMap<String, timestamp::Unit>, error::MrgError> {
+    get_strings(matches, id)
+        .iter()
+        .map(|pair| match pair.rsplit_once('=') {
+            Some((key, unit)) => Ok((key.to_string(), timestamp::Unit::parse(unit)?)),
+            None => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid unit of the key, expected key=unit",
+                    pair
+                ),
+            }),
+        })
+        .collect()
+}
+
+///
+/// Parses `path=key` pairs, keys of the same path are accumulated
+fn get_keys_for(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<HashMap<String, HashSet<String>>, error::MrgError> {
+    let mut keys_for: HashMap<String, HashSet<String>> = HashMap::new();
+    for pair in get_strings(matches, id) {
+        match pair.rsplit_once('=') {
+            Some((path, key)) => {
+                keys_for
+                    .entry(path.to_string())
+                    .or_default()
+                    .insert(key.to_string());
+            }
+            None => {
+                return Err(error::MrgError {
+                    msg: format!(
+                        "'{}' is not a valid key of the file, expected file=key",
+                        pair
+                    ),
+                })
+            }
+        }
+    }
+    Ok(keys_for)
+}
+
+///
+/// Strips unit annotations like `ts:ms` off the keys, the units are added to `units`
+/// unless they are given for the keys explicitly. Keys whose suffix is not a unit are left intact.
+fn strip_units(keys: Vec<String>, units: &mut HashMap<String, timestamp::Unit>) -> Vec<String> {
+    keys.into_iter()
+        .map(|key| {
+            let annotated = key
+                .rsplit_once(':')
+                .and_then(|(name, unit)| Some((name, timestamp::Unit::parse(unit).ok()?)));
+            match annotated {
+                Some((name, unit)) => {
+                    units.entry(name.to_string()).or_insert(unit);
+                    name.to_string()
+                }
+                None => key,
+            }
+        })
+        .collect()
+}
+
+///
+/// Parses `path=duration` pairs
+fn get_offsets(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<HashMap<String, timestamp::Timestamp>, error::MrgError> {
+    get_strings(matches, id)
+        .iter()
+        .map(|pair| match pair.rsplit_once('=') {
+            Some((path, offset)) => Ok((path.to_string(), timestamp::parse_duration(offset)?)),
+            None => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid offset of the file, expected file=offset",
+                    pair
+                ),
+            }),
+        })
+        .collect()
+}
+
+///
+/// Parses `path=label` pairs
+fn get_labels(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<HashMap<String, String>, error::MrgError> {
+    get_strings(matches, id)
+        .iter()
+        .map(|pair| match pair.rsplit_once('=') {
+            Some((path, label)) => Ok((path.to_string(), label.to_string())),
+            None => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid label of the file, expected file=label",
+                    pair
+                ),
+            }),
+        })
+        .collect()
+}
+
+///
+/// Parses `key=value` pair, the first `=` separates the value so that it may contain it
+fn parse_field(pair: &str) -> Result<(String, String), error::MrgError> {
+    match pair.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(error::MrgError {
+            msg: format!("'{}' is not a valid field, expected key=value", pair),
+        }),
+    }
+}
+
+///
+/// Parses `path=key=value` triples, the last two `=` separate the key and the value
+/// so that paths may contain it, fields of the same path are accumulated
+fn get_fields_for(
+    matches: &clap::ArgMatches,
+    id: &str,
+) -> Result<HashMap<String, Vec<(String, String)>>, error::MrgError> {
+    let mut fields_for: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for triple in get_strings(matches, id) {
+        let field = triple
+            .rsplit_once('=')
+            .and_then(|(rest, value)| Some((rest.rsplit_once('=')?, value)));
+        match field {
+            Some(((path, key), value)) if !key.is_empty() => fields_for
+                .entry(path.to_string())
+                .or_default()
+                .push((key.to_string(), value.to_string())),
+            _ => {
+                return Err(error::MrgError {
+                    msg: format!(
+                        "'{}' is not a valid field of the file, expected file=key=value",
+                        triple
+                    ),
+                })
+            }
+        }
+    }
+    Ok(fields_for)
 }
 
 pub fn parse(args: Vec<String>) -> Result<Arguments, error::MrgError> {
@@ -11,39 +330,806 @@ pub fn parse(args: Vec<String>) -> Result<Arguments, error::MrgError> {
         .arg(
             clap::Arg::new("keys")
                 .short('k')
-                .help("Specifies keys to look for, can be specified multiple times")
-                .default_value("timestamp")
+                .help("Specifies keys to look for, JSON pointers like /records/0/eventTime included, can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("key-priority")
+                .long("key-priority")
+                .help("Comma-separated keys in the order of precedence, used when a record has several of them")
+                .value_delimiter(','),
+        )
+        .arg(
+            clap::Arg::new("keys-for")
+                .long("keys-for")
+                .help("Specifies key to look for in the file instead of -k ones, e.g. 'app.json=ts', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("offset")
+                .long("offset")
+                .help("Shifts timestamps of the file with skewed clock, e.g. 'app.json=+2.5s', can be specified multiple times")
+                .allow_hyphen_values(true)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("auto-skew")
+                .long("auto-skew")
+                .help("Estimates clock skew of the files by bursts of their records and shifts their timestamps, up to 60s unless given, e.g. --auto-skew=5m")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("60s")
+                .conflicts_with("max-open-files"),
+        )
+        .arg(
+            clap::Arg::new("monotonic")
+                .long("monotonic")
+                .help("Clamps timestamps which go back in time to the preceding ones, rewriting them in the records")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("inherit-ts")
+                .long("inherit-ts")
+                .help("Keeps the records without the keys right after the preceding record of the file instead of dropping them")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("fallback")
+                .long("fallback")
+                .help("Orders the first records of the file without the keys by its modification time instead of dropping them")
+                .value_parser(["mtime"]),
+        )
+        .arg(
+            clap::Arg::new("humanize-ts")
+                .long("humanize-ts")
+                .help("Rewrites the timestamps of the records as RFC 3339 datetimes in the zone, --assume-tz one or UTC unless given, e.g. --humanize-ts=Europe/Berlin")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value(""),
+        )
+        .arg(
+            clap::Arg::new("compose-ts")
+                .long("compose-ts")
+                .help("Joins the fields into the timestamp, e.g. 'date+time', the records lacking any of them are ordered by the keys")
+                .value_delimiter('+'),
+        )
+        .arg(
+            clap::Arg::new("ts-regex")
+                .long("ts-regex")
+                .help("Extracts the timestamp out of the field with the regex, e.g. 'field=msg;re=^\\[(?P<ts>[^\\]]+)\\]'"),
+        )
+        .arg(
+            clap::Arg::new("auto-key")
+                .long("auto-key")
+                .help("Detects the timestamp key of every input by its first records, 100 unless given, e.g. --auto-key=20")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("100")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            clap::Arg::new("unit")
+                .long("unit")
+                .help("Specifies unit of epoch timestamps")
+                .value_parser(["s", "ms", "us", "ns", "filetime", "ticks", "auto"])
+                .default_value("ms"),
+        )
+        .arg(
+            clap::Arg::new("unit-for")
+                .long("unit-for")
+                .help("Specifies unit of epoch timestamps of the key, e.g. 'ts=s', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("assume-tz")
+                .long("assume-tz")
+                .help("Interprets datetimes without offset in the zone: UTC, local, offset like +02:00 or name like Europe/Berlin"),
+        )
+        .arg(
+            clap::Arg::new("year")
+                .long("year")
+                .visible_alias("assume-year")
+                .help("Specifies year of syslog timestamps like 'Jan  2 15:04:05', the last twelve months are assumed otherwise")
+                .value_parser(clap::value_parser!(i32)),
+        )
+        .arg(
+            clap::Arg::new("locale")
+                .long("locale")
+                .help("Accepts month and weekday names in the language in datetimes, besides English ones")
+                .value_parser(["de", "fr", "ru"]),
+        )
+        .arg(
+            clap::Arg::new("strict-datetime")
+                .long("strict-datetime")
+                .help("Rejects leap seconds like 23:59:60 and the end of the day 24:00:00 instead of mapping them to valid times")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("since")
+                .long("since")
+                .help("Skips records with timestamps below the given one, milliseconds since epoch, RFC 3339 datetime or relative time like '2h' or 'yesterday 14:00'"),
+        )
+        .arg(
+            clap::Arg::new("until")
+                .long("until")
+                .help("Skips records with timestamps at the given one and above, the same way as --since takes them")
+                .conflicts_with("follow"),
+        )
+        .arg(
+            clap::Arg::new("where")
+                .long("where")
+                .help("Merges only the records whose field has the value, e.g. 'level=error', or compares numeric fields with '<', '<=', '>', '>=', e.g. 'duration_ms>500', '!=' negates the match, can be repeated, the records have to meet all the conditions")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("any")
+                .long("any")
+                .help("Merges the records which meet any of --where conditions instead of all of them")
+                .action(clap::ArgAction::SetTrue)
+                .requires("where"),
+        )
+        .arg(
+            clap::Arg::new("anchor")
+                .long("anchor")
+                .help("The moment relative times of --since and --until are anchored to instead of now, e.g. '2024-05-01T12:00:00Z'"),
+        )
+        .arg(
+            clap::Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .help("Merges all files inside of the directories passed as inputs")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("include")
+                .long("include")
+                .help("Only merges files from directories whose names match the glob, can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("exclude")
+                .long("exclude")
+                .help("Skips files from directories whose names match the glob, can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("follow")
+                .short('f')
+                .long("follow")
+                .help("Keeps inputs open and merges new records as they are appended")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("output")
+                .short('o')
+                .long("output")
+                .help("Writes the merged records to the file, which is replaced once the merge is complete, can be repeated, '-' stands for the standard output. Options of the output precede its path, each of them ends with ';', e.g. 'where=level=error;format=logfmt;-'")
+                .action(clap::ArgAction::Append)
+                .required_if_eq_any([("output-format", "parquet"), ("output-format", "sqlite")])
+                .conflicts_with("follow"),
+        )
+        .arg(
+            clap::Arg::new("compress")
+                .long("compress")
+                .help("Compresses the output, it's inferred by the extension of the output file unless given")
+                .value_parser(["plain", "gzip", "bzip2", "zstd", "xz"]),
+        )
+        .arg(
+            clap::Arg::new("split-by")
+                .long("split-by")
+                .help("Splits the output into files by time windows, e.g. '--split-by 1h -o merged-%Y%m%dT%H.json'")
+                .requires("output"),
+        )
+        .arg(
+            clap::Arg::new("split-size")
+                .long("split-size")
+                .help("Splits the output into numbered files of the size before compression, e.g. '--split-size 500M -o merged.json'")
+                .requires("output")
+                .conflicts_with("split-by"),
+        )
+        .arg(
+            clap::Arg::new("normalize-ts")
+                .long("normalize-ts")
+                .help("Writes the timestamp of the record into the field, in iso8601 or an epoch unit, e.g. '@timestamp=iso8601'"),
+        )
+        .arg(
+            clap::Arg::new("tag-source")
+                .long("tag-source")
+                .help("Writes the label of the file the record comes from into the field, e.g. 'file'"),
+        )
+        .arg(
+            clap::Arg::new("label")
+                .long("label")
+                .help("Labels the file in the output instead of its path, e.g. 'app-1.json.gz=web-1', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("prefix")
+                .long("prefix")
+                .help("Prefixes every line with the label of the file it comes from, e.g. 'app-1.json.gz: {...}'")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("add-seq")
+                .long("add-seq")
+                .help("Writes the number of the line in the output into the field, e.g. 'seq'"),
+        )
+        .arg(
+            clap::Arg::new("pretty")
+                .long("pretty")
+                .help("Indents the records and separates them by empty lines, for reading")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("compact")
+                .long("compact")
+                .help("Strips insignificant whitespace off the records")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("pretty"),
+        )
+        .arg(
+            clap::Arg::new("canonical")
+                .long("canonical")
+                .help("Sorts the fields of the records by their names, the given ones go first, e.g. --canonical=ts,level,msg")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value(""),
+        )
+        .arg(
+            clap::Arg::new("output-format")
+                .long("output-format")
+                .help("Specifies how the records are written, lines which are not JSON objects are written as they are")
+                .value_parser([
+                    "ndjson", "logfmt", "csv", "tsv", "parquet", "arrow", "sqlite", "es-bulk", "gelf", "html",
+                ])
+                .default_value("ndjson"),
+        )
+        .arg(
+            clap::Arg::new("schema")
+                .long("schema")
+                .help("JSON file of the columns of parquet and arrow output and their Arrow types, e.g. '{\"ts\": \"Int64\", \"msg\": \"Utf8\"}', they are inferred from the records unless given")
+                .requires("output-format"),
+        )
+        .arg(
+            clap::Arg::new("table")
+                .long("table")
+                .help("Table of sqlite output the records are inserted into")
+                .default_value("logs"),
+        )
+        .arg(
+            clap::Arg::new("index")
+                .long("index")
+                .help("Index of es-bulk actions, the timestamp of the record in UTC is formatted into it, e.g. 'logs-%Y.%m.%d'")
+                .requires("output-format"),
+        )
+        .arg(
+            clap::Arg::new("sink")
+                .long("sink")
+                .help("Forwards the records to the service, e.g. 'loki=http://host:3100', 'otlp=http://host:4318', 'gelf=udp://host:12201', 'syslog=udp://host:514' or 'kafka=broker:9092/topic', can be repeated")
+                .action(clap::ArgAction::Append)
+                .conflicts_with_all(["array", "print0"]),
+        )
+        .arg(
+            clap::Arg::new("loki-labels")
+                .long("loki-labels")
+                .help("Comma-separated fields of the records whose values become the labels of Loki streams, e.g. 'service,level'")
+                .value_delimiter(',')
+                .requires("sink"),
+        )
+        .arg(
+            clap::Arg::new("columns")
+                .long("columns")
+                .help("Comma-separated fields written as columns of csv and tsv output, e.g. 'ts,level,k8s.pod,msg'")
+                .value_delimiter(',')
+                .required_if_eq_any([("output-format", "csv"), ("output-format", "tsv")]),
+        )
+        .arg(
+            clap::Arg::new("template")
+                .long("template")
+                .help("Renders the records through the template instead of the output format, e.g. '{ts} [{level}] {service}: {msg}'")
+                .conflicts_with_all(["output-format", "pretty"]),
+        )
+        .arg(
+            clap::Arg::new("color")
+                .long("color")
+                .help("Highlights the records by their level, errors are red and warnings are yellow")
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            clap::Arg::new("select")
+                .long("select")
+                .help("Comma-separated fields the records are left with, e.g. 'ts,level,msg,k8s.pod'")
+                .value_delimiter(','),
+        )
+        .arg(
+            clap::Arg::new("drop")
+                .long("drop")
+                .help("Removes the field from the records, e.g. 'kubernetes.labels', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("flatten")
+                .long("flatten")
+                .help("Moves fields of nested objects up to the records, their names are joined with '.' unless given, e.g. --flatten=_")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("."),
+        )
+        .arg(
+            clap::Arg::new("add-field")
+                .long("add-field")
+                .help("Adds the field with the string value to every record, e.g. 'env=prod', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("add-field-for")
+                .long("add-field-for")
+                .help("Adds the field to the records of the file, e.g. 'app-1.json.gz=cluster=eu-1', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("add-host")
+                .long("add-host")
+                .help("Writes the name of the host into the field of every record, 'host' unless given, e.g. --add-host=merged_on")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("host"),
+        )
+        .arg(
+            clap::Arg::new("add-meta")
+                .long("add-meta")
+                .help("Writes the id and the start time of the merge into the field of every record, 'jmrg' unless given")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("jmrg"),
+        )
+        .arg(
+            clap::Arg::new("redact")
+                .long("redact")
+                .help("Replaces the value of the field with \"[REDACTED]\", e.g. 'headers.authorization', can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("redact-pattern")
+                .long("redact-pattern")
+                .help("Replaces the text matching the regex in the string values with \"[REDACTED]\", can be specified multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("hash-field")
+                .long("hash-field")
+                .help("Replaces the value of the field with its HMAC-SHA256 keyed by --hash-salt, can be specified multiple times")
+                .action(clap::ArgAction::Append)
+                .requires("hash-salt"),
+        )
+        .arg(
+            clap::Arg::new("hash-salt")
+                .long("hash-salt")
+                .help("Secret key of the hashes written by --hash-field"),
+        )
+        .arg(
+            clap::Arg::new("max-field-len")
+                .long("max-field-len")
+                .help("Truncates string values longer than this many characters, marking them with an ellipsis")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            clap::Arg::new("array")
+                .long("array")
+                .help("Wraps the records into a single JSON array instead of writing them one per line")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["output-format", "template", "follow", "split-by", "split-size"]),
+        )
+        .arg(
+            clap::Arg::new("print0")
+                .long("print0")
+                .help("Follows every record with NUL instead of a newline, e.g. for 'xargs -0'")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("array"),
+        )
+        .arg(
+            clap::Arg::new("reorder-window")
+                .long("reorder-window")
+                .help("In follow mode, holds records back until a record newer by this much arrives")
+                .default_value("1000")
+                .value_parser(clap::value_parser!(i64)),
+        )
+        .arg(
+            clap::Arg::new("max-open-files")
+                .long("max-open-files")
+                .help("Merges inputs in batches of this size through temporary files when there are more of them")
+                .value_parser(clap::value_parser!(u32).range(2..))
+                .conflicts_with("follow"),
+        )
+        .arg(
+            clap::Arg::new("log-format")
+                .long("log-format")
+                .help("Specifies how records are laid out in the inputs")
+                .value_parser(["ndjson", "docker", "cri"])
+                .default_value("ndjson"),
+        )
+        .arg(
+            clap::Arg::new("unwrap")
+                .long("unwrap")
+                .help("Emits the payload instead of the whole line for the formats wrapping records, e.g. docker")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("input-format")
+                .long("input-format")
+                .help("Disables inference of compression, all inputs are expected to be compressed with the given one")
+                .value_parser(["plain", "gzip", "bzip2", "zstd", "xz", "lz4", "snappy"]),
+        )
+        .arg(
+            clap::Arg::new("format-for")
+                .long("format-for")
+                .help("Overrides inferred compression of the input, e.g. 'app.log=gzip', can be specified multiple times")
                 .action(clap::ArgAction::Append),
         )
+        .arg(
+            clap::Arg::new("files-from")
+                .long("files-from")
+                .help("Reads paths of the files to merge from the file, one per line, '-' stands for the standard input"),
+        )
+        .arg(
+            clap::Arg::new("files-from0")
+                .long("files-from0")
+                .help("Same as --files-from, but the paths are separated by NUL, e.g. produced by 'find -print0'")
+                .conflicts_with("files-from"),
+        )
         .arg(
             clap::Arg::new("files")
-                .required(true)
+                .required_unless_present_any(["files-from", "files-from0"])
                 .help("List of files to merge")
                 .action(clap::ArgAction::Append),
         )
         .get_matches_from(args);
-    let keys = matches
-        .get_many::<String>("keys")
-        .ok_or(error::MrgError {
-            msg: "no 'keys' are provided".to_string(),
-        })?
-        .map(|s: &String| s.to_string())
-        .collect::<Vec<String>>();
-    let paths: Vec<String> = matches
-        .get_many::<String>("files")
-        .ok_or(error::MrgError {
-            msg: "no 'files' provided".to_string(),
-        })?
-        .map(|s: &String| s.to_string())
-        .collect::<Vec<String>>();
-    Ok(Arguments { keys, paths })
+    let mut units = get_units(&matches, "unit-for")?;
+    let mut keys: Vec<String> = strip_units(get_strings(&matches, "keys"), &mut units);
+    let mut key_priority: Vec<String> = get_strings(&matches, "key-priority");
+    if keys.is_empty() && key_priority.is_empty() {
+        keys = DEFAULT_KEYS.map(String::from).to_vec();
+        key_priority = keys.clone();
+    }
+    let paths: Vec<String> = get_strings(&matches, "files");
+    let walk = input::Walk {
+        recursive: matches.get_flag("recursive"),
+        include: get_patterns(&matches, "include")?,
+        exclude: get_patterns(&matches, "exclude")?,
+    };
+    let format = match matches.get_one::<String>("log-format").map(String::as_str) {
+        Some("docker") => input::Format::Docker {
+            unwrap: matches.get_flag("unwrap"),
+        },
+        Some("cri") => input::Format::Cri,
+        _ => input::Format::Ndjson,
+    };
+    let zone = matches
+        .get_one::<String>("assume-tz")
+        .map(|name| timestamp::Zone::parse(name))
+        .transpose()?;
+    let humanize_ts = match matches.get_one::<String>("humanize-ts").map(String::as_str) {
+        Some("") => Some(zone.unwrap_or(timestamp::Zone::Utc)),
+        Some(name) => Some(timestamp::Zone::parse(name)?),
+        None => None,
+    };
+    // relative times are anchored to the moment jmrg is started unless it's given, days are the ones of the zone
+    let now = timestamp::from_datetime(&chrono::Utc::now());
+    let bound_zone = zone.unwrap_or(timestamp::Zone::Local);
+    let anchor = match matches.get_one::<String>("anchor") {
+        Some(value) => timestamp::parse_bound(value, now, bound_zone)?,
+        None => now,
+    };
+    let bound = |id: &str| {
+        matches
+            .get_one::<String>(id)
+            .map(|value| timestamp::parse_bound(value, anchor, bound_zone))
+            .transpose()
+    };
+    let sink = get_strings(&matches, "sink")
+        .iter()
+        .map(|spec| output::Remote::parse(spec))
+        .collect::<Result<Vec<output::Remote>, error::MrgError>>()?;
+    let outputs = get_strings(&matches, "output")
+        .iter()
+        .map(|spec| output::Destination::parse(spec))
+        .collect::<Result<Vec<output::Destination>, error::MrgError>>()?;
+    // outputs are written under the same temporary name, they'd overwrite each other
+    if let Some((_, duplicate)) = outputs.iter().enumerate().find(|(i, output)| {
+        outputs[..*i]
+            .iter()
+            .any(|other| std::path::Path::new(&other.path) == std::path::Path::new(&output.path))
+    }) {
+        return Err(error::MrgError {
+            msg: format!("output '{}' is given more than once", duplicate.path),
+        });
+    }
+    let output_format =
+        transform::OutputFormat::parse(matches.get_one::<String>("output-format").unwrap())?;
+    // GELF inputs take GELF messages only
+    let gelf = sink
+        .iter()
+        .any(|remote| matches!(remote, output::Remote::Gelf(_)));
+    let output_format = match (gelf, output_format) {
+        (true, transform::OutputFormat::Gelf) => output_format,
+        (true, _)
+            if matches.value_source("output-format")
+                == Some(clap::parser::ValueSource::CommandLine) =>
+        {
+            return Err(error::MrgError {
+                msg: "gelf sink requires gelf output format".to_string(),
+            });
+        }
+        (true, _) => transform::OutputFormat::Gelf,
+        (false, _) => output_format,
+    };
+    // the pattern of split files and columnar outputs can't be shared with other outputs
+    if outputs.len() + sink.len() > 1 {
+        if let Some(id) = ["split-by", "split-size"]
+            .into_iter()
+            .find(|id| matches.contains_id(id))
+        {
+            return Err(error::MrgError {
+                msg: format!("--{} can't be used with multiple outputs", id),
+            });
+        }
+        if matches!(
+            output_format,
+            transform::OutputFormat::Parquet
+                | transform::OutputFormat::Arrow
+                | transform::OutputFormat::Sqlite
+        ) {
+            return Err(error::MrgError {
+                msg: format!(
+                    "{:?} output can't be written to multiple outputs",
+                    output_format
+                ),
+            });
+        }
+    }
+    // the outputs with their own options take the records as JSON objects and render them themselves
+    if outputs
+        .iter()
+        .any(|output| output.scope != output::Scope::default())
+    {
+        if output_format != transform::OutputFormat::Ndjson {
+            return Err(error::MrgError {
+                msg: format!(
+                    "{:?} output can't be used with options of single outputs, give the format of each output instead",
+                    output_format
+                ),
+            });
+        }
+        if let Some(id) = ["template", "prefix", "array"]
+            .into_iter()
+            .find(|id| matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine))
+        {
+            return Err(error::MrgError {
+                msg: format!("--{} can't be used with options of single outputs", id),
+            });
+        }
+        if !matches.contains_id("columns")
+            && outputs.iter().any(|output| {
+                matches!(
+                    output.scope.format,
+                    Some(transform::OutputFormat::Csv | transform::OutputFormat::Tsv)
+                )
+            })
+        {
+            return Err(error::MrgError {
+                msg: "csv and tsv outputs require --columns".to_string(),
+            });
+        }
+    }
+    // rows of parquet, arrow and sqlite are parsed out of JSON records, they are written in batches,
+    // html is a single document
+    if matches!(
+        output_format,
+        transform::OutputFormat::Parquet
+            | transform::OutputFormat::Arrow
+            | transform::OutputFormat::Sqlite
+            | transform::OutputFormat::Html
+    ) {
+        if let Some(id) = [
+            "split-by",
+            "split-size",
+            "compress",
+            "print0",
+            "pretty",
+            "prefix",
+            "color",
+            "follow",
+        ]
+        .into_iter()
+        .find(|id| matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine))
+        {
+            return Err(error::MrgError {
+                msg: format!("--{} can't be used with {:?} output", id, output_format),
+            });
+        }
+    }
+    // sqlite database is a file, it can't be streamed
+    if output_format == transform::OutputFormat::Sqlite
+        && outputs
+            .iter()
+            .any(|output| output.path == output::STDOUT_PATH)
+    {
+        return Err(error::MrgError {
+            msg: "Sqlite output can't be written to the standard output, give it a file with -o"
+                .to_string(),
+        });
+    }
+    // objects are uploaded as a whole, their content can't be inspected while it's written
+    if outputs
+        .iter()
+        .any(|output| output.path.starts_with(s3::SCHEME))
+    {
+        if let Some(id) = ["split-by", "split-size"]
+            .into_iter()
+            .find(|id| matches.contains_id(id))
+        {
+            return Err(error::MrgError {
+                msg: format!("--{} can't be used with S3 output", id),
+            });
+        }
+        if matches!(
+            output_format,
+            transform::OutputFormat::Parquet
+                | transform::OutputFormat::Arrow
+                | transform::OutputFormat::Sqlite
+        ) {
+            return Err(error::MrgError {
+                msg: format!("{:?} output can't be written to S3", output_format),
+            });
+        }
+    }
+    Ok(Arguments {
+        keys,
+        key_priority,
+        zone,
+        year: matches.get_one::<i32>("year").copied(),
+        locale: matches
+            .get_one::<String>("locale")
+            .map(|name| locale::Locale::parse(name))
+            .transpose()?,
+        strict_datetime: matches.get_flag("strict-datetime"),
+        unit: timestamp::Unit::parse(matches.get_one::<String>("unit").unwrap())?,
+        units,
+        keys_for: get_keys_for(&matches, "keys-for")?,
+        offsets: get_offsets(&matches, "offset")?,
+        auto_skew: matches
+            .get_one::<String>("auto-skew")
+            .map(|max| timestamp::parse_duration(max))
+            .transpose()?,
+        monotonic: matches.get_flag("monotonic"),
+        inherit_ts: matches.get_flag("inherit-ts"),
+        fallback: matches
+            .get_one::<String>("fallback")
+            .map(|name| timestamp::Fallback::parse(name))
+            .transpose()?,
+        humanize_ts,
+        compose: get_strings(&matches, "compose-ts"),
+        ts_regex: matches
+            .get_one::<String>("ts-regex")
+            .map(|spec| timestamp::TsRegex::parse(spec))
+            .transpose()?,
+        auto_key: matches
+            .get_one::<u32>("auto-key")
+            .map(|lines| *lines as usize),
+        paths,
+        files_from: matches
+            .get_one::<String>("files-from")
+            .or(matches.get_one::<String>("files-from0"))
+            .cloned(),
+        manifest_separator: if matches.contains_id("files-from0") {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        filter: match get_strings(&matches, "where") {
+            conditions if conditions.is_empty() => None,
+            conditions => Some(filter::Filter {
+                conditions: conditions
+                    .iter()
+                    .map(|spec| filter::Condition::parse(spec))
+                    .collect::<Result<Vec<filter::Condition>, error::MrgError>>()?,
+                any: matches.get_flag("any"),
+            }),
+        },
+        since: bound("since")?,
+        until: bound("until")?,
+        walk,
+        follow: matches.get_flag("follow"),
+        reorder_window: *matches.get_one::<i64>("reorder-window").unwrap(),
+        output: outputs,
+        compress: matches
+            .get_one::<String>("compress")
+            .map(|name| input::Codec::parse(name))
+            .transpose()?,
+        split_by: matches
+            .get_one::<String>("split-by")
+            .map(|window| timestamp::parse_duration(window))
+            .transpose()?,
+        split_size: matches
+            .get_one::<String>("split-size")
+            .map(|size| output::parse_size(size))
+            .transpose()?,
+        normalize_ts: matches
+            .get_one::<String>("normalize-ts")
+            .map(|spec| transform::Normalize::parse(spec))
+            .transpose()?,
+        tag_source: matches.get_one::<String>("tag-source").cloned(),
+        labels: get_labels(&matches, "label")?,
+        prefix: matches.get_flag("prefix"),
+        add_seq: matches.get_one::<String>("add-seq").cloned(),
+        pretty: matches.get_flag("pretty"),
+        compact: matches.get_flag("compact"),
+        output_format,
+        schema: matches.get_one::<String>("schema").cloned(),
+        table: matches.get_one::<String>("table").unwrap().clone(),
+        index: matches
+            .get_one::<String>("index")
+            .map(|pattern| transform::Index::parse(pattern))
+            .transpose()?,
+        sink,
+        loki_labels: get_strings(&matches, "loki-labels"),
+        columns: matches
+            .contains_id("columns")
+            .then(|| get_strings(&matches, "columns")),
+        template: matches
+            .get_one::<String>("template")
+            .map(|template| transform::Template::parse(template))
+            .transpose()?,
+        color: transform::ColorChoice::parse(matches.get_one::<String>("color").unwrap())?,
+        select: matches
+            .contains_id("select")
+            .then(|| get_strings(&matches, "select")),
+        drop: get_strings(&matches, "drop"),
+        flatten: matches.get_one::<String>("flatten").cloned(),
+        add_fields: get_strings(&matches, "add-field")
+            .iter()
+            .map(|pair| parse_field(pair))
+            .collect::<Result<_, _>>()?,
+        add_fields_for: get_fields_for(&matches, "add-field-for")?,
+        add_host: matches.get_one::<String>("add-host").cloned(),
+        add_meta: matches.get_one::<String>("add-meta").cloned(),
+        redact: get_strings(&matches, "redact"),
+        redact_patterns: get_strings(&matches, "redact-pattern")
+            .iter()
+            .map(|pattern| regex::Regex::new(pattern))
+            .collect::<Result<_, _>>()?,
+        hash_fields: get_strings(&matches, "hash-field"),
+        hash_salt: matches.get_one::<String>("hash-salt").cloned(),
+        max_field_len: matches
+            .get_one::<u32>("max-field-len")
+            .map(|max_len| *max_len as usize),
+        array: matches.get_flag("array"),
+        print0: matches.get_flag("print0"),
+        canonical: matches.get_one::<String>("canonical").map(|first| {
+            first
+                .split(',')
+                .filter(|key| !key.is_empty())
+                .map(String::from)
+                .collect()
+        }),
+        max_open_files: matches
+            .get_one::<u32>("max-open-files")
+            .map(|max_open| *max_open as usize),
+        format,
+        codecs: get_codecs(&matches, "format-for")?,
+        codec: matches
+            .get_one::<String>("input-format")
+            .map(|name| input::Codec::parse(name))
+            .transpose()?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     #[test]
     fn valid_parse() {
-        let args = vec![
+        let args = [
             "program_name",
             "-k",
             "hello",
@@ -62,12 +1148,1053 @@ mod tests {
 
     #[test]
     fn no_keys() {
-        let args = vec!["program_name", "1.log", "2.log"]
+        let args = ["program_name", "1.log", "2.log"]
             .iter()
             .map(|s| s.to_string())
             .collect::<Vec<String>>();
         let parsed = crate::config::parse(args).unwrap();
         assert_eq!(parsed.paths, vec!["1.log", "2.log"]);
-        assert_eq!(parsed.keys, vec!["timestamp"]);
+        assert_eq!(
+            parsed.keys,
+            vec![
+                "timestamp",
+                "@timestamp",
+                "time",
+                "ts",
+                "datetime",
+                "eventTime"
+            ]
+        );
+        assert_eq!(parsed.keys, parsed.key_priority);
+    }
+
+    #[test]
+    fn walk() {
+        let args = [
+            "program_name",
+            "-r",
+            "--include",
+            "*.json",
+            "--exclude",
+            "skip*",
+            "logs",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert!(parsed.walk.recursive);
+        assert_eq!(
+            vec![glob::Pattern::new("*.json").unwrap()],
+            parsed.walk.include
+        );
+        assert_eq!(
+            vec![glob::Pattern::new("skip*").unwrap()],
+            parsed.walk.exclude
+        );
+    }
+
+    #[test]
+    fn docker_format() {
+        let args = [
+            "program_name",
+            "--log-format",
+            "docker",
+            "--unwrap",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(crate::input::Format::Docker { unwrap: true }, parsed.format);
+    }
+
+    #[test]
+    fn files_from() {
+        let args = ["program_name", "--files-from", "-"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert!(parsed.paths.is_empty());
+        assert_eq!(Some("-".to_string()), parsed.files_from);
+    }
+
+    #[test]
+    fn files_from0() {
+        let args = ["program_name", "--files-from0", "list"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some("list".to_string()), parsed.files_from);
+        assert_eq!(b'\0', parsed.manifest_separator);
+    }
+
+    #[test]
+    fn format_for() {
+        let args = [
+            "program_name",
+            "--format-for",
+            "a=b.log=gz",
+            "--format-for",
+            "c=plain",
+            "a=b.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(
+            Some(&crate::input::Codec::Gzip),
+            parsed.codecs.get("a=b.log")
+        );
+        assert_eq!(Some(&crate::input::Codec::Plain), parsed.codecs.get("c"));
+    }
+
+    #[test]
+    fn invalid_format_for() {
+        let args = ["program_name", "--format-for", "a.log", "a.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).is_err());
+    }
+
+    #[test]
+    fn input_format() {
+        let args = ["program_name", "--input-format", "zstd", "-"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some(crate::input::Codec::Zstd), parsed.codec);
+    }
+
+    #[test]
+    fn max_open_files() {
+        let args = ["program_name", "--max-open-files", "100", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(100),
+            crate::config::parse(args).unwrap().max_open_files
+        );
+    }
+
+    #[test]
+    fn output() {
+        let args = ["program_name", "-o", "merged.json", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            vec![crate::output::Destination {
+                path: "merged.json".to_string(),
+                scope: Default::default(),
+            }],
+            crate::config::parse(args).unwrap().output
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        let args = [
+            "program_name",
+            "--since",
+            "yesterday 14:00",
+            "--until",
+            "2h",
+            "--anchor",
+            "2024-05-01T12:00:00Z",
+            "--assume-tz",
+            "UTC",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some(1_714_485_600_000_000_000), parsed.since);
+        assert_eq!(Some(1_714_557_600_000_000_000), parsed.until);
+    }
+
+    #[test]
+    fn filter() {
+        let args = [
+            "program_name",
+            "--where",
+            "level=error",
+            "--where",
+            "k8s.pod=api-1",
+            "--any",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        let filter = parsed.filter.unwrap();
+        assert_eq!(
+            vec![
+                crate::filter::Condition::parse("level=error").unwrap(),
+                crate::filter::Condition::parse("k8s.pod=api-1").unwrap(),
+            ],
+            filter.conditions
+        );
+        assert!(filter.any);
+    }
+
+    #[test]
+    fn tee() {
+        let args = [
+            "program_name",
+            "-o",
+            "merged.json.zst",
+            "-o",
+            "-",
+            "--sink",
+            "loki=http://localhost:3100",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args.clone()).unwrap();
+        assert_eq!(
+            vec!["merged.json.zst", "-"],
+            parsed
+                .output
+                .iter()
+                .map(|output| output.path.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(1, parsed.sink.len());
+        let split = [args, vec!["--split-by".to_string(), "1h".to_string()]].concat();
+        assert!(crate::config::parse(split).is_err());
+    }
+
+    #[test]
+    fn scoped_output() {
+        let parse = |args: &[&str]| {
+            let args = [&["program_name", "-o", "merged.json.zst"], args, &["1.log"]].concat();
+            crate::config::parse(args.iter().map(|s| s.to_string()).collect())
+        };
+        let parsed = parse(&[
+            "-o",
+            "where=level=error;where=status>=500;any;format=logfmt;-",
+        ])
+        .unwrap();
+        let scope = &parsed.output[1].scope;
+        assert_eq!("-", parsed.output[1].path);
+        assert_eq!(Some(crate::transform::OutputFormat::Logfmt), scope.format);
+        let filter = scope.filter.as_ref().unwrap();
+        assert_eq!(2, filter.conditions.len());
+        assert!(filter.any);
+        assert_eq!(crate::output::Scope::default(), parsed.output[0].scope);
+        assert!(parse(&["-o", "format=csv;-", "--columns", "t,msg"]).is_ok());
+        assert!(parse(&["-o", "format=csv;-"]).is_err());
+        assert!(parse(&["-o", "format=parquet;-"]).is_err());
+        assert!(parse(&["-o", "any;-"]).is_err());
+        assert!(parse(&["-o", "where=level=error;", "-o", "-"]).is_err());
+        assert!(parse(&["-o", "where=level=error;-", "--output-format", "logfmt"]).is_err());
+        assert!(parse(&["-o", "where=level=error;-", "--prefix"]).is_err());
+        // paths can have `;` in them, anything but the options is the path
+        let parsed = parse(&["-o", "where=level=error;logs;2024.json"]).unwrap();
+        assert_eq!("logs;2024.json", parsed.output[1].path);
+        assert!(parsed.output[1].scope.filter.is_some());
+        let parsed = parse(&["-o", "errors.json;where=level=error"]).unwrap();
+        assert_eq!("errors.json;where=level=error", parsed.output[1].path);
+        assert_eq!(crate::output::Scope::default(), parsed.output[1].scope);
+        // the outputs sharing the file would overwrite each other
+        assert!(parse(&["-o", "where=level=error;merged.json.zst"]).is_err());
+        assert!(parse(&["-o", "-", "-o", "format=logfmt;-"]).is_err());
+    }
+
+    #[test]
+    fn compress() {
+        let args = ["program_name", "--compress", "zstd", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::input::Codec::Zstd),
+            crate::config::parse(args).unwrap().compress
+        );
+    }
+
+    #[test]
+    fn split_by() {
+        let args = [
+            "program_name",
+            "--split-by",
+            "1h",
+            "-o",
+            "merged-%Y%m%dT%H.json",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            Some(3_600_000_000_000),
+            crate::config::parse(args).unwrap().split_by
+        );
+    }
+
+    #[test]
+    fn split_size() {
+        let args = [
+            "program_name",
+            "--split-size",
+            "500M",
+            "-o",
+            "merged.json",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            Some(500 * 1024 * 1024),
+            crate::config::parse(args).unwrap().split_size
+        );
+    }
+
+    #[test]
+    fn normalize_ts() {
+        let args = ["program_name", "--normalize-ts", "@timestamp=ms", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::transform::Normalize {
+                field: "@timestamp".to_string(),
+                format: crate::transform::TsFormat::Epoch(crate::timestamp::Unit::Millis),
+            }),
+            crate::config::parse(args).unwrap().normalize_ts
+        );
+    }
+
+    #[test]
+    fn tag_source() {
+        let args = [
+            "program_name",
+            "--tag-source",
+            "file",
+            "--label",
+            "app-1.json.gz=web-1",
+            "app-1.json.gz",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some("file".to_string()), parsed.tag_source);
+        assert_eq!(
+            Some(&"web-1".to_string()),
+            parsed.labels.get("app-1.json.gz")
+        );
+    }
+
+    #[test]
+    fn prefix() {
+        let args = ["program_name", "--prefix", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().prefix);
+    }
+
+    #[test]
+    fn add_seq() {
+        let args = ["program_name", "--add-seq", "seq", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some("seq".to_string()),
+            crate::config::parse(args).unwrap().add_seq
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        let args = ["program_name", "--pretty", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().pretty);
+    }
+
+    #[test]
+    fn compact() {
+        let args = ["program_name", "--compact", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().compact);
+    }
+
+    #[test]
+    fn canonical() {
+        let args = ["program_name", "--canonical", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(Vec::new()),
+            crate::config::parse(args).unwrap().canonical
+        );
+        let args = ["program_name", "--canonical=ts,msg", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(vec!["ts".to_string(), "msg".to_string()]),
+            crate::config::parse(args).unwrap().canonical
+        );
+    }
+
+    #[test]
+    fn output_format() {
+        let args = ["program_name", "--output-format", "logfmt", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            crate::transform::OutputFormat::Logfmt,
+            crate::config::parse(args).unwrap().output_format
+        );
+    }
+
+    #[test]
+    fn parquet() {
+        let args = [
+            "program_name",
+            "--output-format",
+            "parquet",
+            "--schema",
+            "schema.json",
+            "-o",
+            "merged.parquet",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args.clone()).unwrap();
+        assert_eq!(
+            crate::transform::OutputFormat::Parquet,
+            parsed.output_format
+        );
+        assert_eq!(Some("schema.json".to_string()), parsed.schema);
+        let split = [args, vec!["--split-size".to_string(), "1M".to_string()]].concat();
+        assert!(crate::config::parse(split).is_err());
+    }
+
+    #[test]
+    fn arrow() {
+        let args = ["program_name", "--output-format", "arrow", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            crate::transform::OutputFormat::Arrow,
+            crate::config::parse(args.clone()).unwrap().output_format
+        );
+        let follow = [args, vec!["--follow".to_string()]].concat();
+        assert!(crate::config::parse(follow).is_err());
+    }
+
+    #[test]
+    fn sqlite() {
+        let args = [
+            "program_name",
+            "--output-format",
+            "sqlite",
+            "--table",
+            "events",
+            "-o",
+            "merged.sqlite",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(crate::transform::OutputFormat::Sqlite, parsed.output_format);
+        assert_eq!("events", parsed.table);
+        let stdout = [
+            "program_name",
+            "--output-format",
+            "sqlite",
+            "-o",
+            "-",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        assert!(crate::config::parse(stdout).is_err());
+    }
+
+    #[test]
+    fn es_bulk() {
+        let args = [
+            "program_name",
+            "--output-format",
+            "es-bulk",
+            "--index",
+            "logs-%Y.%m.%d",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(crate::transform::OutputFormat::EsBulk, parsed.output_format);
+        assert_eq!(
+            Some(crate::transform::Index::parse("logs-%Y.%m.%d").unwrap()),
+            parsed.index
+        );
+    }
+
+    #[test]
+    fn sink() {
+        let args = [
+            "program_name",
+            "--sink",
+            "loki=http://localhost:3100",
+            "--loki-labels",
+            "service,level",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(
+            vec![crate::output::Remote::Loki(
+                "http://localhost:3100".to_string()
+            )],
+            parsed.sink
+        );
+        assert_eq!(
+            vec!["service".to_string(), "level".to_string()],
+            parsed.loki_labels
+        );
+    }
+
+    #[test]
+    fn gelf() {
+        let args = [
+            "program_name",
+            "--sink",
+            "gelf=udp://localhost:12201",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args.clone()).unwrap();
+        assert_eq!(crate::transform::OutputFormat::Gelf, parsed.output_format);
+        let logfmt = [
+            args,
+            vec!["--output-format".to_string(), "logfmt".to_string()],
+        ]
+        .concat();
+        assert!(crate::config::parse(logfmt).is_err());
+    }
+
+    #[test]
+    fn s3_output() {
+        let args = ["program_name", "-o", "s3://bucket/merged.json.zst", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args.clone()).is_ok());
+        let split = [
+            args.clone(),
+            vec!["--split-size".to_string(), "1G".to_string()],
+        ]
+        .concat();
+        assert!(crate::config::parse(split).is_err());
+        let sqlite = [
+            args,
+            vec!["--output-format".to_string(), "sqlite".to_string()],
+        ]
+        .concat();
+        assert!(crate::config::parse(sqlite).is_err());
+    }
+
+    #[test]
+    fn columns() {
+        let args = [
+            "program_name",
+            "--output-format",
+            "csv",
+            "--columns",
+            "ts,level,msg",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            Some(vec![
+                "ts".to_string(),
+                "level".to_string(),
+                "msg".to_string()
+            ]),
+            crate::config::parse(args).unwrap().columns
+        );
+    }
+
+    #[test]
+    fn template() {
+        let args = ["program_name", "--template", "{ts} {msg}", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::transform::Template::parse("{ts} {msg}").unwrap()),
+            crate::config::parse(args).unwrap().template
+        );
+    }
+
+    #[test]
+    fn color() {
+        let args = ["program_name", "--color", "always", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            crate::transform::ColorChoice::Always,
+            crate::config::parse(args).unwrap().color
+        );
+    }
+
+    #[test]
+    fn select() {
+        let args = ["program_name", "--select", "ts,level,k8s.pod", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(vec![
+                "ts".to_string(),
+                "level".to_string(),
+                "k8s.pod".to_string()
+            ]),
+            crate::config::parse(args).unwrap().select
+        );
+    }
+
+    #[test]
+    fn drop() {
+        let args = [
+            "program_name",
+            "--drop",
+            "stacktrace",
+            "--drop",
+            "kubernetes.labels",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            vec!["stacktrace".to_string(), "kubernetes.labels".to_string()],
+            crate::config::parse(args).unwrap().drop
+        );
+    }
+
+    #[test]
+    fn flatten() {
+        let args = ["program_name", "--flatten", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(".".to_string()),
+            crate::config::parse(args).unwrap().flatten
+        );
+        let args = ["program_name", "--flatten=_", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some("_".to_string()),
+            crate::config::parse(args).unwrap().flatten
+        );
+    }
+
+    #[test]
+    fn add_field() {
+        let args = [
+            "program_name",
+            "--add-field",
+            "env=prod",
+            "--add-field",
+            "query=a=b",
+            "--add-field-for",
+            "app-1.json.gz=cluster=eu-1",
+            "app-1.json.gz",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("query".to_string(), "a=b".to_string())
+            ],
+            parsed.add_fields
+        );
+        assert_eq!(
+            Some(&vec![("cluster".to_string(), "eu-1".to_string())]),
+            parsed.add_fields_for.get("app-1.json.gz")
+        );
+    }
+
+    #[test]
+    fn add_meta() {
+        let args = ["program_name", "--add-host", "--add-meta=_merge", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some("host".to_string()), parsed.add_host);
+        assert_eq!(Some("_merge".to_string()), parsed.add_meta);
+    }
+
+    #[test]
+    fn redact() {
+        let args = [
+            "program_name",
+            "--redact",
+            "password",
+            "--redact-pattern",
+            "Bearer \\S+",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(vec!["password".to_string()], parsed.redact);
+        assert_eq!("Bearer \\S+", parsed.redact_patterns[0].as_str());
+    }
+
+    #[test]
+    fn hash_field() {
+        let args = [
+            "program_name",
+            "--hash-field",
+            "user_id",
+            "--hash-field",
+            "user.email",
+            "--hash-salt",
+            "secret",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(
+            vec!["user_id".to_string(), "user.email".to_string()],
+            parsed.hash_fields
+        );
+        assert_eq!(Some("secret".to_string()), parsed.hash_salt);
+    }
+
+    #[test]
+    fn max_field_len() {
+        let args = ["program_name", "--max-field-len", "4096", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(4096),
+            crate::config::parse(args).unwrap().max_field_len
+        );
+    }
+
+    #[test]
+    fn array() {
+        let args = ["program_name", "--array", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().array);
+    }
+
+    #[test]
+    fn print0() {
+        let args = ["program_name", "--print0", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().print0);
+    }
+
+    #[test]
+    fn assume_tz() {
+        let args = ["program_name", "--assume-tz", "+02:00", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(
+            Some(crate::timestamp::Zone::Fixed(
+                chrono::FixedOffset::east_opt(7200).unwrap()
+            )),
+            parsed.zone
+        );
+    }
+
+    #[test]
+    fn units() {
+        let args = ["program_name", "--unit", "s", "--unit-for", "t=us", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(crate::timestamp::Unit::Seconds, parsed.unit);
+        assert_eq!(Some(&crate::timestamp::Unit::Micros), parsed.units.get("t"));
+    }
+
+    #[test]
+    fn key_priority() {
+        let args = ["program_name", "--key-priority", "@timestamp,time", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(vec!["@timestamp", "time"], parsed.key_priority);
+    }
+
+    #[test]
+    fn keys_for() {
+        let args = [
+            "program_name",
+            "--keys-for",
+            "app.json=ts",
+            "--keys-for",
+            "app.json=time",
+            "--keys-for",
+            "nginx.json.gz=time_iso",
+            "app.json",
+            "nginx.json.gz",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(
+            Some(&std::collections::HashSet::from([
+                "ts".to_string(),
+                "time".to_string()
+            ])),
+            parsed.keys_for.get("app.json")
+        );
+        assert_eq!(1, parsed.keys_for["nginx.json.gz"].len());
+    }
+
+    #[test]
+    fn auto_key() {
+        let args = ["program_name", "--auto-key", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some(100), parsed.auto_key);
+        assert_eq!(vec!["1.log"], parsed.paths);
+        let args = ["program_name", "--auto-key=5", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(Some(5), crate::config::parse(args).unwrap().auto_key);
+    }
+
+    #[test]
+    fn year() {
+        let args = ["program_name", "--assume-year", "2023", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(Some(2023), crate::config::parse(args).unwrap().year);
+    }
+
+    #[test]
+    fn compose_ts() {
+        let args = ["program_name", "--compose-ts", "date+time", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(vec!["date", "time"], parsed.compose);
+    }
+
+    #[test]
+    fn ts_regex() {
+        let args = [
+            "program_name",
+            "--ts-regex",
+            "field=msg;re=^(\\S+)",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let ts_regex = crate::config::parse(args).unwrap().ts_regex.unwrap();
+        assert_eq!("msg", ts_regex.field);
+        assert_eq!("^(\\S+)", ts_regex.re.as_str());
+    }
+
+    #[test]
+    fn annotated_keys() {
+        let args = [
+            "program_name",
+            "-k",
+            "ts:us",
+            "-k",
+            "time_s:s",
+            "-k",
+            "k8s:time",
+            "--unit-for",
+            "time_s=ms",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(vec!["ts", "time_s", "k8s:time"], parsed.keys);
+        assert_eq!(
+            Some(&crate::timestamp::Unit::Micros),
+            parsed.units.get("ts")
+        );
+        assert_eq!(
+            Some(&crate::timestamp::Unit::Millis),
+            parsed.units.get("time_s")
+        );
+    }
+
+    #[test]
+    fn locale() {
+        let args = ["program_name", "--locale", "ru", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::locale::Locale::Ru),
+            crate::config::parse(args).unwrap().locale
+        );
+    }
+
+    #[test]
+    fn offsets() {
+        let args = [
+            "program_name",
+            "--offset",
+            "a.json=+2.5s",
+            "--offset",
+            "b.json=-1m",
+            "a.json",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some(&2_500_000_000), parsed.offsets.get("a.json"));
+        assert_eq!(Some(&-60_000_000_000), parsed.offsets.get("b.json"));
+    }
+
+    #[test]
+    fn auto_skew() {
+        let args = ["program_name", "--auto-skew", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert_eq!(Some(60_000_000_000), parsed.auto_skew);
+        assert_eq!(vec!["1.log"], parsed.paths);
+        let args = ["program_name", "--auto-skew=5m", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(300_000_000_000),
+            crate::config::parse(args).unwrap().auto_skew
+        );
+    }
+
+    #[test]
+    fn humanize_ts() {
+        let args = [
+            "program_name",
+            "--humanize-ts",
+            "--assume-tz",
+            "+02:00",
+            "1.log",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::timestamp::Zone::parse("+02:00").unwrap()),
+            crate::config::parse(args).unwrap().humanize_ts
+        );
+        let args = ["program_name", "--humanize-ts=UTC", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::timestamp::Zone::Utc),
+            crate::config::parse(args).unwrap().humanize_ts
+        );
+    }
+
+    #[test]
+    fn monotonic() {
+        let args = ["program_name", "--monotonic", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().monotonic);
+        let args = ["program_name", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let parsed = crate::config::parse(args).unwrap();
+        assert!(!parsed.monotonic);
+        assert!(!parsed.inherit_ts);
+    }
+
+    #[test]
+    fn inherit_ts() {
+        let args = ["program_name", "--inherit-ts", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert!(crate::config::parse(args).unwrap().inherit_ts);
+    }
+
+    #[test]
+    fn fallback() {
+        let args = ["program_name", "--fallback", "mtime", "1.log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            Some(crate::timestamp::Fallback::Mtime),
+            crate::config::parse(args).unwrap().fallback
+        );
     }
 }
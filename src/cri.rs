@@ -0,0 +1,151 @@
+use crate::timestamp;
+
+///
+/// Parts of the long lines which are not finished yet, runtimes split the lines longer than 16 KiB
+/// into `P` lines followed by the `F` line with the rest of the payload.
+/// The parts are buffered for every stream until its `F` line,
+/// the joined line takes the time of the first part.
+#[derive(Default)]
+pub struct Partials {
+    /// stream, time of the first part and the payload so far
+    pending: Vec<(String, String, String)>,
+}
+
+impl Partials {
+    ///
+    /// Takes the next line of the input, returns the line to parse once it's complete
+    pub fn push(&mut self, raw_line: String) -> Option<String> {
+        let mut fields = raw_line.splitn(4, ' ');
+        let (Some(time), Some(stream), Some(tag)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Some(raw_line);
+        };
+        let payload = fields.next().unwrap_or_default();
+        let pending = self.pending.iter().position(|(s, _, _)| s == stream);
+        match (tag, pending) {
+            ("P", Some(at)) => {
+                self.pending[at].2.push_str(payload);
+                None
+            }
+            ("P", None) => {
+                let part = (stream.to_string(), time.to_string(), payload.to_string());
+                self.pending.push(part);
+                None
+            }
+            (_, Some(at)) => {
+                let (stream, time, mut joined) = self.pending.remove(at);
+                joined.push_str(payload);
+                Some(format!("{} {} F {}", time, stream, joined))
+            }
+            (_, None) => Some(raw_line),
+        }
+    }
+
+    ///
+    /// Parts of the lines the input has ended in the middle of, each of them joined as far as it goes
+    pub fn drain(&mut self) -> impl Iterator<Item = String> + '_ {
+        self.pending
+            .drain(..)
+            .map(|(stream, time, joined)| format!("{} {} P {}", time, stream, joined))
+    }
+}
+
+///
+/// Lines of CRI input with the parts of the long lines joined, the lines of the other formats go through as they are
+pub struct Join<I> {
+    lines: I,
+    /// there is nothing to join unless the input is CRI
+    partials: Option<Partials>,
+    /// parts left at the end of the input
+    rest: std::collections::VecDeque<String>,
+}
+
+impl<I> Join<I> {
+    pub fn new(lines: I, cri: bool) -> Self {
+        Join {
+            lines,
+            partials: cri.then(Partials::default),
+            rest: std::collections::VecDeque::new(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = std::io::Result<String>>> Iterator for Join<I> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(partials) = self.partials.as_mut() else {
+            return self.lines.next();
+        };
+        loop {
+            match self.lines.next() {
+                Some(Ok(raw_line)) => {
+                    if let Some(line) = partials.push(raw_line) {
+                        return Some(Ok(line));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.rest.extend(partials.drain());
+                    return self.rest.pop_front().map(Ok);
+                }
+            }
+        }
+    }
+}
+
+///
+/// Splits the line written by CRI runtimes like containerd, which looks like
+/// `<RFC3339 time> <stream> <tag> <payload>`, the tag is `F` for full lines
+/// and `P` for the parts of a long line, which are joined by `Partials` beforehand.
+///
+/// returns: timestamp and the payload to emit
+pub fn parse(raw_line: &str) -> Result<(timestamp::Timestamp, String), serde_json::Error> {
+    let mut fields = raw_line.splitn(4, ' ');
+    let (Some(time), Some(_stream), Some(_tag)) = (fields.next(), fields.next(), fields.next())
+    else {
+        return Err(serde::de::Error::custom("line has no CRI prefix"));
+    };
+    let dt = chrono::DateTime::parse_from_rfc3339(time).map_err(serde::de::Error::custom)?;
+    let ts = timestamp::from_datetime(&dt);
+    Ok((ts, fields.next().unwrap_or_default().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse() {
+        let (ts, line) =
+            crate::cri::parse(r#"2024-01-02T03:04:05.6Z stdout F {"msg":"a b"}"#).unwrap();
+        assert_eq!(1704164645600000000, ts);
+        assert_eq!(r#"{"msg":"a b"}"#, line);
+        assert!(crate::cri::parse(r#"{"msg":"a b"}"#).is_err());
+        assert!(crate::cri::parse("stdout F {}").is_err());
+    }
+
+    #[test]
+    fn join() {
+        let lines = [
+            r#"2024-01-02T03:04:05.1Z stdout P {"msg":"#,
+            r#"2024-01-02T03:04:05.2Z stderr F {"err":1}"#,
+            r#"2024-01-02T03:04:05.3Z stdout P "a "#,
+            r#"2024-01-02T03:04:05.4Z stdout F b"}"#,
+            r#"2024-01-02T03:04:05.5Z stdout P {"cut"#,
+        ];
+        let joined: Vec<String> =
+            crate::cri::Join::new(lines.iter().map(|line| Ok(line.to_string())), true)
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(
+            vec![
+                r#"2024-01-02T03:04:05.2Z stderr F {"err":1}"#,
+                r#"2024-01-02T03:04:05.1Z stdout F {"msg":"a b"}"#,
+                r#"2024-01-02T03:04:05.5Z stdout P {"cut"#,
+            ],
+            joined
+        );
+        let (ts, line) = crate::cri::parse(&joined[1]).unwrap();
+        assert_eq!(1704164645100000000, ts);
+        assert_eq!(r#"{"msg":"a b"}"#, line);
+    }
+}
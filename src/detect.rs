@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::timestamp::{self, Zone};
+
+///
+/// Magnitudes of epoch timestamps of years 2001 to 2286 in seconds, milliseconds, microseconds and nanoseconds
+const EPOCH_RANGES: [(f64, f64); 4] = [(1e9, 1e10), (1e12, 1e13), (1e15, 1e16), (1e18, 1e19)];
+
+///
+/// Value of the field as a comparable number, if it looks like a timestamp:
+/// a number of epoch magnitude, quoted or not, or a datetime
+fn timestamp_like(value: &serde_json::Value) -> Option<f64> {
+    let epoch = |number: f64| {
+        EPOCH_RANGES
+            .iter()
+            .any(|(lo, hi)| (*lo..*hi).contains(&number))
+            .then_some(number)
+    };
+    match value {
+        serde_json::Value::Number(number) => number.as_f64().and_then(epoch),
+        serde_json::Value::String(s) => match s.parse::<f64>() {
+            Ok(number) => epoch(number),
+            // the zone doesn't matter, only the order of the values is compared
+            Err(_) => timestamp::Calendar {
+                zone: Some(Zone::Utc),
+                ..Default::default()
+            }
+            .parse(s)
+            .ok()
+            .map(|ts| ts as f64),
+        },
+        _ => None,
+    }
+}
+
+///
+/// How a field behaved across the sampled records
+struct Candidate {
+    /// the order the field was first seen in, the earlier one wins a tie
+    order: usize,
+    /// records where the field looks like a timestamp which isn't smaller than the previous one
+    hits: usize,
+    last: f64,
+    /// the field is disqualified as soon as it goes back in time or doesn't look like a timestamp
+    valid: bool,
+}
+
+///
+/// Picks the field which looks like a monotonic timestamp in all the records it's present in.
+/// Fields present in more records are preferred.
+///
+/// # Arguments
+///
+/// * `lines`: the first records of the input, lines which are not JSON objects are ignored
+///
+/// returns: the name of the field, if any of them looks like a timestamp
+pub fn key_of<'a>(lines: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+    for line in lines {
+        let record = match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line)
+        {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+        for (key, value) in record {
+            let order = candidates.len();
+            let candidate = candidates.entry(key).or_insert(Candidate {
+                order,
+                hits: 0,
+                last: f64::MIN,
+                valid: true,
+            });
+            match timestamp_like(&value) {
+                Some(ts) if candidate.valid && ts >= candidate.last => {
+                    candidate.hits += 1;
+                    candidate.last = ts;
+                }
+                _ => candidate.valid = false,
+            }
+        }
+    }
+    candidates
+        .into_iter()
+        .filter(|(_, candidate)| candidate.valid)
+        .max_by(|(_, a), (_, b)| a.hits.cmp(&b.hits).then(b.order.cmp(&a.order)))
+        .map(|(key, _)| key)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::detect::key_of;
+
+    #[test]
+    fn epoch() {
+        let lines = [
+            r#"{"id":5, "ts":1713212345123, "level":"info"}"#,
+            r#"{"id":3, "ts":1713212345124, "level":"warn"}"#,
+        ];
+        assert_eq!(Some("ts".to_string()), key_of(lines.into_iter()));
+    }
+
+    #[test]
+    fn datetime() {
+        let lines = [
+            r#"{"msg":"2024-01-02", "time":"2024-01-02T03:04:05Z"}"#,
+            "not json",
+            r#"{"msg":"a", "time":"2024-01-02T03:04:06Z"}"#,
+        ];
+        assert_eq!(Some("time".to_string()), key_of(lines.into_iter()));
+    }
+
+    #[test]
+    fn not_monotonic() {
+        let lines = [
+            r#"{"started":1713212345, "ts":1713212345000}"#,
+            r#"{"started":1713212340, "ts":1713212346000}"#,
+        ];
+        assert_eq!(Some("ts".to_string()), key_of(lines.into_iter()));
+        assert_eq!(None, key_of([r#"{"id":15}"#].into_iter()));
+    }
+}
@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::timestamp;
+
+///
+/// Line of the `json-file` logging driver of Docker
+#[derive(Deserialize)]
+struct Line<'a> {
+    #[serde(borrow)]
+    log: Cow<'a, str>,
+    time: &'a str,
+}
+
+///
+/// Extracts the time of the line.
+///
+/// # Arguments
+///
+/// * `raw_line`: line written by the `json-file` logging driver
+/// * `unwrap`: whether the payload of `log` field is emitted instead of the whole line
+/// * `check`: checks the payload, e.g. against `--where` filter, whichever line is emitted
+///
+/// returns: timestamp and the line to emit
+pub fn parse(
+    raw_line: String,
+    unwrap: bool,
+    check: impl FnOnce(&str) -> Result<(), serde_json::Error>,
+) -> Result<(timestamp::Timestamp, String), serde_json::Error> {
+    let line: Line = serde_json::from_str(&raw_line)?;
+    let dt = chrono::DateTime::parse_from_rfc3339(line.time).map_err(serde::de::Error::custom)?;
+    let ts = timestamp::from_datetime(&dt);
+    // the payload keeps the newline the application has written
+    let payload = line.log.trim_end_matches(['\r', '\n']);
+    check(payload)?;
+    if unwrap {
+        return Ok((ts, payload.to_string()));
+    }
+    Ok((ts, raw_line))
+}
+
+#[cfg(test)]
+mod tests {
+    const LINE: &str = r#"{"log":"{\"level\":\"info\"}\n","stream":"stdout","time":"2024-01-02T03:04:05.678901234Z"}"#;
+
+    #[test]
+    fn parse() {
+        let (ts, line) = crate::docker::parse(LINE.to_string(), false, |_| Ok(())).unwrap();
+        assert_eq!(1704164645678901234, ts);
+        assert_eq!(LINE, line);
+    }
+
+    #[test]
+    fn parse_unwrap() {
+        let (ts, line) = crate::docker::parse(LINE.to_string(), true, |_| Ok(())).unwrap();
+        assert_eq!(1704164645678901234, ts);
+        assert_eq!(r#"{"level":"info"}"#, line);
+    }
+}
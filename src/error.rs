@@ -35,6 +35,64 @@ impl From<std::io::Error> for MrgError {
     }
 }
 
+impl From<zip::result::ZipError> for MrgError {
+    fn from(value: zip::result::ZipError) -> Self {
+        MrgError {
+            msg: format!("cannot read zip archive: {}", value),
+        }
+    }
+}
+
+impl From<glob::PatternError> for MrgError {
+    fn from(value: glob::PatternError) -> Self {
+        MrgError {
+            msg: format!("cannot parse glob pattern: {}", value),
+        }
+    }
+}
+
+impl From<ureq::Error> for MrgError {
+    fn from(value: ureq::Error) -> Self {
+        MrgError {
+            msg: format!("cannot perform HTTP request: {}", value),
+        }
+    }
+}
+
+impl From<notify::Error> for MrgError {
+    fn from(value: notify::Error) -> Self {
+        MrgError {
+            msg: format!("cannot watch directory: {}", value),
+        }
+    }
+}
+
+impl From<regex::Error> for MrgError {
+    fn from(value: regex::Error) -> Self {
+        MrgError {
+            msg: format!("cannot parse regular expression: {}", value),
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<rdkafka::error::KafkaError> for MrgError {
+    fn from(value: rdkafka::error::KafkaError) -> Self {
+        MrgError {
+            msg: format!("Kafka error: {}", value),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for MrgError {
+    fn from(value: rusqlite::Error) -> Self {
+        MrgError {
+            msg: format!("cannot write SQLite database: {}", value),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -67,4 +125,76 @@ mod tests {
             msg
         );
     }
+
+    #[test]
+    fn mrg_error_from_zip_error() {
+        let src = zip::result::ZipError::FileNotFound;
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert_eq!(
+            "cannot read zip archive: specified file not found in archive",
+            msg
+        );
+    }
+
+    #[test]
+    fn mrg_error_from_glob_error() {
+        let src = glob::Pattern::new("[").unwrap_err();
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert_eq!(
+            "cannot parse glob pattern: Pattern syntax error near position 0: invalid range pattern",
+            msg
+        );
+    }
+
+    #[test]
+    fn mrg_error_from_ureq_error() {
+        let response = ureq::Response::new(404, "Not Found", "").unwrap();
+        let src = ureq::Error::Status(404, response);
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert_eq!(
+            "cannot perform HTTP request: https://example.com/: status code 404",
+            msg
+        );
+    }
+
+    #[test]
+    fn mrg_error_from_notify_error() {
+        let src = notify::Error::path_not_found();
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert_eq!("cannot watch directory: No path was found.", msg);
+    }
+
+    #[test]
+    fn mrg_error_from_regex_error() {
+        let pattern = String::from("(");
+        let src = regex::Regex::new(&pattern).unwrap_err();
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert!(msg.starts_with("cannot parse regular expression: regex parse error"));
+    }
+
+    #[cfg(feature = "kafka")]
+    #[test]
+    fn mrg_error_from_kafka_error() {
+        let src = rdkafka::error::KafkaError::NoMessageReceived;
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert_eq!(
+            "Kafka error: No message received within the given poll interval",
+            msg
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn mrg_error_from_rusqlite_error() {
+        let src = rusqlite::Error::InvalidQuery;
+        let mrg_error = crate::error::MrgError::from(src);
+        let msg = format!("{}", mrg_error);
+        assert_eq!("cannot write SQLite database: Query is not read-only", msg);
+    }
 }
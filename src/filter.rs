@@ -0,0 +1,318 @@
+use crate::error;
+
+///
+/// How the value of the field is compared to the one of the condition
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Operator {
+    /// Operators the way they are spelled, the longer ones go first so that `>=` is not taken for `>`
+    const ALL: [(&'static str, Operator); 6] = [
+        ("!=", Operator::Ne),
+        (">=", Operator::Ge),
+        ("<=", Operator::Le),
+        ("=", Operator::Eq),
+        (">", Operator::Gt),
+        ("<", Operator::Lt),
+    ];
+}
+
+///
+/// Condition on a field of the records, e.g. `level=error` or `duration_ms>500`. Names of nested fields are joined with dots
+/// like `k8s.pod`, unless the record has the field with the dots in its name.
+/// The records without the field don't meet the condition, whatever the operator is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    field: String,
+    operator: Operator,
+    value: String,
+    /// the value as a number, the fields are compared with it by `<`, `<=`, `>` and `>=`
+    number: Option<f64>,
+}
+
+impl Condition {
+    pub fn parse(spec: &str) -> Result<Self, error::MrgError> {
+        let invalid = || {
+            error::MrgError {
+            msg: format!(
+                "'{}' is not a valid condition, expected field=value, field!=value or a comparison like field>500",
+                spec
+            ),
+        }
+        };
+        let at = spec.find(['=', '!', '<', '>']).ok_or_else(invalid)?;
+        let (field, rest) = spec.split_at(at);
+        let (operator, value) = Operator::ALL
+            .iter()
+            .find_map(|(token, operator)| Some((*operator, rest.strip_prefix(token)?)))
+            .ok_or_else(invalid)?;
+        if field.is_empty() {
+            return Err(invalid());
+        }
+        let number = value.parse::<f64>().ok();
+        if number.is_none() && !matches!(operator, Operator::Eq | Operator::Ne) {
+            return Err(error::MrgError {
+                msg: format!(
+                    "'{}' compares '{}' with '{}', which is not a number",
+                    spec, field, value
+                ),
+            });
+        }
+        Ok(Condition {
+            field: field.to_string(),
+            operator,
+            value: value.to_string(),
+            number,
+        })
+    }
+
+    ///
+    /// The value of the field out of the value of the top-level field `key` of the record, if it's there
+    fn value_of<'v>(
+        &self,
+        key: &str,
+        value: &'v serde_json::Value,
+    ) -> Option<&'v serde_json::Value> {
+        if self.field == key {
+            return Some(value);
+        }
+        let rest = self.field.strip_prefix(key)?.strip_prefix('.')?;
+        rest.split('.')
+            .try_fold(value, |value, name| value.as_object()?.get(name))
+    }
+
+    ///
+    /// Whether the value of the field meets the condition, only numbers are ordered
+    fn meets(&self, value: &serde_json::Value) -> bool {
+        let ordered = |order: fn(f64, f64) -> bool| {
+            value
+                .as_f64()
+                .zip(self.number)
+                .is_some_and(|(value, number)| order(value, number))
+        };
+        match self.operator {
+            Operator::Eq => self.matches(value),
+            Operator::Ne => !self.matches(value),
+            Operator::Lt => ordered(|value, number| value < number),
+            Operator::Le => ordered(|value, number| value <= number),
+            Operator::Gt => ordered(|value, number| value > number),
+            Operator::Ge => ordered(|value, number| value >= number),
+        }
+    }
+
+    ///
+    /// Strings match the value as they are, numbers match it as numbers, e.g. `status=500` matches `500.0`,
+    /// booleans and nulls match `true`, `false` and `null`, objects and arrays match nothing
+    fn matches(&self, value: &serde_json::Value) -> bool {
+        match value {
+            serde_json::Value::String(s) => *s == self.value,
+            serde_json::Value::Number(n) => {
+                n.to_string() == self.value
+                    || n.as_f64()
+                        .zip(self.number)
+                        .is_some_and(|(n, value)| n == value)
+            }
+            serde_json::Value::Bool(b) => b.to_string() == self.value,
+            serde_json::Value::Null => self.value == "null",
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => false,
+        }
+    }
+}
+
+///
+/// Whether the record meets the condition, the field with the dots in its name wins over the nested one,
+/// so the condition is evaluated on a single value of the record
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Met {
+    /// the record has no such field
+    #[default]
+    Missing,
+    Nested(bool),
+    Literal(bool),
+}
+
+impl Met {
+    fn is_met(self) -> bool {
+        matches!(self, Met::Nested(true) | Met::Literal(true))
+    }
+}
+
+///
+/// Conditions the records have to meet to be merged, all of them or any of them.
+/// They are checked while the timestamps are parsed, so the records are not parsed again.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub conditions: Vec<Condition>,
+    /// a single condition is enough
+    pub any: bool,
+}
+
+impl Filter {
+    ///
+    /// Whether any of the conditions is on the top-level field `key` or on the fields nested in it
+    pub fn wants(&self, key: &str) -> bool {
+        self.conditions.iter().any(|condition| {
+            condition.field == key
+                || condition
+                    .field
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    ///
+    /// Marks whether the conditions on the top-level field `key` or on the fields nested in it are met
+    pub fn check(&self, key: &str, value: &serde_json::Value, met: &mut [Met]) {
+        for (condition, met) in self.conditions.iter().zip(met.iter_mut()) {
+            if condition.field == key {
+                *met = Met::Literal(condition.meets(value));
+            } else if let Some(value) = condition.value_of(key, value) {
+                if !matches!(met, Met::Literal(_)) {
+                    *met = Met::Nested(condition.meets(value));
+                }
+            }
+        }
+    }
+
+    ///
+    /// Whether the whole record passes, the lines which are not JSON objects are not filtered
+    pub fn admits(&self, line: &str) -> bool {
+        let Ok(record) = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line)
+        else {
+            return true;
+        };
+        let mut met = vec![Met::Missing; self.conditions.len()];
+        for (key, value) in record.iter().filter(|(key, _)| self.wants(key)) {
+            self.check(key, value, &mut met);
+        }
+        self.passes(&met)
+    }
+
+    ///
+    /// Whether the record which meets the marked conditions passes
+    pub fn passes(&self, met: &[Met]) -> bool {
+        match self.any {
+            true => met.iter().any(|met| met.is_met()),
+            false => met.iter().all(|met| met.is_met()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Condition, Filter, Met};
+
+    #[test]
+    fn admits() {
+        let filter = Filter {
+            conditions: vec![Condition::parse("level=error").unwrap()],
+            any: false,
+        };
+        assert!(filter.admits(r#"{"level":"error","msg":"failed"}"#));
+        assert!(!filter.admits(r#"{"level":"info","msg":"started"}"#));
+        assert!(filter.admits("not json"));
+    }
+
+    fn met(filter: &Filter, record: &str) -> Vec<bool> {
+        let record: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(record).unwrap();
+        let mut met = vec![Met::Missing; filter.conditions.len()];
+        for (key, value) in &record {
+            if filter.wants(key) {
+                filter.check(key, value, &mut met);
+            }
+        }
+        met.into_iter().map(Met::is_met).collect()
+    }
+
+    #[test]
+    fn filter() {
+        let mut filter = Filter {
+            conditions: vec![
+                Condition::parse("level=error").unwrap(),
+                Condition::parse("k8s.pod=api-1").unwrap(),
+                Condition::parse("status=500").unwrap(),
+            ],
+            any: false,
+        };
+        assert_eq!(
+            vec![true, true, true],
+            met(
+                &filter,
+                r#"{"level":"error","k8s":{"pod":"api-1"},"status":500.0}"#
+            )
+        );
+        assert_eq!(
+            vec![false, true, false],
+            met(&filter, r#"{"level":"info","k8s.pod":"api-1"}"#)
+        );
+        let (yes, no) = (Met::Literal(true), Met::Nested(false));
+        assert!(filter.passes(&[yes, yes, yes]));
+        assert!(!filter.passes(&[no, yes, Met::Missing]));
+        filter.any = true;
+        assert!(filter.passes(&[no, yes, Met::Missing]));
+        assert!(!filter.passes(&[no, no, Met::Missing]));
+        assert!(!filter.wants("k8"));
+        assert!(Condition::parse("=error").is_err());
+        assert!(Condition::parse("level").is_err());
+    }
+
+    #[test]
+    fn comparisons() {
+        let filter = Filter {
+            conditions: vec![
+                Condition::parse("duration_ms>500").unwrap(),
+                Condition::parse("duration_ms<=1e3").unwrap(),
+                Condition::parse("status>=500").unwrap(),
+                Condition::parse("status<500").unwrap(),
+                Condition::parse("level!=info").unwrap(),
+            ],
+            any: false,
+        };
+        assert_eq!(
+            vec![true, true, true, false, true],
+            met(
+                &filter,
+                r#"{"duration_ms":1000,"status":500,"level":"warn"}"#
+            )
+        );
+        assert_eq!(
+            vec![false, true, false, false, false],
+            met(
+                &filter,
+                r#"{"duration_ms":500,"status":"503","level":"info"}"#
+            )
+        );
+        assert_eq!(
+            vec![false, false, false, false, false],
+            met(&filter, r#"{"t":1}"#)
+        );
+        let pod = Filter {
+            conditions: vec![Condition::parse("k8s.pod!=a").unwrap()],
+            any: false,
+        };
+        assert_eq!(
+            vec![false],
+            met(&pod, r#"{"k8s.pod":"a","k8s":{"pod":"b"}}"#)
+        );
+        assert_eq!(
+            vec![false],
+            met(&pod, r#"{"k8s":{"pod":"b"},"k8s.pod":"a"}"#)
+        );
+        assert_eq!(
+            vec![true],
+            met(&pod, r#"{"k8s":{"pod":"a"},"k8s.pod":"b"}"#)
+        );
+        assert!(Condition::parse("duration_ms>slow").is_err());
+        assert!(Condition::parse("level!error").is_err());
+        assert!(Condition::parse(">500").is_err());
+        assert_eq!(Condition::parse("url=/a?b=c").unwrap().value, "/a?b=c");
+    }
+}
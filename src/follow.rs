@@ -0,0 +1,326 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{BufRead, BufReader};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use notify::Watcher;
+
+use crate::error;
+use crate::input::{Codec, Format, Input, Stream, Walk};
+use crate::output::Sink;
+use crate::timestamp::{self, Timestamp};
+use crate::transform;
+
+/// How often sources which reached their end are checked for new data
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+///
+/// Record along with the path of its input
+type Record = (Timestamp, Arc<str>, String);
+
+///
+/// Record held back, the sequence number follows the timestamp
+type Pending = (Timestamp, u64, Arc<str>, String);
+
+///
+/// Holds records back until it's unlikely that a record with a smaller timestamp shows up.
+/// A record is released when a record newer by more than `window` arrives.
+pub struct Reorder {
+    window: Timestamp,
+    newest: Option<Timestamp>,
+    /// the newest record emitted
+    released: Option<Timestamp>,
+    /// sequence number keeps records with equal timestamps in the order of arrival
+    seq: u64,
+    pending: BinaryHeap<Reverse<Pending>>,
+}
+
+impl Reorder {
+    pub fn new(window: Timestamp) -> Self {
+        Reorder {
+            window,
+            newest: None,
+            released: None,
+            seq: 0,
+            pending: BinaryHeap::new(),
+        }
+    }
+
+    pub fn push(&mut self, ts: Timestamp, path: Arc<str>, raw_line: String) {
+        self.newest = Some(self.newest.map_or(ts, |newest| newest.max(ts)));
+        self.pending.push(Reverse((ts, self.seq, path, raw_line)));
+        self.seq += 1;
+    }
+
+    ///
+    /// Timestamp of the newest record emitted, if any
+    pub fn released(&self) -> Option<Timestamp> {
+        self.released
+    }
+
+    ///
+    /// Records which are old enough to be emitted, in the order of their timestamps
+    pub fn ready(&mut self) -> Vec<Record> {
+        let watermark = match self.newest {
+            Some(newest) => newest.saturating_sub(self.window),
+            None => return Vec::new(),
+        };
+        let mut ready: Vec<Record> = Vec::new();
+        while self
+            .pending
+            .peek()
+            .is_some_and(|Reverse((ts, _, _, _))| *ts <= watermark)
+        {
+            let Reverse((ts, _, path, raw_line)) = self.pending.pop().unwrap();
+            self.released = Some(ts);
+            ready.push((ts, path, raw_line));
+        }
+        ready
+    }
+
+    ///
+    /// All the records held back, used when inputs are idle
+    pub fn drain(&mut self) -> Vec<Record> {
+        let mut all: Vec<Record> = Vec::with_capacity(self.pending.len());
+        while let Some(Reverse((ts, _, path, raw_line))) = self.pending.pop() {
+            self.released = Some(ts);
+            all.push((ts, path, raw_line));
+        }
+        all
+    }
+}
+
+///
+/// Reads complete lines of the input forever, sending them to the merging thread.
+/// When the end of the input is reached, it waits for new data to be appended.
+fn tail(mut input: Input<BufReader<Stream>>, sender: mpsc::Sender<(Arc<str>, Format, String)>) {
+    let mut line = String::new();
+    let mut partials = crate::cri::Partials::default();
+    loop {
+        match input.reader.read_line(&mut line) {
+            Ok(0) => std::thread::sleep(POLL_INTERVAL),
+            // the writer might not have finished the line yet, the rest of it comes later
+            Ok(_) if !line.ends_with('\n') => std::thread::sleep(POLL_INTERVAL),
+            Ok(_) => {
+                let raw_line = line.trim_end_matches(['\r', '\n']).to_string();
+                line.clear();
+                let raw_line = match input.format {
+                    Format::Cri => match partials.push(raw_line) {
+                        Some(raw_line) => raw_line,
+                        // the rest of the long line comes later
+                        None => continue,
+                    },
+                    _ => raw_line,
+                };
+                if sender
+                    .send((input.path.clone(), input.format, raw_line))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Err(e) => {
+                eprintln!("cannot get next line: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+///
+/// Directories whose new files are attached to the merge while it's running
+pub struct Watch<'a> {
+    pub dirs: Vec<String>,
+    /// only the files accepted by the filters are attached
+    pub walk: &'a Walk,
+    /// format of the attached files
+    pub format: Format,
+    /// compression of the attached files, it's inferred if not given
+    pub codec: Option<Codec>,
+}
+
+///
+/// Starts watching the directories, paths of the new files are sent to the returned channel.
+/// The watcher stops as soon as it's dropped.
+fn watch(
+    watch: &Watch,
+) -> Result<
+    (
+        notify::RecommendedWatcher,
+        mpsc::Receiver<std::path::PathBuf>,
+    ),
+    error::MrgError,
+> {
+    let (sender, receiver) = mpsc::channel::<std::path::PathBuf>();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => match event.kind {
+                notify::EventKind::Create(_)
+                | notify::EventKind::Modify(notify::event::ModifyKind::Name(
+                    notify::event::RenameMode::To,
+                )) => event.paths.into_iter().for_each(|path| {
+                    let _ = sender.send(path);
+                }),
+                _ => {}
+            },
+            Err(e) => eprintln!("cannot watch directory: {}", e),
+        })?;
+    let mode = if watch.walk.recursive {
+        notify::RecursiveMode::Recursive
+    } else {
+        notify::RecursiveMode::NonRecursive
+    };
+    for dir in &watch.dirs {
+        watcher.watch(std::path::Path::new(dir), mode)?;
+    }
+    Ok((watcher, receiver))
+}
+
+///
+/// Opens the newly created file and starts tailing it, files which don't pass the filters are ignored
+fn attach(path: &std::path::Path, dirs: &Watch, sender: &mpsc::Sender<(Arc<str>, Format, String)>) {
+    let accepted = path.is_file()
+        && path
+            .file_name()
+            .is_some_and(|name| dirs.walk.accepts(&name.to_string_lossy()));
+    if !accepted {
+        return;
+    }
+    match crate::input::open_file(&path.to_string_lossy(), None, dirs.codec) {
+        Ok(streams) => {
+            for stream in streams {
+                let sender = sender.clone();
+                let input = Input {
+                    path: Arc::from(path.to_string_lossy()),
+                    format: dirs.format,
+                    reader: BufReader::with_capacity(crate::BUF_SIZE, stream),
+                };
+                std::thread::spawn(move || tail(input, sender));
+            }
+        }
+        Err(e) => eprintln!("cannot attach '{}': {}", path.display(), e),
+    }
+}
+
+///
+/// Merges the inputs, picking up lines as they are appended, never returns unless output fails.
+///
+/// # Arguments
+///
+/// * `parser`: extracts timestamps out of the records
+/// * `since`: records with timestamps below it are skipped
+/// * `ins`: inputs, each of them is read from its own thread
+/// * `transform`: changes made to the records
+/// * `out`: the merged stream
+/// * `window`: how far behind the newest record a record is held back for reordering
+/// * `dirs`: directories whose new files are merged as well, if any
+pub fn run<Out: Sink>(
+    parser: &timestamp::Parser,
+    since: Option<Timestamp>,
+    ins: Vec<Input<BufReader<Stream>>>,
+    transform: &mut transform::Transform,
+    out: &mut Out,
+    window: Timestamp,
+    dirs: Option<&Watch>,
+) -> Result<(), error::MrgError> {
+    let (sender, receiver) = mpsc::channel::<(Arc<str>, Format, String)>();
+    for mut input in ins {
+        if input.format == Format::Ndjson {
+            parser.sample(&input.path, &mut input.reader);
+        }
+        let sender = sender.clone();
+        std::thread::spawn(move || tail(input, sender));
+    }
+    // the watcher must stay alive for as long as the merge is running,
+    // the sender is only kept when there are directories to attach new inputs from
+    let watched = dirs.map(watch).transpose()?;
+    let sender = dirs.map(|_| sender);
+    let mut reorder = Reorder::new(window);
+    // timestamps of the latest records of the inputs, for the records which inherit them
+    // along with whether they are inherited
+    let mut latest: HashMap<Arc<str>, (Timestamp, bool)> = HashMap::new();
+    loop {
+        if let (Some((_, created)), Some(dirs), Some(sender)) = (&watched, dirs, &sender) {
+            for path in created.try_iter() {
+                attach(&path, dirs, sender);
+            }
+        }
+        let ready: Vec<Record> = match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(received) => {
+                for (path, format, raw_line) in std::iter::once(received).chain(receiver.try_iter())
+                {
+                    let inherited = match latest.get(&path) {
+                        Some((ts, inherited)) => {
+                            Some(*ts).filter(|_| parser.inherit_ts || *inherited)
+                        }
+                        None => parser.fallback.and_then(|fallback| fallback.of(&path)),
+                    };
+                    let parsed =
+                        crate::parse_or_inherit(raw_line, parser, &path, format, inherited);
+                    if let Ok((ts, _, inherited)) = &parsed {
+                        latest.insert(path.clone(), (*ts, *inherited));
+                    }
+                    match parsed.map(|(ts, line, _)| (ts, line)) {
+                        Ok((ts, line)) if since.is_none_or(|since| ts >= since) => {
+                            let (ts, line) =
+                                match reorder.released().filter(|released| *released > ts) {
+                                    // it's too late to put the record in order
+                                    Some(released) if parser.monotonic => {
+                                        crate::clamp(line, parser, &path, format, released)
+                                    }
+                                    _ => (ts, line),
+                                };
+                            reorder.push(ts, path.clone(), line)
+                        }
+                        Ok(_) => {}
+                        Err(e) if timestamp::is_filtered(&e) => {}
+                        Err(e) => eprintln!("cannot parse entry: {}", e),
+                    }
+                }
+                reorder.ready()
+            }
+            // nothing is being written, there is no reason to hold records back
+            Err(mpsc::RecvTimeoutError::Timeout) => reorder.drain(),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        for (ts, path, raw_line) in ready {
+            out.emit(ts, &transform.apply(ts, &path, &raw_line))?;
+        }
+        out.flush_records()?;
+    }
+    for (ts, path, raw_line) in reorder.drain() {
+        out.emit(ts, &transform.apply(ts, &path, &raw_line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn reorder() {
+        let path: std::sync::Arc<str> = std::sync::Arc::from("-");
+        let mut reorder = crate::follow::Reorder::new(10);
+        reorder.push(100, path.clone(), "100".to_string());
+        reorder.push(95, path.clone(), "95".to_string());
+        assert!(reorder.ready().is_empty());
+        reorder.push(106, path.clone(), "106".to_string());
+        assert_eq!(vec![(95, path.clone(), "95".to_string())], reorder.ready());
+        reorder.push(98, path.clone(), "98".to_string());
+        reorder.push(110, path.clone(), "110".to_string());
+        assert_eq!(
+            vec![
+                (98, path.clone(), "98".to_string()),
+                (100, path.clone(), "100".to_string())
+            ],
+            reorder.ready()
+        );
+        assert_eq!(
+            vec![
+                (106, path.clone(), "106".to_string()),
+                (110, path.clone(), "110".to_string())
+            ],
+            reorder.drain()
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use crate::input::Stream;
+use crate::{error, s3};
+
+pub const SCHEME: &str = "gs://";
+
+const DEFAULT_ENDPOINT: &str = "https://storage.googleapis.com";
+
+///
+/// Turns `gs://bucket/object` into the URL of the JSON API which returns the content of the object.
+/// `STORAGE_EMULATOR_HOST` replaces the default endpoint, e.g. for fake-gcs-server.
+fn media_url(url: &str) -> Result<String, error::MrgError> {
+    let (bucket, object) = url
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.split_once('/'))
+        .filter(|(bucket, object)| !bucket.is_empty() && !object.is_empty())
+        .ok_or(error::MrgError {
+            msg: format!(
+                "'{}' is not a valid GCS URL, expected gs://bucket/object",
+                url
+            ),
+        })?;
+    let endpoint = std::env::var("STORAGE_EMULATOR_HOST").unwrap_or(DEFAULT_ENDPOINT.to_string());
+    Ok(format!(
+        "{}/storage/v1/b/{}/o/{}?alt=media",
+        endpoint.trim_end_matches('/'),
+        bucket,
+        // object name is a single path segment in the JSON API
+        s3::uri_encode(object).replace('/', "%2F")
+    ))
+}
+
+///
+/// Streams the object, authenticating with the OAuth token from `GOOGLE_OAUTH_ACCESS_TOKEN`
+/// (e.g. `gcloud auth print-access-token`), public objects are read anonymously.
+pub fn open(url: &str) -> Result<Stream, error::MrgError> {
+    let mut call = ureq::get(&media_url(url)?);
+    if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+        call = call.set("Authorization", &format!("Bearer {}", token));
+    }
+    Ok(call.call()?.into_reader())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn media_url() {
+        assert_eq!(
+            "https://storage.googleapis.com/storage/v1/b/bucket/o/logs%2Fapp%201.json.gz?alt=media",
+            crate::gcs::media_url("gs://bucket/logs/app 1.json.gz").unwrap()
+        );
+        assert!(crate::gcs::media_url("gs://bucket").is_err());
+    }
+}
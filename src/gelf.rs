@@ -0,0 +1,147 @@
+use std::hash::BuildHasher;
+use std::io::{BufWriter, Write};
+use std::net::{TcpStream, UdpSocket};
+
+use crate::error;
+use crate::output::Sink;
+use crate::timestamp::Timestamp;
+
+/// Bytes of a message in a datagram, longer messages are chunked
+const CHUNK_SIZE: usize = 8180;
+/// Chunks of a message Graylog accepts
+const MAX_CHUNKS: usize = 128;
+/// Magic bytes chunks start with
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+
+///
+/// Graylog input the GELF messages are sent to, `udp://host:port` or `tcp://host:port`
+pub enum Gelf {
+    /// along with the id of the next chunked message
+    Udp(UdpSocket, u64),
+    /// messages are followed by NUL
+    Tcp(BufWriter<TcpStream>),
+}
+
+impl Gelf {
+    pub fn connect(url: &str) -> Result<Self, error::MrgError> {
+        match url.split_once("://") {
+            Some(("udp", address)) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(address)?;
+                // ids of chunked messages of different runs differ
+                let id =
+                    std::collections::hash_map::RandomState::new().hash_one(std::process::id());
+                Ok(Gelf::Udp(socket, id))
+            }
+            Some(("tcp", address)) => Ok(Gelf::Tcp(BufWriter::new(TcpStream::connect(address)?))),
+            _ => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid GELF address, expected udp://host:port or tcp://host:port",
+                    url
+                ),
+            }),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), error::MrgError> {
+        match self {
+            Gelf::Udp(_, _) => Ok(()),
+            Gelf::Tcp(mut writer) => Ok(writer.flush()?),
+        }
+    }
+}
+
+///
+/// Sends the message as a datagram, or as chunks of it when it doesn't fit one
+fn send(socket: &UdpSocket, id: u64, message: &[u8]) -> std::io::Result<()> {
+    if message.len() <= CHUNK_SIZE {
+        return socket.send(message).map(|_| ());
+    }
+    let chunks = message.len().div_ceil(CHUNK_SIZE);
+    if chunks > MAX_CHUNKS {
+        return Err(std::io::Error::other(format!(
+            "GELF message of {} bytes is too long to be sent over UDP",
+            message.len()
+        )));
+    }
+    for (number, chunk) in message.chunks(CHUNK_SIZE).enumerate() {
+        let mut datagram = Vec::with_capacity(12 + chunk.len());
+        datagram.extend_from_slice(&CHUNK_MAGIC);
+        datagram.extend_from_slice(&id.to_be_bytes());
+        datagram.push(number as u8);
+        datagram.push(chunks as u8);
+        datagram.extend_from_slice(chunk);
+        socket.send(&datagram)?;
+    }
+    Ok(())
+}
+
+impl Sink for Gelf {
+    fn emit(&mut self, _: Timestamp, line: &str) -> std::io::Result<()> {
+        match self {
+            Gelf::Udp(socket, id) => {
+                *id = id.wrapping_add(1);
+                send(socket, *id, line.as_bytes())
+            }
+            Gelf::Tcp(writer) => {
+                writer.write_all(line.as_bytes())?;
+                writer.write_all(b"\0")
+            }
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        match self {
+            Gelf::Udp(_, _) => Ok(()),
+            Gelf::Tcp(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::output::Sink;
+
+    #[test]
+    fn udp() {
+        let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = format!("udp://{}", server.local_addr().unwrap());
+        let mut gelf = crate::gelf::Gelf::connect(&url).unwrap();
+        gelf.emit(1, r#"{"version":"1.1"}"#).unwrap();
+        let long = format!(r#"{{"short_message":"{}"}}"#, "a".repeat(10_000));
+        gelf.emit(2, &long).unwrap();
+        gelf.finish().unwrap();
+
+        let mut datagram = [0u8; 9000];
+        let len = server.recv(&mut datagram).unwrap();
+        assert_eq!(br#"{"version":"1.1"}"#, &datagram[..len]);
+        let mut message = Vec::new();
+        for number in 0..2 {
+            let len = server.recv(&mut datagram).unwrap();
+            assert_eq!([0x1e, 0x0f], datagram[..2]);
+            assert_eq!([number, 2], datagram[10..12]);
+            message.extend_from_slice(&datagram[12..len]);
+        }
+        assert_eq!(long.as_bytes(), message);
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+        let mut gelf = crate::gelf::Gelf::connect(&url).unwrap();
+        gelf.emit(1, r#"{"a":1}"#).unwrap();
+        gelf.emit(2, r#"{"a":2}"#).unwrap();
+        gelf.finish().unwrap();
+        assert_eq!(b"{\"a\":1}\0{\"a\":2}\0".to_vec(), server.join().unwrap());
+        assert!(crate::gelf::Gelf::connect("http://host:12201").is_err());
+    }
+}
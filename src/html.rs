@@ -0,0 +1,165 @@
+use crate::output::{Output, Sink};
+use crate::timestamp::Timestamp;
+
+/// The document up to the records, which are the items of a JavaScript array
+const HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>jmrg report</title>
+<style>
+body { font-family: sans-serif; margin: 1em; }
+input { width: 40em; padding: 0.3em; margin-bottom: 0.5em; }
+table { border-collapse: collapse; font-family: monospace; font-size: 0.9em; }
+th { position: sticky; top: 0; background: #eee; cursor: pointer; user-select: none; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.4em; text-align: left; vertical-align: top; }
+td { white-space: pre-wrap; word-break: break-word; }
+tr:nth-child(even) td { background: #fafafa; }
+</style>
+</head>
+<body>
+<input id="filter" type="search" placeholder="Filter records" autofocus>
+<span id="count"></span>
+<table><thead><tr id="header"></tr></thead><tbody id="rows"></tbody></table>
+<script>
+const LINES = [
+"#;
+
+/// The rest of the document, the table is rendered out of the records
+const TAIL: &str = r#"];
+const records = LINES.map((line) => {
+  try {
+    const record = JSON.parse(line);
+    if (record !== null && typeof record === "object" && !Array.isArray(record)) {
+      return record;
+    }
+  } catch (e) {}
+  return { line: line };
+});
+const columns = [];
+for (const record of records) {
+  for (const key of Object.keys(record)) {
+    if (!columns.includes(key)) {
+      columns.push(key);
+    }
+  }
+}
+const text = (value) =>
+  value === undefined ? "" : typeof value === "object" ? JSON.stringify(value) : String(value);
+const rows = records.map((record, index) => {
+  const tr = document.createElement("tr");
+  for (const column of columns) {
+    const td = document.createElement("td");
+    td.textContent = text(record[column]);
+    tr.appendChild(td);
+  }
+  return { record: record, index: index, tr: tr, text: tr.textContent.toLowerCase() };
+});
+const filter = document.getElementById("filter");
+const body = document.getElementById("rows");
+let order = { column: null, descending: false };
+const render = () => {
+  const needle = filter.value.toLowerCase();
+  const shown = rows.filter((row) => row.text.includes(needle));
+  if (order.column !== null) {
+    const compare = (a, b) => {
+      const x = a.record[order.column];
+      const y = b.record[order.column];
+      const c = typeof x === "number" && typeof y === "number"
+        ? x - y
+        : text(x).localeCompare(text(y), undefined, { numeric: true });
+      return (order.descending ? -c : c) || a.index - b.index;
+    };
+    shown.sort(compare);
+  }
+  body.replaceChildren(...shown.map((row) => row.tr));
+  document.getElementById("count").textContent = shown.length + " of " + rows.length + " records";
+};
+const header = document.getElementById("header");
+for (const column of columns) {
+  const th = document.createElement("th");
+  th.textContent = column;
+  th.dataset.column = column;
+  th.addEventListener("click", () => {
+    order = {
+      column: column,
+      descending: order.column === column && !order.descending,
+    };
+    for (const cell of header.children) {
+      const arrow = cell === th ? (order.descending ? " ▼" : " ▲") : "";
+      cell.textContent = cell.dataset.column + arrow;
+    }
+    render();
+  });
+  header.appendChild(th);
+}
+filter.addEventListener("input", render);
+render();
+</script>
+</body>
+</html>
+"#;
+
+///
+/// Item of the array of the records, `<` is escaped in the string so that `</script>` in the records doesn't close the script
+fn item(line: &str) -> String {
+    let string = serde_json::Value::from(line.trim_end_matches('\n')).to_string();
+    format!("{},", string.replace('<', "\\u003c"))
+}
+
+///
+/// Single HTML document of the records, they are rendered into a table which can be filtered and sorted.
+/// The records are embedded into it as JavaScript strings, so the lines which are not JSON objects are kept as well.
+pub struct Html {
+    inner: Output,
+    /// the head of the document is written already
+    started: bool,
+}
+
+impl Html {
+    pub fn new(inner: Output) -> Self {
+        Html {
+            inner,
+            started: false,
+        }
+    }
+
+    ///
+    /// Writes the rest of the document, a document without records is written when there are none
+    pub fn close(&mut self) -> std::io::Result<()> {
+        if !self.started {
+            self.inner.emit(0, HEAD.trim_end())?;
+        }
+        self.inner.emit(0, TAIL.trim_end())
+    }
+
+    pub fn into_inner(self) -> Output {
+        self.inner
+    }
+}
+
+impl Sink for Html {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.inner.emit(0, HEAD.trim_end())?;
+        }
+        self.inner.emit(ts, &item(line))
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.inner.flush_records()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn item() {
+        assert_eq!(
+            r#""{\"msg\":\"\u003c/script>\"}","#,
+            crate::html::item("{\"msg\":\"</script>\"}\n")
+        );
+        assert_eq!(r#""not json","#, crate::html::item("not json"));
+    }
+}
@@ -0,0 +1,1032 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "azure")]
+use crate::azure;
+#[cfg(feature = "gcs")]
+use crate::gcs;
+#[cfg(feature = "kafka")]
+use crate::kafka;
+use crate::{error, journal, s3, seekable, ssh, timestamp};
+
+/// Stream of raw bytes of an input, it's `Send` so that inputs can be read from separate threads
+pub type Stream = Box<dyn Read + Send>;
+
+///
+/// How records of an input are laid out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// one JSON object per line, the timestamp is taken from the configured keys
+    Ndjson,
+    /// `journalctl --output=json` entries, the timestamp is taken from `__REALTIME_TIMESTAMP`
+    Journal,
+    /// lines of Docker `json-file` logging driver, the timestamp is taken from `time`,
+    /// the payload of `log` is emitted instead of the whole line if `unwrap` is set
+    Docker { unwrap: bool },
+    /// lines of Kubernetes CRI runtimes, the timestamp is taken from the prefix which is stripped
+    Cri,
+    /// intermediate run of the cascaded merge, the timestamp is stored in front of every record
+    Run,
+}
+
+impl Format {
+    ///
+    /// Format of the input, journal is recognized by its scheme, the rest have the default one
+    pub fn of(path: &str, default: Format) -> Self {
+        if path.starts_with(journal::SCHEME) {
+            Format::Journal
+        } else {
+            default
+        }
+    }
+}
+
+///
+/// Compression of an input, given by the user when it can't be inferred
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Codec {
+    Plain,
+    Gzip,
+    Bzip2,
+    Zstd,
+    Xz,
+    Lz4,
+    Snappy,
+}
+
+impl Codec {
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "plain" => Ok(Codec::Plain),
+            "gzip" | "gz" => Ok(Codec::Gzip),
+            "bzip2" | "bz2" => Ok(Codec::Bzip2),
+            "zstd" | "zst" => Ok(Codec::Zstd),
+            "xz" => Ok(Codec::Xz),
+            "lz4" => Ok(Codec::Lz4),
+            "snappy" | "sz" => Ok(Codec::Snappy),
+            _ => Err(error::MrgError {
+                msg: format!(
+                    "unknown input format '{}', expected one of plain, gzip, bzip2, zstd, xz, lz4, snappy",
+                    name
+                ),
+            }),
+        }
+    }
+
+    ///
+    /// Extension the inferred type would have
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            Codec::Plain => None,
+            Codec::Gzip => Some("gz"),
+            Codec::Bzip2 => Some("bz2"),
+            Codec::Zstd => Some("zst"),
+            Codec::Xz => Some("xz"),
+            Codec::Lz4 => Some("lz4"),
+            Codec::Snappy => Some("sz"),
+        }
+    }
+}
+
+///
+/// Input ready to be merged
+pub struct Input<R> {
+    /// path the input is opened from, entries of an archive share the path of the archive
+    pub path: Arc<str>,
+    pub format: Format,
+    pub reader: R,
+}
+
+/// Path which stands for the standard input
+pub const STDIN_PATH: &str = "-";
+
+/// Number of bytes `infer` looks at, when the type of the file is inferred by its path
+const SNIFF_SIZE: usize = 8192;
+
+/// Tar header is 512 bytes long, its magic is located at offset 257
+const TAR_HEADER_SIZE: usize = 512;
+
+/// Magic number of the LZ4 frame format, `infer` doesn't know about it
+const LZ4_FRAME_MAGIC: [u8; 4] = [0x04, 0x22, 0x4D, 0x18];
+
+/// Stream identifier chunk every snappy framed stream starts with, `infer` doesn't know about it
+const SNAPPY_FRAME_MAGIC: [u8; 10] = [0xFF, 0x06, 0x00, 0x00, b's', b'N', b'a', b'P', b'p', b'Y'];
+
+fn is_lz4(buf: &[u8]) -> bool {
+    buf.starts_with(&LZ4_FRAME_MAGIC)
+}
+
+fn is_snappy(buf: &[u8]) -> bool {
+    buf.starts_with(&SNAPPY_FRAME_MAGIC)
+}
+
+///
+/// The earliest record the caller is interested in, lets inputs skip the data which precedes it
+pub struct Since<'a> {
+    /// records with timestamps below this one are not interesting
+    pub ts: timestamp::Timestamp,
+    /// extracts the timestamp out of a raw line of the input at the given path
+    pub ts_of: &'a dyn Fn(&str, &str) -> Option<timestamp::Timestamp>,
+}
+
+///
+/// Describes how directories passed as inputs are traversed
+pub struct Walk {
+    /// directories are only traversed when it's requested explicitly
+    pub recursive: bool,
+    /// if not empty, only files whose names match any of these are merged
+    pub include: Vec<glob::Pattern>,
+    /// files whose names match any of these are skipped
+    pub exclude: Vec<glob::Pattern>,
+}
+
+impl Walk {
+    pub fn accepts(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+}
+
+fn make_matcher() -> infer::Infer {
+    let mut matcher = infer::Infer::new();
+    matcher.add("application/x-lz4", "lz4", is_lz4);
+    matcher.add("application/x-snappy-framed", "sz", is_snappy);
+    matcher
+}
+
+///
+/// Wraps the reader into a decoder corresponding to the inferred extension.
+/// If the extension is unknown or is not a compression format, the reader is returned as is.
+///
+/// # Arguments
+///
+/// * `extension`: extension inferred from the magic bytes of the stream, if any
+/// * `reader`: raw stream
+///
+/// returns: Result<Stream, MrgError>
+fn decode(extension: Option<&str>, reader: Stream) -> Result<Stream, error::MrgError> {
+    match extension {
+        // files appended with `gzip >>` consist of several members, all of them must be read
+        Some("gz") => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+        Some("bz2") => Ok(Box::new(bzip2::read::BzDecoder::new(reader))),
+        Some("zst") => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+        Some("xz") => Ok(Box::new(xz2::read::XzDecoder::new(reader))),
+        Some("lz4") => Ok(Box::new(lz4_flex::frame::FrameDecoder::new(reader))),
+        Some("sz") => Ok(Box::new(snap::read::FrameDecoder::new(reader))),
+        // in case it's not a compression we know about, we try to parse it as is
+        _ => Ok(reader),
+    }
+}
+
+/// Number of the archive entries spilled so far, it makes the names of their files unique
+static SPILLED: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Temporary file which is removed when dropped
+struct TempPath(PathBuf);
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+///
+/// Content of an archive entry copied into a temporary file, so that it's not held in memory
+struct Spilled {
+    file: File,
+    /// dropped after the file is closed
+    _path: TempPath,
+}
+
+impl Spilled {
+    ///
+    /// Copies the content into a temporary file, as much of it as can be read
+    ///
+    /// returns: the file and the error the content has failed with, e.g. when the archive is truncated
+    fn new(mut content: impl Read) -> Result<(Self, Option<std::io::Error>), error::MrgError> {
+        let path = std::env::temp_dir().join(format!(
+            "jmrg-entry-{}-{}",
+            std::process::id(),
+            SPILLED.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let mut spilled = Spilled {
+            file,
+            _path: TempPath(path),
+        };
+        // failures of the file are fatal, failures of the content leave the part read before them
+        let mut buf = vec![0u8; 64 * 1024];
+        let failure = loop {
+            match content.read(&mut buf) {
+                Ok(0) => break None,
+                Ok(read) => spilled.file.write_all(&buf[..read])?,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => break Some(e),
+            }
+        };
+        spilled.file.rewind()?;
+        Ok((spilled, failure))
+    }
+}
+
+impl Read for Spilled {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for Spilled {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+///
+/// Turns the content of an archive entry into a stream, the content is spilled into a temporary file.
+/// Entries are decoded the same way as regular files, so `.json.gz` inside of an archive is fine.
+/// The entry which can't be read to the end fails after the part read before the failure, like truncated files do.
+///
+/// returns: the stream and whether the entry is cut short
+fn open_entry(content: impl Read) -> Result<(Stream, bool), error::MrgError> {
+    let (spilled, failure) = Spilled::new(content)?;
+    let cut = failure.is_some();
+    let stream: Stream = match failure {
+        Some(e) => Box::new(spilled.chain(Failed(Some(e)))),
+        None => Box::new(spilled),
+    };
+    let (prefix, stream) = peek(stream, SNIFF_SIZE)?;
+    let extension = make_matcher().get(&prefix).map(|t| t.extension());
+    Ok((decode(extension, stream)?, cut))
+}
+
+///
+/// Spills every regular file of the zip archive into a temporary file
+/// and returns each of them as a separate stream, the sizes in the headers of the entries are not trusted.
+fn open_zip<R: Read + Seek>(reader: R) -> Result<Vec<Stream>, error::MrgError> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut entries: Vec<Stream> = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if !entry.is_file() {
+            continue;
+        }
+        // entries of zip are independent, the ones after the broken one are fine
+        entries.push(open_entry(&mut entry)?.0);
+    }
+    Ok(entries)
+}
+
+///
+/// Spills every regular file of the tar archive into a temporary file
+/// and returns each of them as a separate stream, the archive cut off in the middle of an entry ends with it.
+/// Tar archives can only be read sequentially, that's why entries can't be streamed lazily.
+fn open_tar(reader: Stream) -> Result<Vec<Stream>, error::MrgError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries: Vec<Stream> = Vec::new();
+    for entry in archive.entries()? {
+        // truncated archive still has the entries before the cut
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("cannot read the rest of tar archive: {}", e);
+                break;
+            }
+        };
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let (stream, cut) = open_entry(&mut entry)?;
+        entries.push(stream);
+        // the failure is reported once the part of the entry before it is merged
+        if cut {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+///
+/// Stream which fails with the error that has already happened,
+/// so that it's reported after the data read before it is consumed
+struct Failed(Option<std::io::Error>);
+
+impl Read for Failed {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        self.0.take().map_or(Ok(0), Err)
+    }
+}
+
+///
+/// Reads up to `size` bytes from the beginning of the stream without losing them.
+/// If the stream fails earlier, e.g. it's a truncated archive, the failure is postponed.
+///
+/// returns: the bytes read and the stream that still starts from the very beginning
+fn peek(mut reader: Stream, size: usize) -> std::io::Result<(Vec<u8>, Stream)> {
+    let mut prefix: Vec<u8> = Vec::with_capacity(size);
+    let rest: Stream = match (&mut reader).take(size as u64).read_to_end(&mut prefix) {
+        Ok(_) => reader,
+        Err(e) => Box::new(Failed(Some(e))),
+    };
+    let restored: Stream = Box::new(Cursor::new(prefix.clone()).chain(rest));
+    Ok((prefix, restored))
+}
+
+///
+/// Records which are already buffered by the reader, nothing is consumed.
+/// The last line is left out, it may be cut off by the end of the buffer.
+pub fn buffered<R: BufRead>(reader: &mut R) -> String {
+    let buf: &[u8] = match reader.fill_buf() {
+        Ok(buf) => buf,
+        // the error shows up again when the input is read
+        Err(_) => return String::new(),
+    };
+    let complete = buf
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(&buf[..0], |end| &buf[..end]);
+    String::from_utf8_lossy(complete).into_owned()
+}
+
+///
+/// The function attempts to open a file,
+/// infers its type (e.g., whether it's an archive like gzip or bzip2),
+/// and returns corresponding Read trait objects that can be used to read the file's contents.
+/// Containers like zip, tar or compressed tar produce one stream per entry,
+/// everything else produces a single stream.
+/// If the file is not an archive or if it's an unsupported archive, tries to read it as is.
+/// `-` stands for the standard input, which is read as is unless the compression is given,
+/// as well as named pipes.
+/// `http://`, `https://`, `s3://`, `gs://` and `az://` URLs are streamed from the network,
+/// `journal://` reads systemd journal, `ssh://` streams the remote file through `ssh`.
+///
+/// # Arguments
+///
+/// * `path`: path to the file in the filesystem, URL or `-`
+/// * `since`: the earliest record the caller is interested in, if any
+/// * `codec`: compression of the input, it's inferred when not given
+///
+/// returns: Result<Vec<Stream>, MrgError>
+///
+/// # Examples
+///
+/// ```
+/// let f = open_file("/var/log/vector.log", None, None)
+/// ```
+pub fn open_file(
+    path: &str,
+    since: Option<&Since>,
+    codec: Option<Codec>,
+) -> Result<Vec<Stream>, error::MrgError> {
+    if path == STDIN_PATH {
+        return open_stream(Box::new(std::io::stdin()), codec.and_then(Codec::extension));
+    }
+    if path.starts_with(journal::SCHEME) {
+        return Ok(vec![journal::open(path)?]);
+    }
+    if path.starts_with(KAFKA_SCHEME) {
+        return open_topic(path);
+    }
+    if is_url(path) {
+        return open_body(ureq::get(path).call()?.into_reader(), codec);
+    }
+    if path.starts_with(ssh::SCHEME) {
+        return open_body(ssh::open(path)?, codec);
+    }
+    if is_remote(path) {
+        return open_body(open_cloud(path)?, codec);
+    }
+    let file: File = File::open(path)?;
+    let metadata = file.metadata()?;
+    let regular = metadata.is_file();
+    // there is nothing to decompress in an empty file, whatever its extension or hint is
+    if regular && metadata.len() == 0 {
+        return Ok(vec![Box::new(std::io::empty())]);
+    }
+    let extension = match codec {
+        Some(codec) => codec.extension(),
+        // pipes and devices, e.g. `<(zcat a.gz)`, can't be read twice, so their content is not sniffed
+        None if !regular => return Ok(vec![Box::new(file)]),
+        None => make_matcher().get_from_path(path)?.map(|t| t.extension()),
+    };
+    match (extension, since) {
+        // seekable zstd lets us skip frames, it's only worth trying when there is something to skip
+        (Some("zst"), Some(since)) if regular => Ok(vec![seekable::open(file, path, since)?]),
+        (Some("zip"), _) => open_zip(file),
+        _ => open_stream(Box::new(file), extension),
+    }
+}
+
+///
+/// Opens any stream but zip, which requires random access.
+fn open_stream(reader: Stream, extension: Option<&str>) -> Result<Vec<Stream>, error::MrgError> {
+    match extension {
+        Some("tar") => open_tar(reader),
+        // compressed stream may contain tar inside, it can only be seen after decompression
+        Some(_) => {
+            let (prefix, decoded) = peek(decode(extension, reader)?, TAR_HEADER_SIZE)?;
+            if infer::archive::is_tar(&prefix) {
+                open_tar(decoded)
+            } else {
+                Ok(vec![decoded])
+            }
+        }
+        None => Ok(vec![reader]),
+    }
+}
+
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Schemes of cloud storages which are only supported when the corresponding feature is enabled,
+/// they are known regardless, so that the error is clear
+const GCS_SCHEME: &str = "gs://";
+const AZURE_SCHEME: &str = "az://";
+const KAFKA_SCHEME: &str = "kafka://";
+
+///
+/// Child process whose standard output is an input, the process is reaped when the input is dropped
+struct ChildStream {
+    child: std::process::Child,
+    stdout: std::process::ChildStdout,
+}
+
+impl Read for ChildStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stdout.read(buf)
+    }
+}
+
+impl Drop for ChildStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+///
+/// Spawns the command and returns its standard output as a stream
+pub fn spawn(mut command: std::process::Command) -> Result<Stream, error::MrgError> {
+    let mut child = command.stdout(std::process::Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().ok_or(error::MrgError {
+        msg: format!("cannot capture output of {:?}", command.get_program()),
+    })?;
+    Ok(Box::new(ChildStream { child, stdout }))
+}
+
+fn is_remote(path: &str) -> bool {
+    is_url(path)
+        || [s3::SCHEME, GCS_SCHEME, AZURE_SCHEME, KAFKA_SCHEME]
+            .iter()
+            .any(|scheme| path.starts_with(scheme))
+        || path.starts_with(journal::SCHEME)
+        || path.starts_with(ssh::SCHEME)
+}
+
+///
+/// Opens the object in the cloud storage, as long as jmrg is built with its support
+fn open_cloud(path: &str) -> Result<Stream, error::MrgError> {
+    if path.starts_with(s3::SCHEME) {
+        return s3::open(path);
+    }
+    #[cfg(feature = "gcs")]
+    if path.starts_with(gcs::SCHEME) {
+        return gcs::open(path);
+    }
+    #[cfg(feature = "azure")]
+    if path.starts_with(azure::SCHEME) {
+        return azure::open(path);
+    }
+    Err(error::MrgError {
+        msg: format!(
+            "'{}' requires jmrg to be built with the corresponding cargo feature",
+            path
+        ),
+    })
+}
+
+///
+/// Opens the partitions of Kafka topic, as long as jmrg is built with its support
+fn open_topic(path: &str) -> Result<Vec<Stream>, error::MrgError> {
+    #[cfg(feature = "kafka")]
+    return kafka::open(path);
+    #[cfg(not(feature = "kafka"))]
+    Err(error::MrgError {
+        msg: format!(
+            "'{}' requires jmrg to be built with kafka cargo feature",
+            path
+        ),
+    })
+}
+
+///
+/// Streams the body of the response. `Content-Encoding: gzip` is handled by the HTTP client,
+/// everything else, e.g. `.json.gz` artifacts, is inferred from the body itself.
+/// Zip archives are downloaded into a temporary file, since they can't be read sequentially.
+/// Nothing is inferred if the compression is given.
+fn open_body(body: Stream, codec: Option<Codec>) -> Result<Vec<Stream>, error::MrgError> {
+    if let Some(codec) = codec {
+        return open_stream(body, codec.extension());
+    }
+    let (prefix, body) = peek(body, SNIFF_SIZE)?;
+    let extension = make_matcher().get(&prefix).map(|t| t.extension());
+    match extension {
+        Some("zip") => match Spilled::new(body)? {
+            (spilled, None) => open_zip(spilled),
+            // the directory of zip archive is at the end, nothing can be read without it
+            (_, Some(e)) => Err(e.into()),
+        },
+        _ => open_stream(body, extension),
+    }
+}
+
+///
+/// Reads the paths listed in the manifest, empty entries are skipped.
+/// Line endings of Windows are tolerated when paths are separated by newlines.
+///
+/// # Arguments
+///
+/// * `manifest`: path to the manifest, `-` stands for the standard input
+/// * `separator`: byte which terminates every path
+///
+/// returns: Result<Vec<String>, MrgError>
+pub fn read_manifest(manifest: &str, separator: u8) -> Result<Vec<String>, error::MrgError> {
+    let reader: Box<dyn BufRead> = if manifest == STDIN_PATH {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(manifest)?))
+    };
+    let mut paths: Vec<String> = Vec::new();
+    for entry in reader.split(separator) {
+        let entry = String::from_utf8_lossy(&entry?).into_owned();
+        let path = match separator {
+            b'\n' => entry.trim_end_matches('\r'),
+            _ => entry.as_str(),
+        };
+        if !path.is_empty() {
+            paths.push(path.to_string());
+        }
+    }
+    Ok(paths)
+}
+
+///
+/// Expands glob patterns like `logs/*.json.gz` for the systems where the shell doesn't do it.
+/// Paths without special characters are passed as is,
+/// as well as patterns which match nothing, so that opening them reports a proper error.
+pub fn expand_globs(paths: Vec<String>) -> Result<Vec<String>, error::MrgError> {
+    let mut expanded: Vec<String> = Vec::with_capacity(paths.len());
+    for path in paths {
+        if is_remote(&path) || !path.contains(['*', '?', '[']) {
+            expanded.push(path);
+            continue;
+        }
+        let matched: Vec<String> = glob::glob(&path)?
+            .map(|entry| entry.map(|p| p.to_string_lossy().to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(std::io::Error::from)?;
+        if matched.is_empty() {
+            expanded.push(path);
+        } else {
+            expanded.extend(matched);
+        }
+    }
+    Ok(expanded)
+}
+
+///
+/// Replaces directories with all the files inside of them which pass the filters of `walk`.
+/// Files are listed in the order of their names, symbolic links are followed,
+/// the ones which lead to the directory itself are skipped with a warning.
+pub fn expand_dirs(paths: Vec<String>, walk: &Walk) -> Result<Vec<String>, error::MrgError> {
+    let mut expanded: Vec<String> = Vec::with_capacity(paths.len());
+    for path in paths {
+        if !std::path::Path::new(&path).is_dir() {
+            expanded.push(path);
+            continue;
+        }
+        if !walk.recursive {
+            return Err(error::MrgError {
+                msg: format!(
+                    "'{}' is a directory, use --recursive to merge files inside of it",
+                    path
+                ),
+            });
+        }
+        let entries = walkdir::WalkDir::new(&path)
+            .follow_links(true)
+            .sort_by_file_name();
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) if e.loop_ancestor().is_some() => {
+                    eprintln!("skipping symbolic link loop: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(std::io::Error::from(e).into()),
+            };
+            if entry.file_type().is_file() && walk.accepts(&entry.file_name().to_string_lossy()) {
+                expanded.push(entry.path().to_string_lossy().to_string());
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+///
+/// Identity of the local file, hard and symbolic links to the same file share it
+#[cfg(unix)]
+fn file_id(path: &str) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(path: &str) -> Option<String> {
+    Some(
+        std::fs::canonicalize(path)
+            .ok()?
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+///
+/// Drops inputs which refer to the file already merged, with a warning.
+/// Only local files are compared, everything else is kept as is.
+pub fn dedup(paths: Vec<String>) -> Vec<String> {
+    let mut seen: HashMap<String, String> = HashMap::with_capacity(paths.len());
+    let mut unique: Vec<String> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let id = Some(&path)
+            .filter(|path| *path != STDIN_PATH && !is_remote(path))
+            .and_then(|path| file_id(path));
+        if let Some(id) = id {
+            if let Some(first) = seen.get(&id) {
+                eprintln!(
+                    "'{}' is the same file as '{}', merging it once",
+                    path, first
+                );
+                continue;
+            }
+            seen.insert(id, path.clone());
+        }
+        unique.push(path);
+    }
+    unique
+}
+
+pub fn make_readers(
+    paths: &[String],
+    since: Option<&Since>,
+    format: Format,
+    codecs: &HashMap<String, Codec>,
+    codec: Option<Codec>,
+) -> Result<Vec<Input<BufReader<Stream>>>, error::MrgError> {
+    let mut inputs: Vec<Input<BufReader<Stream>>> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let format = Format::of(path, format);
+        let codec = codecs.get(path).copied().or(codec);
+        let shared: Arc<str> = Arc::from(path.as_str());
+        for stream in open_file(path, since, codec)? {
+            inputs.push(Input {
+                path: shared.clone(),
+                format,
+                reader: BufReader::with_capacity(crate::BUF_SIZE, stream),
+            });
+        }
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    fn first_line(r: crate::input::Stream) -> String {
+        let mut r = BufReader::with_capacity(1024, r);
+        let mut line = String::new();
+        r.read_line(&mut line).unwrap();
+        line.replace(['\r', '\n'], "")
+    }
+
+    #[test]
+    fn open_file() {
+        let mut readers = crate::input::open_file("tests/data/1.json", None, None).unwrap();
+        assert_eq!(1, readers.len());
+        assert_eq!(r#"{"t":15, "add": "15_1"}"#, first_line(readers.remove(0)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_fifo() {
+        let dir = std::env::temp_dir().join(format!("jmrg-fifo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("in.json.gz");
+        assert!(std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .unwrap()
+            .success());
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            std::fs::write(writer_path, "{\"t\":1}\n").unwrap();
+        });
+        let mut readers = crate::input::open_file(&path.to_string_lossy(), None, None).unwrap();
+        assert_eq!(r#"{"t":1}"#, first_line(readers.remove(0)));
+        writer.join().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_file_with_codec() {
+        let path = std::env::temp_dir().join(format!("jmrg-codec-{}", std::process::id()));
+        std::fs::copy("tests/data/2.json.gz", &path).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let codec = Some(crate::input::Codec::Gzip);
+        let mut readers = crate::input::open_file(&path, None, codec).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(r#"{"t":16, "add": "16_2"}"#, first_line(readers.remove(0)));
+        assert!(crate::input::Codec::parse("rar").is_err());
+    }
+
+    #[test]
+    fn open_empty_file() {
+        let path = std::env::temp_dir().join(format!("jmrg-empty-{}.gz", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let codec = Some(crate::input::Codec::Gzip);
+        let mut readers = crate::input::open_file(&path, None, codec).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!("", first_line(readers.remove(0)));
+    }
+
+    #[test]
+    fn open_truncated_file() {
+        let mut readers = crate::input::open_file("tests/data/13.json.gz", None, None).unwrap();
+        let mut lines = BufReader::new(readers.remove(0)).lines();
+        assert_eq!(
+            r#"{"t":20, "add": "20_13"}"#,
+            lines.next().unwrap().unwrap()
+        );
+        assert!(lines.any(|line| line.is_err()));
+    }
+
+    #[test]
+    fn open_truncated_tar_gz() {
+        let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for name in ["a.json", "b.json"] {
+            let content: String = (0..3000)
+                .map(|t| format!("{{\"t\":{}, \"add\": \"{}_{}\"}}\n", t, t, name))
+                .collect();
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive
+                .append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        let mut archive = archive.into_inner().unwrap().finish().unwrap();
+        // cut in the middle of the second entry
+        archive.truncate(archive.len() * 3 / 4);
+        let readers =
+            crate::input::open_body(Box::new(std::io::Cursor::new(archive)), None).unwrap();
+        let lines: Vec<Vec<std::io::Result<String>>> = readers
+            .into_iter()
+            .map(|reader| BufReader::new(reader).lines().collect())
+            .collect();
+        assert_eq!(2, lines.len());
+        // the entry before the cut is complete
+        assert_eq!(3000, lines[0].len());
+        assert!(lines[0].iter().all(|line| line.is_ok()));
+        // the cut one has its readable prefix, then fails once
+        let cut = &lines[1];
+        assert!(cut.len() > 1);
+        assert_eq!(r#"{"t":0, "add": "0_"#, &cut[0].as_ref().unwrap()[..18]);
+        assert!(cut.last().unwrap().is_err());
+        assert_eq!(1, cut.iter().filter(|line| line.is_err()).count());
+    }
+
+    #[test]
+    fn open_zstd_file() {
+        let mut readers = crate::input::open_file("tests/data/4.json.zst", None, None).unwrap();
+        assert_eq!(r#"{"t":14, "add": "14_4"}"#, first_line(readers.remove(0)));
+    }
+
+    #[test]
+    fn open_xz_file() {
+        let mut readers = crate::input::open_file("tests/data/5.json.xz", None, None).unwrap();
+        assert_eq!(r#"{"t":13, "add": "13_5"}"#, first_line(readers.remove(0)));
+    }
+
+    #[test]
+    fn open_lz4_file() {
+        let mut readers = crate::input::open_file("tests/data/6.json.lz4", None, None).unwrap();
+        assert_eq!(r#"{"t":12, "add": "12_6"}"#, first_line(readers.remove(0)));
+    }
+
+    #[test]
+    fn open_snappy_file() {
+        let mut readers = crate::input::open_file("tests/data/11.json.sz", None, None).unwrap();
+        assert_eq!(r#"{"t":8, "add": "8_11"}"#, first_line(readers.remove(0)));
+    }
+
+    #[test]
+    fn open_zip_file() {
+        let readers = crate::input::open_file("tests/data/7.zip", None, None).unwrap();
+        let lines: Vec<String> = readers.into_iter().map(first_line).collect();
+        assert_eq!(
+            vec![
+                r#"{"t":11, "add": "11_7a"}"#.to_string(),
+                r#"{"t":12, "add": "12_7b"}"#.to_string()
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn open_zip_body() {
+        let body: crate::input::Stream = Box::new(std::fs::File::open("tests/data/7.zip").unwrap());
+        let readers = crate::input::open_body(body, None).unwrap();
+        let lines: Vec<String> = readers.into_iter().map(first_line).collect();
+        assert_eq!(
+            vec![
+                r#"{"t":11, "add": "11_7a"}"#.to_string(),
+                r#"{"t":12, "add": "12_7b"}"#.to_string()
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn spilled() {
+        let (mut spilled, failure) = crate::input::Spilled::new("{\"t\":1}\n".as_bytes()).unwrap();
+        assert!(failure.is_none());
+        let path = spilled._path.0.clone();
+        assert!(path.exists());
+        let mut content = String::new();
+        spilled.read_to_string(&mut content).unwrap();
+        assert_eq!("{\"t\":1}\n", content);
+        drop(spilled);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn open_tar_file() {
+        let readers = crate::input::open_file("tests/data/8.tar", None, None).unwrap();
+        let lines: Vec<String> = readers.into_iter().map(first_line).collect();
+        assert_eq!(
+            vec![
+                r#"{"t":10, "add": "10_8a"}"#.to_string(),
+                r#"{"t":11, "add": "11_8b"}"#.to_string()
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn open_tar_gz_file() {
+        let readers = crate::input::open_file("tests/data/9.tar.gz", None, None).unwrap();
+        let lines: Vec<String> = readers.into_iter().map(first_line).collect();
+        assert_eq!(
+            vec![
+                r#"{"t":10, "add": "10_8a"}"#.to_string(),
+                r#"{"t":11, "add": "11_8b"}"#.to_string()
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn open_multi_member_gz_file() {
+        let readers = crate::input::open_file("tests/data/10.json.gz", None, None).unwrap();
+        let mut content = String::new();
+        BufReader::new(readers.into_iter().next().unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(
+            "{\"t\":9, \"add\": \"9_10\"}\n{\"t\":27, \"add\": \"27_10\"}\n",
+            content
+        );
+    }
+
+    #[test]
+    fn read_manifest() {
+        let path = std::env::temp_dir().join(format!("jmrg-manifest-{}", std::process::id()));
+        std::fs::write(&path, "tests/data/1.json\r\n\ntests/data/2.json.gz\n").unwrap();
+        let paths = crate::input::read_manifest(&path.to_string_lossy(), b'\n').unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(vec!["tests/data/1.json", "tests/data/2.json.gz"], paths);
+    }
+
+    #[test]
+    fn read_manifest0() {
+        let path = std::env::temp_dir().join(format!("jmrg-manifest0-{}", std::process::id()));
+        std::fs::write(&path, "a b\nc\0\0d\0").unwrap();
+        let paths = crate::input::read_manifest(&path.to_string_lossy(), b'\0').unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(vec!["a b\nc", "d"], paths);
+    }
+
+    #[test]
+    fn expand_globs() {
+        let paths = vec![
+            "-".to_string(),
+            "tests/data/[12].json*".to_string(),
+            "tests/data/missing*.json".to_string(),
+        ];
+        assert_eq!(
+            vec![
+                "-",
+                "tests/data/1.json",
+                "tests/data/2.json.gz",
+                "tests/data/missing*.json"
+            ],
+            crate::input::expand_globs(paths).unwrap()
+        );
+    }
+
+    #[test]
+    fn expand_dirs() {
+        let walk = crate::input::Walk {
+            recursive: true,
+            include: vec![glob::Pattern::new("[1-2].json*").unwrap()],
+            exclude: vec![glob::Pattern::new("*.gz").unwrap()],
+        };
+        let paths = vec!["-".to_string(), "tests/data".to_string()];
+        assert_eq!(
+            vec!["-", "tests/data/1.json"],
+            crate::input::expand_dirs(paths, &walk).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dedup() {
+        let dir = std::env::temp_dir().join(format!("jmrg-dedup-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("d")).unwrap();
+        std::fs::write(dir.join("a.json"), "{}\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("a.json"), dir.join("b.json")).unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("d").join("loop")).unwrap();
+        let walk = crate::input::Walk {
+            recursive: true,
+            include: vec![],
+            exclude: vec![],
+        };
+        let root = dir.to_string_lossy().into_owned();
+        let paths = crate::input::expand_dirs(vec![root.clone()], &walk).unwrap();
+        let mut paths = crate::input::dedup(paths);
+        paths.push("-".to_string());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec![format!("{}/a.json", root), "-".to_string()], paths);
+    }
+
+    #[test]
+    fn expand_dirs_not_recursive() {
+        let walk = crate::input::Walk {
+            recursive: false,
+            include: vec![],
+            exclude: vec![],
+        };
+        let err = crate::input::expand_dirs(vec!["tests/data".to_string()], &walk).unwrap_err();
+        assert_eq!(
+            "'tests/data' is a directory, use --recursive to merge files inside of it",
+            err.msg
+        );
+    }
+
+    #[test]
+    fn open_url() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/2.json.gz", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            let body = std::fs::read("tests/data/2.json.gz").unwrap();
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        let mut readers = crate::input::open_file(&url, None, None).unwrap();
+        assert_eq!(r#"{"t":16, "add": "16_2"}"#, first_line(readers.remove(0)));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use serde::Deserializer;
+
+use crate::input::Stream;
+use crate::{error, timestamp};
+
+pub const SCHEME: &str = "journal://";
+
+/// Time of the entry in microseconds since the epoch, serialized as a string
+const REALTIME_KEY: &str = "__REALTIME_TIMESTAMP";
+
+///
+/// Spawns `journalctl` which prints the journal as JSON, one entry per line.
+/// `journal://` reads the whole journal, `journal://<unit>` only reads the entries of the unit.
+pub fn open(url: &str) -> Result<Stream, error::MrgError> {
+    let unit: &str = url.strip_prefix(SCHEME).unwrap_or_default();
+    let mut command = std::process::Command::new("journalctl");
+    command.args(["--output=json", "--no-pager"]);
+    if !unit.is_empty() {
+        command.args(["--unit", unit]);
+    }
+    crate::input::spawn(command)
+}
+
+struct RealtimeVisitor;
+
+impl<'de> serde::de::Visitor<'de> for RealtimeVisitor {
+    type Value = i64;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "journal entry with {}", REALTIME_KEY)
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: serde::de::MapAccess<'de>,
+    {
+        let mut ts: Option<i64> = None;
+        while let Some(k) = map.next_key::<&str>()? {
+            if ts.is_none() && k == REALTIME_KEY {
+                let us: &str = map.next_value::<&str>()?;
+                ts = Some(us.parse::<i64>().map_err(serde::de::Error::custom)?);
+            } else {
+                map.next_value::<serde::de::IgnoredAny>()?;
+            }
+        }
+        ts.ok_or(serde::de::Error::custom(format!(
+            "no {} field",
+            REALTIME_KEY
+        )))
+    }
+}
+
+///
+/// Extracts the time of the journal entry, converted to nanoseconds
+/// so that entries can be merged with regular inputs.
+pub fn parse_ts(raw_line: &str) -> Result<timestamp::Timestamp, serde_json::Error> {
+    let mut des = serde_json::de::Deserializer::from_str(raw_line);
+    Ok(timestamp::Timestamp::from(des.deserialize_map(RealtimeVisitor)?) * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_ts() {
+        let line = r#"{"MESSAGE":"started","__REALTIME_TIMESTAMP":"1713212345678901","_PID":"1"}"#;
+        assert_eq!(1713212345678901000, crate::journal::parse_ts(line).unwrap());
+        assert!(crate::journal::parse_ts(r#"{"MESSAGE":"started"}"#).is_err());
+    }
+}
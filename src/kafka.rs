@@ -0,0 +1,297 @@
+use std::io::Read;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::DeliveryResult;
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer, ProducerContext};
+use rdkafka::{ClientContext, Message, Offset, TopicPartitionList};
+
+use crate::error;
+use crate::input::Stream;
+use crate::output::Sink;
+use crate::timestamp::Timestamp;
+
+pub const SCHEME: &str = "kafka://";
+
+/// How long requests to the brokers are allowed to take
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+///
+/// Topic addressed by `kafka://broker[,broker...]/topic[?partitions=0,1]`,
+/// all partitions of the topic are read when none are given.
+#[derive(Debug, PartialEq)]
+struct Topic {
+    brokers: String,
+    name: String,
+    partitions: Option<Vec<i32>>,
+}
+
+impl Topic {
+    fn parse(url: &str) -> Result<Self, error::MrgError> {
+        let invalid = || error::MrgError {
+            msg: format!(
+                "'{}' is not a valid Kafka URL, expected kafka://broker/topic[?partitions=0,1]",
+                url
+            ),
+        };
+        let (location, query) = url
+            .strip_prefix(SCHEME)
+            .map(|rest| rest.split_once('?').unwrap_or((rest, "")))
+            .ok_or_else(invalid)?;
+        let (brokers, name) = location
+            .split_once('/')
+            .filter(|(brokers, name)| !brokers.is_empty() && !name.is_empty())
+            .ok_or_else(invalid)?;
+        let partitions = match query.strip_prefix("partitions=") {
+            Some(list) => Some(
+                list.split(',')
+                    .map(|p| p.parse::<i32>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<i32>, error::MrgError>>()?,
+            ),
+            None if query.is_empty() => None,
+            None => return Err(invalid()),
+        };
+        Ok(Topic {
+            brokers: brokers.to_string(),
+            name: name.to_string(),
+            partitions,
+        })
+    }
+}
+
+///
+/// Records of a single partition, one per line, up to the end of the partition at the time it's opened
+struct Partition {
+    consumer: BaseConsumer,
+    /// offset of the next record to be read
+    next: i64,
+    /// high watermark of the partition when it was opened
+    end: i64,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Partition {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.line.len() {
+            if self.next >= self.end {
+                return Ok(0);
+            }
+            let Some(message) = self.consumer.poll(TIMEOUT) else {
+                continue;
+            };
+            let message = message.map_err(std::io::Error::other)?;
+            self.next = message.offset() + 1;
+            // records without payload, e.g. tombstones, are not worth merging
+            if let Some(payload) = message.payload() {
+                self.line.clear();
+                self.line.extend_from_slice(payload);
+                self.line.push(b'\n');
+                self.pos = 0;
+            }
+        }
+        let n = buf.len().min(self.line.len() - self.pos);
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn consumer(brokers: &str) -> Result<BaseConsumer, error::MrgError> {
+    Ok(rdkafka::ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set("group.id", "jmrg")
+        .set("enable.auto.commit", "false")
+        .create()?)
+}
+
+///
+/// Opens every partition of the topic as a separate stream, the records are read
+/// from the beginning of the partition up to its end at the time of opening.
+pub fn open(url: &str) -> Result<Vec<Stream>, error::MrgError> {
+    let topic = Topic::parse(url)?;
+    let partitions: Vec<i32> = match topic.partitions {
+        Some(partitions) => partitions,
+        None => consumer(&topic.brokers)?
+            .fetch_metadata(Some(&topic.name), TIMEOUT)?
+            .topics()
+            .iter()
+            .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+            .collect(),
+    };
+    let mut streams: Vec<Stream> = Vec::with_capacity(partitions.len());
+    for partition in partitions {
+        let consumer = consumer(&topic.brokers)?;
+        let (low, high) = consumer.fetch_watermarks(&topic.name, partition, TIMEOUT)?;
+        let mut assignment = TopicPartitionList::new();
+        assignment.add_partition_offset(&topic.name, partition, Offset::Offset(low))?;
+        consumer.assign(&assignment)?;
+        streams.push(Box::new(Partition {
+            consumer,
+            next: low,
+            end: high,
+            line: Vec::new(),
+            pos: 0,
+        }));
+    }
+    Ok(streams)
+}
+
+///
+/// Remembers the first record the brokers failed to accept
+#[derive(Default)]
+struct Deliveries {
+    failure: Mutex<Option<KafkaError>>,
+}
+
+impl ClientContext for Deliveries {}
+
+impl ProducerContext for Deliveries {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {
+        if let Err((e, _)) = result {
+            if let Ok(mut failure) = self.failure.lock() {
+                failure.get_or_insert_with(|| e.clone());
+            }
+        }
+    }
+}
+
+///
+/// Topic the records are published to in the order they are merged, the timestamps of the records
+/// become the timestamps of the messages. Idempotence keeps the order of the messages of a partition on retries.
+pub struct Publisher {
+    producer: BaseProducer<Deliveries>,
+    topic: String,
+    /// the partition all the records are published to unless the partitioner picks them
+    partition: Option<i32>,
+}
+
+impl Publisher {
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: `broker[,broker...]/topic[?partitions=0]`, at most a single partition can be given
+    pub fn new(target: &str) -> Result<Self, error::MrgError> {
+        let topic = Topic::parse(&format!("{}{}", SCHEME, target))?;
+        let partition = match topic.partitions.as_deref() {
+            None => None,
+            Some([partition]) => Some(*partition),
+            Some(_) => {
+                return Err(error::MrgError {
+                    msg: format!(
+                    "'{}' is not a valid Kafka sink, records are published to a single partition",
+                    target
+                ),
+                })
+            }
+        };
+        let producer = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", &topic.brokers)
+            .set("enable.idempotence", "true")
+            .create_with_context(Deliveries::default())?;
+        Ok(Publisher {
+            producer,
+            topic: topic.name,
+            partition,
+        })
+    }
+
+    ///
+    /// The first record the brokers failed to accept, if any
+    fn failure(&self) -> std::io::Result<()> {
+        match self.producer.context().failure.lock() {
+            Ok(failure) => match failure.as_ref() {
+                Some(e) => Err(std::io::Error::other(format!(
+                    "cannot publish to Kafka: {}",
+                    e
+                ))),
+                None => Ok(()),
+            },
+            Err(_) => Ok(()),
+        }
+    }
+
+    ///
+    /// Waits for the brokers to accept the rest of the records
+    pub fn finish(self) -> Result<(), error::MrgError> {
+        self.producer.flush(TIMEOUT)?;
+        Ok(self.failure()?)
+    }
+}
+
+impl Sink for Publisher {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        let mut record = BaseRecord::<(), str>::to(&self.topic)
+            .payload(line)
+            .timestamp(ts.div_euclid(1_000_000) as i64);
+        if let Some(partition) = self.partition {
+            record = record.partition(partition);
+        }
+        loop {
+            match self.producer.send(record) {
+                Ok(()) => break,
+                // the queue of the producer is full until the brokers accept some of the records
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), rejected)) => {
+                    self.producer.poll(Duration::from_millis(100));
+                    record = rejected;
+                }
+                Err((e, _)) => {
+                    return Err(std::io::Error::other(format!(
+                        "cannot publish to Kafka: {}",
+                        e
+                    )))
+                }
+            }
+        }
+        self.producer.poll(Duration::ZERO);
+        self.failure()
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.producer
+            .flush(TIMEOUT)
+            .map_err(|e| std::io::Error::other(format!("cannot publish to Kafka: {}", e)))?;
+        self.failure()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_url() {
+        assert_eq!(
+            crate::kafka::Topic {
+                brokers: "a:9092,b:9092".to_string(),
+                name: "logs".to_string(),
+                partitions: Some(vec![0, 2]),
+            },
+            crate::kafka::Topic::parse("kafka://a:9092,b:9092/logs?partitions=0,2").unwrap()
+        );
+        assert_eq!(
+            None,
+            crate::kafka::Topic::parse("kafka://a/logs")
+                .unwrap()
+                .partitions
+        );
+        assert!(crate::kafka::Topic::parse("kafka://a").is_err());
+        assert!(crate::kafka::Topic::parse("kafka:///logs").is_err());
+        assert!(crate::kafka::Topic::parse("kafka://a/logs?partitions=x").is_err());
+        assert!(crate::kafka::Topic::parse("kafka://a/logs?offset=1").is_err());
+    }
+
+    #[test]
+    fn publisher() {
+        assert_eq!(
+            Some(1),
+            crate::kafka::Publisher::new("localhost:9092/logs?partitions=1")
+                .unwrap()
+                .partition
+        );
+        assert!(crate::kafka::Publisher::new("localhost:9092/logs?partitions=0,1").is_err());
+        assert!(crate::kafka::Publisher::new("localhost:9092").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::error;
+
+///
+/// Language of month and weekday names in datetimes, e.g. `Di, 02 Mär 2024 15:04:05 +0100`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    De,
+    Fr,
+    Ru,
+}
+
+/// Localized names, full and abbreviated, along with English abbreviations chrono understands
+const DE: &[(&str, &str)] = &[
+    ("januar", "Jan"),
+    ("jan", "Jan"),
+    ("jänner", "Jan"),
+    ("jän", "Jan"),
+    ("februar", "Feb"),
+    ("feb", "Feb"),
+    ("märz", "Mar"),
+    ("mär", "Mar"),
+    ("mrz", "Mar"),
+    ("april", "Apr"),
+    ("apr", "Apr"),
+    ("mai", "May"),
+    ("juni", "Jun"),
+    ("jun", "Jun"),
+    ("juli", "Jul"),
+    ("jul", "Jul"),
+    ("august", "Aug"),
+    ("aug", "Aug"),
+    ("september", "Sep"),
+    ("sept", "Sep"),
+    ("sep", "Sep"),
+    ("oktober", "Oct"),
+    ("okt", "Oct"),
+    ("november", "Nov"),
+    ("nov", "Nov"),
+    ("dezember", "Dec"),
+    ("dez", "Dec"),
+    ("montag", "Mon"),
+    ("mo", "Mon"),
+    ("dienstag", "Tue"),
+    ("di", "Tue"),
+    ("mittwoch", "Wed"),
+    ("mi", "Wed"),
+    ("donnerstag", "Thu"),
+    ("do", "Thu"),
+    ("freitag", "Fri"),
+    ("fr", "Fri"),
+    ("samstag", "Sat"),
+    ("sonnabend", "Sat"),
+    ("sa", "Sat"),
+    ("sonntag", "Sun"),
+    ("so", "Sun"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("janvier", "Jan"),
+    ("janv", "Jan"),
+    ("février", "Feb"),
+    ("févr", "Feb"),
+    ("fevrier", "Feb"),
+    ("fevr", "Feb"),
+    ("mars", "Mar"),
+    ("avril", "Apr"),
+    ("avr", "Apr"),
+    ("mai", "May"),
+    ("juin", "Jun"),
+    ("juillet", "Jul"),
+    ("juil", "Jul"),
+    ("août", "Aug"),
+    ("aout", "Aug"),
+    ("septembre", "Sep"),
+    ("sept", "Sep"),
+    ("octobre", "Oct"),
+    ("oct", "Oct"),
+    ("novembre", "Nov"),
+    ("nov", "Nov"),
+    ("décembre", "Dec"),
+    ("déc", "Dec"),
+    ("decembre", "Dec"),
+    ("dec", "Dec"),
+    ("lundi", "Mon"),
+    ("lun", "Mon"),
+    ("mardi", "Tue"),
+    ("mar", "Tue"),
+    ("mercredi", "Wed"),
+    ("mer", "Wed"),
+    ("jeudi", "Thu"),
+    ("jeu", "Thu"),
+    ("vendredi", "Fri"),
+    ("ven", "Fri"),
+    ("samedi", "Sat"),
+    ("sam", "Sat"),
+    ("dimanche", "Sun"),
+    ("dim", "Sun"),
+];
+
+/// Months are written both in nominative and in genitive case, e.g. `2 января`
+const RU: &[(&str, &str)] = &[
+    ("январь", "Jan"),
+    ("января", "Jan"),
+    ("янв", "Jan"),
+    ("февраль", "Feb"),
+    ("февраля", "Feb"),
+    ("фев", "Feb"),
+    ("март", "Mar"),
+    ("марта", "Mar"),
+    ("мар", "Mar"),
+    ("апрель", "Apr"),
+    ("апреля", "Apr"),
+    ("апр", "Apr"),
+    ("май", "May"),
+    ("мая", "May"),
+    ("июнь", "Jun"),
+    ("июня", "Jun"),
+    ("июн", "Jun"),
+    ("июль", "Jul"),
+    ("июля", "Jul"),
+    ("июл", "Jul"),
+    ("август", "Aug"),
+    ("августа", "Aug"),
+    ("авг", "Aug"),
+    ("сентябрь", "Sep"),
+    ("сентября", "Sep"),
+    ("сент", "Sep"),
+    ("сен", "Sep"),
+    ("октябрь", "Oct"),
+    ("октября", "Oct"),
+    ("окт", "Oct"),
+    ("ноябрь", "Nov"),
+    ("ноября", "Nov"),
+    ("ноя", "Nov"),
+    ("декабрь", "Dec"),
+    ("декабря", "Dec"),
+    ("дек", "Dec"),
+    ("понедельник", "Mon"),
+    ("пн", "Mon"),
+    ("вторник", "Tue"),
+    ("вт", "Tue"),
+    ("среда", "Wed"),
+    ("ср", "Wed"),
+    ("четверг", "Thu"),
+    ("чт", "Thu"),
+    ("пятница", "Fri"),
+    ("пт", "Fri"),
+    ("суббота", "Sat"),
+    ("сб", "Sat"),
+    ("воскресенье", "Sun"),
+    ("вс", "Sun"),
+];
+
+impl Locale {
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "de" => Ok(Locale::De),
+            "fr" => Ok(Locale::Fr),
+            "ru" => Ok(Locale::Ru),
+            _ => Err(error::MrgError {
+                msg: format!("unknown locale '{}', expected one of de, fr, ru", name),
+            }),
+        }
+    }
+
+    fn names(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::De => DE,
+            Locale::Fr => FR,
+            Locale::Ru => RU,
+        }
+    }
+
+    ///
+    /// Replaces localized month and weekday names with English ones,
+    /// the dot after an abbreviation, e.g. `janv.`, is dropped along with it.
+    ///
+    /// returns: the datetime in English, if there was anything to replace
+    pub fn translate(self, value: &str) -> Option<String> {
+        let mut translated = String::with_capacity(value.len());
+        let mut replaced = false;
+        let mut rest = value;
+        while !rest.is_empty() {
+            let word_len = rest
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(rest.len());
+            if word_len == 0 {
+                let c = rest.chars().next()?;
+                translated.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let (word, after) = rest.split_at(word_len);
+            let english = self
+                .names()
+                .iter()
+                .find(|(name, _)| name.chars().eq(word.chars().flat_map(char::to_lowercase)))
+                .map(|(_, english)| *english);
+            match english {
+                Some(english) => {
+                    translated.push_str(english);
+                    replaced = true;
+                    rest = after.strip_prefix('.').unwrap_or(after);
+                }
+                None => {
+                    translated.push_str(word);
+                    rest = after;
+                }
+            }
+        }
+        replaced.then_some(translated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::Locale;
+    use crate::timestamp::{Calendar, Zone};
+
+    fn parse(locale: Locale, value: &str) -> Result<crate::timestamp::Timestamp, String> {
+        let calendar = Calendar {
+            zone: Some(Zone::Utc),
+            year: Some(2024),
+            locale: Some(locale),
+            strict: false,
+        };
+        calendar.parse(value)
+    }
+
+    #[test]
+    fn de() {
+        // 2024-03-05T14:04:05Z
+        assert_eq!(
+            Ok(1709647445000000000),
+            parse(Locale::De, "Di, 05 Mär 2024 15:04:05 +0100")
+        );
+        // 2024-01-02T15:04:05Z
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::De, "2. Januar 2024 15:04:05")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::De, "Dienstag, 2. Januar 2024 15:04:05")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::De, "2. Jan. 2024 16:04:05 +0100")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::De, "Jän  2 15:04:05")
+        );
+        assert!(parse(Locale::De, "2. Janvier 2024 15:04:05").is_err());
+    }
+
+    #[test]
+    fn fr() {
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::Fr, "janv.  2 15:04:05")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::Fr, "2 janvier 2024 15:04:05")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::Fr, "mardi, 02 janv. 2024 15:04:05 +0000")
+        );
+    }
+
+    #[test]
+    fn ru() {
+        // 2024-03-03T10:00:00Z
+        assert_eq!(
+            Ok(1709460000000000000),
+            parse(Locale::Ru, "3 марта 2024 10:00:00")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::Ru, "2 Января 2024 18:04:05 +0300")
+        );
+        assert_eq!(
+            Ok(1704207845000000000),
+            parse(Locale::Ru, "Вт, 02 янв 2024 15:04:05 +0000")
+        );
+    }
+
+    #[test]
+    fn untranslated() {
+        assert_eq!(None, Locale::De.translate("2024-01-02T15:04:05Z"));
+        assert!(Locale::parse("en").is_err());
+    }
+}
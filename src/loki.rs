@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use crate::error;
+use crate::output::Sink;
+use crate::timestamp::Timestamp;
+
+/// Records pushed in a request
+const BATCH_SIZE: usize = 1000;
+
+/// Label every stream has, Loki requires at least one
+const JOB: (&str, &str) = ("job", "jmrg");
+
+type Labels = BTreeMap<String, String>;
+
+///
+/// Loki the records are pushed to in batches, they are grouped into streams by the labels
+pub struct Loki {
+    /// the push endpoint, e.g. `http://host:3100/loki/api/v1/push`
+    url: String,
+    /// fields of the records whose values become the labels of their streams
+    fields: Vec<String>,
+    /// records which are not pushed yet, by the labels of their streams
+    streams: BTreeMap<Labels, Vec<(Timestamp, String)>>,
+    pending: usize,
+}
+
+impl Loki {
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: Loki address, e.g. `http://host:3100`
+    /// * `fields`: fields of the records whose values become the labels of their streams
+    pub fn new(url: &str, fields: Vec<String>) -> Self {
+        Loki {
+            url: format!("{}/loki/api/v1/push", url.trim_end_matches('/')),
+            fields,
+            streams: BTreeMap::new(),
+            pending: 0,
+        }
+    }
+
+    ///
+    /// Labels of the stream of the record, the fields which are missing or are objects are skipped
+    fn labels_of(&self, line: &str) -> Labels {
+        let mut labels = Labels::from([(JOB.0.to_string(), JOB.1.to_string())]);
+        let record = match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line)
+        {
+            Ok(record) => record,
+            Err(_) => return labels,
+        };
+        for field in &self.fields {
+            let value = match record.get(field) {
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(serde_json::Value::Number(value)) => value.to_string(),
+                Some(serde_json::Value::Bool(value)) => value.to_string(),
+                _ => continue,
+            };
+            // label names are restricted to [a-zA-Z_][a-zA-Z0-9_]*
+            let name = field.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+            labels.insert(name, value);
+        }
+        labels
+    }
+
+    ///
+    /// Pushes the records gathered so far
+    fn push(&mut self) -> std::io::Result<()> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+        let streams: Vec<serde_json::Value> = std::mem::take(&mut self.streams)
+            .into_iter()
+            .map(|(labels, values)| {
+                let values: Vec<[String; 2]> = values
+                    .into_iter()
+                    .map(|(ts, line)| [ts.to_string(), line])
+                    .collect();
+                serde_json::json!({"stream": labels, "values": values})
+            })
+            .collect();
+        self.pending = 0;
+        ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&serde_json::json!({ "streams": streams }).to_string())
+            .map_err(|e| std::io::Error::other(format!("cannot push to Loki: {}", e)))?;
+        Ok(())
+    }
+
+    ///
+    /// Pushes the rest of the records
+    pub fn finish(mut self) -> Result<(), error::MrgError> {
+        Ok(self.push()?)
+    }
+}
+
+impl Sink for Loki {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        let labels = self.labels_of(line);
+        self.streams
+            .entry(labels)
+            .or_default()
+            .push((ts, line.to_string()));
+        self.pending += 1;
+        match self.pending >= BATCH_SIZE {
+            true => self.push(),
+            false => Ok(()),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.push()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    use crate::output::Sink;
+
+    ///
+    /// Accepts a request and answers it with `204 No Content`, the request line and the body are returned
+    pub fn receive(listener: std::net::TcpListener) -> std::thread::JoinHandle<(String, String)> {
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            (request_line, String::from_utf8(body).unwrap())
+        })
+    }
+
+    #[test]
+    fn push() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = receive(listener);
+        let mut loki = crate::loki::Loki::new(&url, vec!["service".to_string()]);
+        loki.emit(1_000, r#"{"service":"api","msg":"a"}"#).unwrap();
+        loki.emit(2_000, "not json").unwrap();
+        loki.emit(3_000, r#"{"service":"api","msg":"b"}"#).unwrap();
+        loki.finish().unwrap();
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!("POST /loki/api/v1/push HTTP/1.1", request_line.trim_end());
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            serde_json::json!({"streams": [
+                {"stream": {"job": "jmrg"}, "values": [["2000", "not json"]]},
+                {"stream": {"job": "jmrg", "service": "api"}, "values": [
+                    ["1000", r#"{"service":"api","msg":"a"}"#],
+                    ["3000", r#"{"service":"api","msg":"b"}"#],
+                ]},
+            ]}),
+            body
+        );
+    }
+}
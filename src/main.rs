@@ -1,168 +1,274 @@
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Lines, Read, Write};
-
-use infer::MatcherType;
-use serde::Deserializer;
-use serde_json;
+use std::io::{BufRead, BufReader, IsTerminal, Lines};
+use std::sync::Arc;
 
+#[cfg(feature = "azure")]
+mod azure;
+mod cascade;
+#[cfg(any(feature = "parquet", feature = "arrow"))]
+mod columnar;
 mod config;
+mod cri;
+mod detect;
+mod docker;
 mod error;
+mod filter;
+mod follow;
+#[cfg(feature = "gcs")]
+mod gcs;
+mod gelf;
+mod html;
+mod input;
+mod journal;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod locale;
+mod loki;
+mod meta;
+mod otlp;
+mod output;
+mod s3;
+mod seekable;
+mod skew;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod ssh;
+mod syslog;
+mod tai64;
+mod timestamp;
+mod transform;
 
 const BUF_SIZE: usize = 1024 * 1024;
 
-///
-/// The function attempts to open a file,
-/// infers its type (e.g., whether it's an archive like gzip or bzip2),
-/// and returns a corresponding Read trait object that can be used to read the file's contents.
-/// If the file is not an archive or if it's an unsupported archive, tries to read it as is.
-///
-/// # Arguments
-///
-/// * `path`: path to the file in the filesystem
-///
-/// returns: Result<Box<dyn Read>, MrgError>
-///
-/// # Examples
-///
-/// ```
-/// let f = open_file("/var/log/vector.log")
-/// ```
-fn open_file(path: &str) -> Result<Box<dyn Read>, error::MrgError> {
-    let file: File = File::open(path)?;
-    match infer::get_from_path(path).unwrap() {
-        Some(inferred_type) => match inferred_type.matcher_type() {
-            MatcherType::Archive => match inferred_type.extension() {
-                "gz" => Ok(Box::new(flate2::read::GzDecoder::new(file))),
-                "bz2" => Ok(Box::new(bzip2::read::BzDecoder::new(file))),
-                // in case it's not archive we know about, we try to parse it as is
-                _ => Ok(Box::new(file)),
-            },
-            // in case it's not archive we try to parse it as is
-            _ => Ok(Box::new(file)),
-        },
-        // in case we couldn't not infer type, we try to parse it as is
-        None => Ok(Box::new(file)),
-    }
-}
-
-fn make_readers(paths: &Vec<String>) -> Result<Vec<BufReader<Box<dyn Read>>>, error::MrgError> {
-    Ok(paths
-        .into_iter()
-        .map(|path| open_file(path))
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .map(|s| BufReader::with_capacity(BUF_SIZE, s))
-        .collect())
-}
-
-struct Source<'a, Input: BufRead> {
-    input: Lines<Input>,
+struct Source<'a, R: BufRead> {
+    input: cri::Join<Lines<R>>,
+    path: Arc<str>,
+    format: input::Format,
     raw_line: String,
-    ts: i64,
-    keys: &'a HashSet<String>,
+    ts: timestamp::Timestamp,
+    /// the record lacks the keys and follows the preceding one
+    inherited: bool,
+    parser: &'a timestamp::Parser,
 }
 
-impl<'a, Input: BufRead> Source<'a, Input> {
-    fn new(input: Input, keys: &'a HashSet<String>) -> Option<Self> {
-        Self {
-            input: input.lines(),
-            raw_line: String::new(),
-            ts: -1,
-            keys,
+impl<'a, R: BufRead> Source<'a, R> {
+    ///
+    /// The source positioned at the first record of the input, if the input has any
+    fn new(mut input: input::Input<R>, parser: &'a timestamp::Parser) -> Option<Self> {
+        if input.format == input::Format::Ndjson {
+            parser.sample(&input.path, &mut input.reader);
+        }
+        let mut lines = cri::Join::new(input.reader.lines(), input.format == input::Format::Cri);
+        // there is nothing to inherit the timestamp from, but the fallback
+        let fallback = parser
+            .fallback
+            .and_then(|fallback| fallback.of(&input.path));
+        let (ts, raw_line, inherited) =
+            next_record(&mut lines, parser, &input.path, input.format, fallback)?;
+        if inherited {
+            eprintln!(
+                "'{}': the first records lack the keys, they are ordered by the fallback",
+                input.path
+            );
         }
-        .fetch_next()
+        Some(Self {
+            input: lines,
+            path: input.path,
+            format: input.format,
+            raw_line,
+            ts,
+            inherited,
+            parser,
+        })
     }
 
     fn fetch_next(mut self) -> Option<Self> {
-        while let Some(next_line) = self.input.next() {
-            match next_line {
-                Ok(raw_line) => {
-                    let mut des = serde_json::de::Deserializer::from_str(raw_line.as_str());
-                    match des.deserialize_map(EntryVisitor { keys: self.keys }) {
-                        Ok(ts) => {
-                            self.ts = ts;
-                            self.raw_line = raw_line;
-                            return Some(self);
-                        }
-                        Err(e) => {
-                            eprintln!("cannot parse entry: {}", e);
-                        }
-                    }
-                }
+        let (ts, raw_line, inherited) = next_record(
+            &mut self.input,
+            self.parser,
+            &self.path,
+            self.format,
+            // the records at the beginning keep the fallback until one of them has the keys
+            Some(self.ts).filter(|_| self.parser.inherit_ts || self.inherited),
+        )?;
+        self.inherited = inherited;
+        (self.ts, self.raw_line) = if self.parser.monotonic && ts < self.ts {
+            clamp(raw_line, self.parser, &self.path, self.format, self.ts)
+        } else {
+            (ts, raw_line)
+        };
+        Some(self)
+    }
+}
+
+///
+/// Reads lines until one of them is parsed, the lines which can't be parsed are reported and skipped
+///
+/// returns: timestamp, the line to emit and whether the timestamp is inherited,
+/// or nothing when the input is over
+fn next_record(
+    lines: &mut impl Iterator<Item = std::io::Result<String>>,
+    parser: &timestamp::Parser,
+    path: &str,
+    format: input::Format,
+    inherited: Option<timestamp::Timestamp>,
+) -> Option<(timestamp::Timestamp, String, bool)> {
+    for next_line in lines {
+        match next_line {
+            Ok(raw_line) => match parse_or_inherit(raw_line, parser, path, format, inherited) {
+                Ok(record) => return Some(record),
+                Err(e) if timestamp::is_filtered(&e) => {}
                 Err(e) => {
-                    eprintln!("cannot get next line: {}", e);
+                    eprintln!("cannot parse entry: {}", e);
                 }
+            },
+            // the rest of the input, e.g. truncated archive, is most likely unreadable as well
+            Err(e) => {
+                eprintln!(
+                    "cannot get next line, skipping the rest of the input: {}",
+                    e
+                );
+                return None;
             }
         }
-        None
     }
+    None
 }
 
 impl<'a, T: BufRead> Eq for Source<'a, T> {}
 
 impl<'a, T: BufRead> PartialEq<Self> for Source<'a, T> {
     fn eq(&self, other: &Self) -> bool {
-        self.ts == other.ts
+        self.ts == other.ts && self.inherited == other.inherited
     }
 }
 
 impl<'a, T: BufRead> PartialOrd<Self> for Source<'a, T> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.ts.cmp(&self.ts))
+        Some(self.cmp(other))
     }
 }
 
 impl<T: BufRead> Ord for Source<'_, T> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.ts.cmp(&self.ts)
+        // records which inherit the timestamp stay right after the record they inherit it from
+        other
+            .ts
+            .cmp(&self.ts)
+            .then(self.inherited.cmp(&other.inherited))
     }
 }
 
-struct EntryVisitor<'a> {
-    keys: &'a HashSet<String>,
-}
-
-impl<'de> serde::de::Visitor<'de> for EntryVisitor<'de> {
-    type Value = i64;
-
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, "map with keys from provided set")
-    }
-
-    #[inline]
-    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
-    where
-        M: serde::de::MapAccess<'de>,
-    {
-        let mut ts: Option<i64> = None;
-
-        while let Some(k) = map.next_key::<&str>()? {
-            if ts.is_none() && self.keys.contains(k) {
-                ts = Some(map.next_value::<i64>()?);
-            } else {
-                map.next_value::<serde::de::IgnoredAny>()?;
+///
+/// Extracts the timestamp out of the raw line according to the format of the input.
+/// Formats which wrap records may emit something else than the raw line.
+///
+/// Timestamps of the inputs with skewed clocks are shifted.
+///
+/// returns: timestamp and the line to emit
+fn parse_line(
+    raw_line: String,
+    parser: &timestamp::Parser,
+    path: &str,
+    format: input::Format,
+) -> Result<(timestamp::Timestamp, String), serde_json::Error> {
+    let (ts, line) = match format {
+        input::Format::Ndjson => {
+            let ts = parser.parse(&raw_line, path)?;
+            match parser.humanize {
+                Some(_) => (ts, parser.humanize(&raw_line, path, ts).unwrap_or(raw_line)),
+                None => (ts, raw_line),
             }
         }
+        input::Format::Journal => {
+            let ts = journal::parse_ts(&raw_line)?;
+            parser.check(&raw_line)?;
+            (ts, raw_line)
+        }
+        input::Format::Docker { unwrap } => {
+            docker::parse(raw_line, unwrap, |payload| parser.check(payload))?
+        }
+        input::Format::Cri => {
+            let (ts, payload) = cri::parse(&raw_line)?;
+            parser.check(&payload)?;
+            (ts, payload)
+        }
+        input::Format::Run => cascade::parse(&raw_line)?,
+    };
+    Ok((ts + parser.offset(path), line))
+}
 
-        ts.ok_or(serde::de::Error::custom("no fields of the provided set"))
+///
+/// Extracts the timestamp out of the raw line like `parse_line` does,
+/// NDJSON records which lack the keys take `inherited` timestamp over, if there is any.
+///
+/// returns: timestamp, the line to emit and whether the timestamp is inherited
+fn parse_or_inherit(
+    raw_line: String,
+    parser: &timestamp::Parser,
+    path: &str,
+    format: input::Format,
+    inherited: Option<timestamp::Timestamp>,
+) -> Result<(timestamp::Timestamp, String, bool), serde_json::Error> {
+    // the line is kept aside only when it may be needed
+    let kept = inherited
+        .filter(|_| format == input::Format::Ndjson)
+        .map(|ts| (ts, raw_line.clone()));
+    match parse_line(raw_line, parser, path, format) {
+        Ok((ts, line)) => Ok((ts, line, false)),
+        Err(e) => match kept {
+            Some((ts, line)) if timestamp::is_missing_key(&e) => Ok((ts, line, true)),
+            _ => Err(e),
+        },
     }
 }
 
-pub fn run<Input: BufRead, Output: Write>(
-    keys: Vec<String>,
-    ins: Vec<Input>,
-    out: &mut Output,
+///
+/// The record which goes back in time is emitted with the timestamp it's behind,
+/// the timestamp is rewritten in NDJSON records the timestamp is taken from a single field of.
+///
+/// returns: the timestamp and the line to emit
+fn clamp(
+    line: String,
+    parser: &timestamp::Parser,
+    path: &str,
+    format: input::Format,
+    ts: timestamp::Timestamp,
+) -> (timestamp::Timestamp, String) {
+    let line = match format {
+        // the records keep the clock of their input
+        input::Format::Ndjson => parser
+            .rewrite(&line, path, ts - parser.offset(path))
+            .unwrap_or(line),
+        _ => line,
+    };
+    (ts, line)
+}
+
+///
+/// Merges the inputs, every record which is not filtered out is passed to `emit`
+/// along with its timestamp and the path of its input, in the order of timestamps.
+/// The merge stops once the records of all the inputs are past the upper bound.
+pub fn merge<R: BufRead>(
+    parser: &timestamp::Parser,
+    bounds: timestamp::Bounds,
+    ins: Vec<input::Input<R>>,
+    mut emit: impl FnMut(timestamp::Timestamp, &str, &str) -> std::io::Result<()>,
 ) -> Result<(), error::MrgError> {
-    let key_set: HashSet<String> = HashSet::from_iter(keys.into_iter());
-    let mut sources: BinaryHeap<Source<Input>> = ins
+    let mut sources: BinaryHeap<Source<R>> = ins
         .into_iter()
-        .filter_map(|input: Input| Source::new(input, &key_set))
+        .filter_map(|input: input::Input<R>| Source::new(input, parser))
         .collect();
     while !sources.is_empty() {
-        let source: Source<Input> = sources.pop().unwrap();
-        writeln!(out, "{}", source.raw_line.as_str())?;
+        let source: Source<R> = sources.pop().unwrap();
+        // the inputs are sorted, the rest of the records of every one of them are past the bound as well
+        if bounds.passed(source.ts) {
+            break;
+        }
+        if bounds.contains(source.ts) {
+            emit(source.ts, &source.path, source.raw_line.as_str())?;
+        }
         if let Some(s) = source.fetch_next() {
             sources.push(s);
         }
@@ -170,39 +276,330 @@ pub fn run<Input: BufRead, Output: Write>(
     Ok(())
 }
 
+pub fn run<R: BufRead, Out: output::Sink>(
+    parser: &timestamp::Parser,
+    bounds: timestamp::Bounds,
+    ins: Vec<input::Input<R>>,
+    transform: &mut transform::Transform,
+    out: &mut Out,
+) -> Result<(), error::MrgError> {
+    merge(parser, bounds, ins, |ts, path, raw_line| {
+        out.emit(ts, &transform.apply(ts, path, raw_line))
+    })
+}
+
 fn main() -> Result<(), error::MrgError> {
     let cmd_args: Vec<String> = env::args().collect();
     let args: config::Arguments = config::parse(cmd_args)?;
 
-    let sources: Vec<BufReader<Box<dyn Read>>> = make_readers(&args.paths)?;
-    let mut output = BufWriter::with_capacity(BUF_SIZE, std::io::stdout());
-    run(args.keys, sources, &mut output)
+    // prioritized keys don't have to be repeated with -k
+    let keys = HashSet::from_iter(args.keys.into_iter().chain(args.key_priority.clone()));
+    let mut parser = timestamp::Parser::new(keys);
+    parser.priority = HashMap::from_iter(
+        args.key_priority
+            .into_iter()
+            .enumerate()
+            .map(|(rank, key)| (key, rank)),
+    );
+    parser.calendar = timestamp::Calendar {
+        zone: args.zone,
+        year: args.year,
+        locale: args.locale,
+        strict: args.strict_datetime,
+    };
+    parser.unit = args.unit;
+    parser.units = args.units;
+    parser.keys_for = args.keys_for;
+    parser.offsets = args.offsets;
+    parser.compose = args.compose;
+    parser.ts_regex = args.ts_regex;
+    parser.auto_key = args.auto_key;
+    parser.monotonic = args.monotonic;
+    parser.inherit_ts = args.inherit_ts;
+    parser.fallback = args.fallback;
+    parser.humanize = args.humanize_ts;
+    parser.filter = args.filter;
+    let color = args
+        .color
+        .enabled(args.output.is_empty() && args.sink.is_empty() && std::io::stdout().is_terminal());
+    let mut fields: Vec<(String, serde_json::Value)> = args
+        .add_fields
+        .into_iter()
+        .map(|(key, value)| (key, value.into()))
+        .collect();
+    if let Some(field) = args.add_host {
+        fields.push((field, meta::hostname()?.into()));
+    }
+    if let Some(field) = args.add_meta {
+        fields.push((field, meta::of_run()));
+    }
+    let mut transform = transform::Transform {
+        normalize: args.normalize_ts,
+        tag_source: args.tag_source,
+        labels: args.labels,
+        prefix: args.prefix,
+        seq: args.add_seq.map(transform::Seq::new),
+        pretty: args.pretty,
+        compact: args.compact,
+        canonical: args.canonical,
+        format: args.output_format,
+        columns: args.columns.clone().map(transform::Columns::new),
+        template: args.template,
+        color,
+        palette: (color && args.prefix).then(transform::Palette::default),
+        select: args.select,
+        drop: args.drop,
+        flatten: args.flatten,
+        fields,
+        fields_for: args
+            .add_fields_for
+            .into_iter()
+            .map(|(path, fields)| {
+                let fields = fields.into_iter().map(|(key, value)| (key, value.into()));
+                (path, fields.collect())
+            })
+            .collect(),
+        redact: args.redact,
+        redact_patterns: args.redact_patterns,
+        pseudonymize: (!args.hash_fields.is_empty()).then(|| {
+            transform::Pseudonymize::new(args.hash_fields, args.hash_salt.as_deref().unwrap_or(""))
+        }),
+        max_field_len: args.max_field_len,
+        index: args.index.clone(),
+    };
+    let ts_of = |path: &str, raw_line: &str| {
+        parse_line(raw_line.to_string(), &parser, path, args.format)
+            .ok()
+            .map(|(ts, _)| ts)
+    };
+    let bounds = timestamp::Bounds {
+        since: args.since,
+        until: args.until,
+    };
+    let since = bounds.since.map(|ts| input::Since { ts, ts_of: &ts_of });
+    let mut paths: Vec<String> = input::expand_globs(args.paths)?;
+    if let Some(manifest) = &args.files_from {
+        if manifest == input::STDIN_PATH && paths.iter().any(|path| path == input::STDIN_PATH) {
+            return Err(error::MrgError {
+                msg: "standard input can't be both the list of files and an input".to_string(),
+            });
+        }
+        // paths from the manifest are taken literally, they are not globs
+        paths.extend(input::read_manifest(manifest, args.manifest_separator)?);
+    }
+    let dirs: Vec<String> = paths
+        .iter()
+        .filter(|path| std::path::Path::new(path).is_dir())
+        .cloned()
+        .collect();
+    let paths: Vec<String> = input::dedup(input::expand_dirs(paths, &args.walk)?);
+    let open = |paths: &[String]| {
+        input::make_readers(paths, since.as_ref(), args.format, &args.codecs, args.codec)
+    };
+    let mut outputs: Vec<output::Output> = Vec::new();
+    for remote in &args.sink {
+        outputs.push(match remote {
+            output::Remote::Loki(url) => {
+                output::Output::Loki(Box::new(loki::Loki::new(url, args.loki_labels.clone())))
+            }
+            output::Remote::Gelf(url) => output::Output::Gelf(Box::new(gelf::Gelf::connect(url)?)),
+            output::Remote::Syslog(url) => {
+                output::Output::Syslog(Box::new(syslog::Syslog::connect(url)?))
+            }
+            output::Remote::Kafka(target) => output::Output::kafka(target)?,
+            output::Remote::Otlp(url) => output::Output::Otlp(Box::new(otlp::Otlp::new(url))),
+        });
+    }
+    // the records are written out unless they are only forwarded
+    let files: Vec<(Option<&str>, output::Scope)> =
+        match (args.output.is_empty(), args.sink.is_empty()) {
+            (true, true) => vec![(None, output::Scope::default())],
+            (true, false) => Vec::new(),
+            (false, _) => args
+                .output
+                .iter()
+                .map(|output| {
+                    let path =
+                        Some(output.path.as_str()).filter(|path| *path != output::STDOUT_PATH);
+                    (path, output.scope.clone())
+                })
+                .collect(),
+        };
+    for (path, scope) in files {
+        let opened = match args.output_format {
+            transform::OutputFormat::Parquet | transform::OutputFormat::Arrow => {
+                output::Output::columnar(args.output_format, path, args.schema.as_deref())?
+            }
+            transform::OutputFormat::Sqlite => {
+                output::Output::sqlite(path.unwrap_or_default(), &args.table)?
+            }
+            _ => output::Output::open(
+                path,
+                args.compress,
+                args.split_by,
+                args.split_size,
+                if args.print0 { b'\0' } else { b'\n' },
+            )?,
+        };
+        outputs.push(match scope == output::Scope::default() {
+            true => opened,
+            false => output::Output::Scoped(Box::new(output::Scoped::new(
+                scope,
+                args.columns.clone(),
+                args.index.clone(),
+                opened,
+            ))),
+        });
+    }
+    let mut output = match outputs.len() {
+        1 => outputs.remove(0),
+        _ => output::Output::Tee(outputs),
+    };
+    if args.array {
+        output = output::Output::Array(Box::new(output::Array::new(output)));
+    }
+    if args.output_format == transform::OutputFormat::Html {
+        output = output::Output::Html(Box::new(html::Html::new(output)));
+    }
+    if let Some(max_open) = args
+        .max_open_files
+        .filter(|max_open| paths.len() > *max_open)
+    {
+        cascade::run(
+            &parser,
+            bounds,
+            &paths,
+            &open,
+            max_open,
+            &mut transform,
+            &mut output,
+        )?;
+        return output.finish();
+    }
+    let mut sources: Vec<input::Input<BufReader<input::Stream>>> = open(&paths)?;
+    if let Some(max) = args.auto_skew {
+        skew::apply(&mut parser, &mut sources, max);
+    }
+    if args.follow {
+        let watch = follow::Watch {
+            dirs,
+            walk: &args.walk,
+            format: args.format,
+            codec: args.codec,
+        };
+        let watch = Some(&watch).filter(|watch| !watch.dirs.is_empty());
+        return follow::run(
+            &parser,
+            bounds.since,
+            sources,
+            &mut transform,
+            &mut output,
+            timestamp::from_millis(args.reorder_window),
+            watch,
+        );
+    }
+    run(&parser, bounds, sources, &mut transform, &mut output)?;
+    output.finish()
 }
 
 #[cfg(test)]
 mod tests {
-    use std::io::{BufRead, BufReader};
+    use std::io::BufReader;
+
+    ///
+    /// Merges NDJSON inputs with the parser and returns the output
+    fn run_lines(
+        parser: &crate::timestamp::Parser,
+        bounds: crate::timestamp::Bounds,
+        inputs: &[&str],
+    ) -> String {
+        let mut buf = std::io::BufWriter::new(Vec::new());
+        let ins = inputs
+            .iter()
+            .map(|input| crate::input::Input {
+                path: std::sync::Arc::from("-"),
+                format: crate::input::Format::Ndjson,
+                reader: BufReader::new(stringreader::StringReader::new(input)),
+            })
+            .collect();
+        crate::run(parser, bounds, ins, &mut Default::default(), &mut buf).unwrap();
+        String::from_utf8(buf.into_inner().unwrap()).unwrap()
+    }
+
+    fn parser() -> crate::timestamp::Parser {
+        crate::timestamp::Parser::new(std::collections::HashSet::from([String::from("t")]))
+    }
+
+    ///
+    /// Whether the line of the format passes `--where` condition
+    fn passes(condition: &str, format: crate::input::Format, line: String) -> bool {
+        let mut parser = parser();
+        parser.filter = Some(crate::filter::Filter {
+            conditions: vec![crate::filter::Condition::parse(condition).unwrap()],
+            any: false,
+        });
+        match crate::parse_line(line, &parser, "-", format) {
+            Ok(_) => true,
+            Err(e) if crate::timestamp::is_filtered(&e) => false,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn where_docker() {
+        let line = |level: &str| {
+            format!(
+                r#"{{"log":"{{\"level\":\"{}\"}}\n","stream":"stdout","time":"2024-01-02T03:04:05Z"}}"#,
+                level
+            )
+        };
+        for unwrap in [false, true] {
+            let format = crate::input::Format::Docker { unwrap };
+            assert!(passes("level=error", format, line("error")));
+            assert!(!passes("level=error", format, line("info")));
+        }
+    }
+
+    #[test]
+    fn where_cri() {
+        let format = crate::input::Format::Cri;
+        let line =
+            |level: &str| format!(r#"2024-01-02T03:04:05Z stdout F {{"level":"{}"}}"#, level);
+        assert!(passes("level=error", format, line("error")));
+        assert!(!passes("level=error", format, line("info")));
+    }
+
+    #[test]
+    fn where_journal() {
+        let format = crate::input::Format::Journal;
+        let line = |priority: &str| {
+            format!(
+                r#"{{"MESSAGE":"started","PRIORITY":"{}","__REALTIME_TIMESTAMP":"1713212345678901"}}"#,
+                priority
+            )
+        };
+        assert!(passes("PRIORITY=3", format, line("3")));
+        assert!(!passes("PRIORITY=3", format, line("6")));
+    }
 
     #[test]
     fn normal_run() {
-        let keys = vec![String::from("t")];
-        let in1 = BufReader::new(stringreader::StringReader::new(
-            r#"
+        let result = run_lines(
+            &parser(),
+            Default::default(),
+            &[
+                r#"
 {"t":15, "add": "15_1"}
 {"t":16, "add": "16_1"}
 {"t":18, "add": "18_1"}
 "#,
-        ));
-        let in2 = BufReader::new(stringreader::StringReader::new(
-            r#"
+                r#"
 {"t":16, "add": "16_2"}
 {"t":17, "add": "17_2"}
 {"t":18, "add": "18_2"}
 "#,
-        ));
-        let mut buf = std::io::BufWriter::new(Vec::new());
-        crate::run(keys, vec![in1, in2], &mut buf).unwrap();
-        let result = String::from_utf8(buf.into_inner().unwrap()).unwrap();
+            ],
+        );
         assert_eq!(
             r#"{"t":15, "add": "15_1"}
 {"t":16, "add": "16_2"}
@@ -216,14 +613,77 @@ mod tests {
     }
 
     #[test]
-    fn open_file() {
-        let mut r = BufReader::with_capacity(
-            1024,
-            crate::open_file(&String::from("tests/data/1.json")).unwrap(),
+    fn until_run() {
+        let result = run_lines(
+            &parser(),
+            crate::timestamp::Bounds {
+                since: Some(2_000_000),
+                until: Some(5_000_000),
+            },
+            &[
+                "{\"t\":1}\n{\"t\":3}\n{\"t\":5}\n",
+                "{\"t\":2}\n{\"t\":4}\n{\"t\":6}\n",
+            ],
+        );
+        assert_eq!("{\"t\":2}\n{\"t\":3}\n{\"t\":4}\n", result);
+    }
+
+    #[test]
+    fn pre_epoch_run() {
+        let result = run_lines(
+            &parser(),
+            crate::timestamp::Bounds {
+                since: Some(-2_000_000),
+                until: None,
+            },
+            &[
+                "{\"t\":-2}\n{\"t\":0}\n{\"t\":1}\n",
+                "{\"t\":-3}\n{\"t\":-1}\n{\"t\":0}\n",
+            ],
+        );
+        assert_eq!(
+            "{\"t\":-2}\n{\"t\":-1}\n{\"t\":0}\n{\"t\":0}\n{\"t\":1}\n",
+            result
+        );
+    }
+
+    #[test]
+    fn monotonic_run() {
+        let mut parser = parser();
+        parser.monotonic = true;
+        let result = run_lines(
+            &parser,
+            Default::default(),
+            &[
+                "{\"t\":1}\n{\"t\":4}\n{\"t\":3, \"late\":true}\n{\"t\":6}\n",
+                "{\"t\":2}\n{\"t\":5}\n",
+            ],
+        );
+        assert_eq!(
+            "{\"t\":1}\n{\"t\":2}\n{\"t\":4}\n{\"t\":4,\"late\":true}\n{\"t\":5}\n{\"t\":6}\n",
+            result
+        );
+    }
+
+    #[test]
+    fn inherit_run() {
+        let mut parser = parser();
+        parser.inherit_ts = true;
+        let result = run_lines(
+            &parser,
+            Default::default(),
+            &[
+                "{\"msg\":\"dropped\"}\n{\"t\":2}\n{\"msg\":\"trace\"}\nnot json\n{\"t\":3}\n",
+                "{\"t\":1}\n{\"t\":2, \"in\":2}\n",
+            ],
         );
-        let mut line = String::new();
-        r.read_line(&mut line).unwrap();
-        let replaced = line.replace("\r", "").replace("\n", "");
-        assert_eq!(r#"{"t":15, "add": "15_1"}"#, replaced);
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(5, lines.len());
+        let trace = lines
+            .iter()
+            .position(|line| line.contains("trace"))
+            .unwrap();
+        assert_eq!("{\"t\":2}", lines[trace - 1]);
+        assert_eq!("{\"t\":3}", lines[4]);
     }
 }
@@ -0,0 +1,47 @@
+use std::hash::BuildHasher;
+
+use crate::{error, timestamp};
+
+///
+/// Name of the host `jmrg` runs on, the kernel is asked first and `hostname` command afterwards
+pub fn hostname() -> Result<String, error::MrgError> {
+    let name = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        });
+    match name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => Ok(name.to_string()),
+        _ => Err(error::MrgError {
+            msg: "cannot get the name of the host".to_string(),
+        }),
+    }
+}
+
+///
+/// Metadata of the merge: the identifier which differs from run to run and the time it's started at
+pub fn of_run() -> serde_json::Value {
+    let started = timestamp::from_datetime(&chrono::Utc::now());
+    // hashers are seeded randomly, for every process
+    let id = std::collections::hash_map::RandomState::new().hash_one((std::process::id(), started));
+    serde_json::json!({
+        "run_id": format!("{:016x}", id),
+        "started_at": timestamp::to_rfc3339(started),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn of_run() {
+        let first = crate::meta::of_run();
+        let second = crate::meta::of_run();
+        assert_eq!(16, first["run_id"].as_str().unwrap().len());
+        assert_ne!(first["run_id"], second["run_id"]);
+        assert!(first["started_at"].as_str().unwrap().ends_with('Z'));
+    }
+}
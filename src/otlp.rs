@@ -0,0 +1,140 @@
+use crate::error;
+use crate::output::Sink;
+use crate::timestamp::Timestamp;
+use crate::transform::Severity;
+
+/// Records exported in a request
+const BATCH_SIZE: usize = 1000;
+
+///
+/// Severity number and text of OpenTelemetry log data model
+fn severity_of(severity: Severity) -> (u8, &'static str) {
+    match severity {
+        Severity::Trace => (1, "TRACE"),
+        Severity::Debug => (5, "DEBUG"),
+        Severity::Info => (9, "INFO"),
+        Severity::Warn => (13, "WARN"),
+        Severity::Error => (17, "ERROR"),
+        Severity::Fatal => (21, "FATAL"),
+    }
+}
+
+///
+/// LogRecord of the record in JSON encoding of OTLP, the timestamp the records are ordered by
+/// becomes both the time of the record and the time it's observed at
+fn log_record(ts: Timestamp, line: &str) -> serde_json::Value {
+    // unsigned nanoseconds are written as strings, timestamps before the epoch are unknown ones
+    let nanos = u64::try_from(ts).unwrap_or(0).to_string();
+    let mut record = serde_json::json!({
+        "timeUnixNano": nanos,
+        "observedTimeUnixNano": nanos,
+        "body": {"stringValue": line},
+    });
+    if let Some((number, text)) = Severity::of(line).map(severity_of) {
+        record["severityNumber"] = number.into();
+        record["severityText"] = text.into();
+    }
+    record
+}
+
+///
+/// OTLP/HTTP collector the records are exported to as LogRecords in batches
+pub struct Otlp {
+    /// the logs endpoint, e.g. `http://host:4318/v1/logs`
+    url: String,
+    /// records which are not exported yet
+    records: Vec<serde_json::Value>,
+}
+
+impl Otlp {
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: collector address, e.g. `http://host:4318`
+    pub fn new(url: &str) -> Self {
+        Otlp {
+            url: format!("{}/v1/logs", url.trim_end_matches('/')),
+            records: Vec::new(),
+        }
+    }
+
+    ///
+    /// Exports the records gathered so far
+    fn export(&mut self) -> std::io::Result<()> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+        let request = serde_json::json!({"resourceLogs": [{
+            "resource": {"attributes": [
+                {"key": "service.name", "value": {"stringValue": "jmrg"}},
+            ]},
+            "scopeLogs": [{
+                "scope": {"name": "jmrg", "version": env!("CARGO_PKG_VERSION")},
+                "logRecords": std::mem::take(&mut self.records),
+            }],
+        }]});
+        ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&request.to_string())
+            .map_err(|e| {
+                std::io::Error::other(format!("cannot export to OTLP collector: {}", e))
+            })?;
+        Ok(())
+    }
+
+    ///
+    /// Exports the rest of the records
+    pub fn finish(mut self) -> Result<(), error::MrgError> {
+        Ok(self.export()?)
+    }
+}
+
+impl Sink for Otlp {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        self.records.push(log_record(ts, line));
+        match self.records.len() >= BATCH_SIZE {
+            true => self.export(),
+            false => Ok(()),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.export()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::Sink;
+
+    #[test]
+    fn export() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = crate::loki::tests::receive(listener);
+        let mut otlp = crate::otlp::Otlp::new(&url);
+        otlp.emit(1_000, r#"{"level":"warn","msg":"a"}"#).unwrap();
+        otlp.emit(2_000, "not json").unwrap();
+        otlp.finish().unwrap();
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!("POST /v1/logs HTTP/1.1", request_line.trim_end());
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            serde_json::json!([
+                {
+                    "timeUnixNano": "1000",
+                    "observedTimeUnixNano": "1000",
+                    "body": {"stringValue": r#"{"level":"warn","msg":"a"}"#},
+                    "severityNumber": 13,
+                    "severityText": "WARN",
+                },
+                {
+                    "timeUnixNano": "2000",
+                    "observedTimeUnixNano": "2000",
+                    "body": {"stringValue": "not json"},
+                },
+            ]),
+            body["resourceLogs"][0]["scopeLogs"][0]["logRecords"]
+        );
+    }
+}
@@ -0,0 +1,1120 @@
+use std::fs::File;
+use std::io::{BufWriter, Stdout, Write};
+use std::path::{Path, PathBuf};
+
+use crate::input::Codec;
+use crate::timestamp::{self, Timestamp};
+use crate::transform::OutputFormat;
+use crate::{error, filter, transform};
+
+/// Output path which stands for the standard output
+pub const STDOUT_PATH: &str = "-";
+
+///
+/// File being written, it's removed when dropped unless it's renamed by then
+pub struct TempFile(pub PathBuf);
+
+impl TempFile {
+    ///
+    /// Temporary file next to the file, e.g. `.merged.json.1234.tmp`, it's not created
+    pub fn next_to(path: &Path) -> std::io::Result<Self> {
+        let name = path.file_name().ok_or_else(|| {
+            std::io::Error::other(format!("'{}' is not a file name", path.display()))
+        })?;
+        Ok(TempFile(path.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ))))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+///
+/// Compresses the output, the compression is finished once the output is complete
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(flate2::write::GzEncoder<W>),
+    Bzip2(bzip2::write::BzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Xz(xz2::write::XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(codec: Codec, writer: W) -> Result<Self, error::MrgError> {
+        match codec {
+            Codec::Plain => Ok(Encoder::Plain(writer)),
+            Codec::Gzip => Ok(Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            ))),
+            Codec::Bzip2 => Ok(Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            ))),
+            Codec::Zstd => Ok(Encoder::Zstd(zstd::stream::write::Encoder::new(writer, 0)?)),
+            Codec::Xz => Ok(Encoder::Xz(xz2::write::XzEncoder::new(writer, 6))),
+            Codec::Lz4 | Codec::Snappy => Err(error::MrgError {
+                msg: format!(
+                    "{:?} compression of the output is not supported, expected one of gzip, bzip2, zstd, xz",
+                    codec
+                ),
+            }),
+        }
+    }
+
+    ///
+    /// Writes the end of the compressed stream
+    fn finish(self) -> std::io::Result<W> {
+        match self {
+            Encoder::Plain(writer) => Ok(writer),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Bzip2(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+            Encoder::Xz(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::Xz(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::Xz(encoder) => encoder.flush(),
+        }
+    }
+}
+
+///
+/// Compression implied by the extension of the output file, e.g. `merged.json.zst`
+fn infer(path: &Path) -> Codec {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("gz") => Codec::Gzip,
+        Some("bz2") => Codec::Bzip2,
+        Some("zst") => Codec::Zstd,
+        Some("xz") => Codec::Xz,
+        _ => Codec::Plain,
+    }
+}
+
+///
+/// Destination of the merged records
+pub trait Sink {
+    ///
+    /// Writes the record, `ts` is the timestamp it's ordered by
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()>;
+
+    ///
+    /// Passes the records written so far on, e.g. when inputs are followed
+    fn flush_records(&mut self) -> std::io::Result<()>;
+}
+
+fn write_record(writer: &mut impl Write, line: &str, terminator: u8) -> std::io::Result<()> {
+    writer.write_all(line.as_bytes())?;
+    writer.write_all(&[terminator])
+}
+
+impl<W: Write> Sink for W {
+    fn emit(&mut self, _: Timestamp, line: &str) -> std::io::Result<()> {
+        writeln!(self, "{}", line)
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.flush()
+    }
+}
+
+///
+/// File which is written under a temporary name next to it and renamed once it's complete,
+/// so that it never holds a partial merge and the previous content is kept when the merge fails
+pub struct Target {
+    writer: BufWriter<Encoder<File>>,
+    temp: TempFile,
+    path: PathBuf,
+    /// byte every record is followed by
+    terminator: u8,
+}
+
+impl Target {
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: the file to write, missing directories are created
+    /// * `codec`: compression of the file, it's inferred by the extension of the file unless given
+    /// * `terminator`: byte every record is followed by, a newline unless NUL is asked for
+    pub fn create(path: &Path, codec: Option<Codec>, terminator: u8) -> std::io::Result<Self> {
+        let temp = TempFile::next_to(path)?;
+        let file = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| File::create(&temp.0))
+            .map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("cannot create '{}': {}", temp.0.display(), e),
+                )
+            })?;
+        let encoder = Encoder::new(codec.unwrap_or_else(|| infer(path)), file)
+            .map_err(|e| std::io::Error::other(e.msg))?;
+        Ok(Target {
+            writer: BufWriter::with_capacity(crate::BUF_SIZE, encoder),
+            temp,
+            path: path.to_path_buf(),
+            terminator,
+        })
+    }
+
+    fn write_record(&mut self, line: &str) -> std::io::Result<()> {
+        write_record(&mut self.writer, line, self.terminator)
+    }
+
+    ///
+    /// The file takes its place, its path and size are reported on stderr
+    pub fn finish(self) -> std::io::Result<()> {
+        let file = self
+            .writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .finish()?;
+        file.sync_all()?;
+        std::fs::rename(&self.temp.0, &self.path)?;
+        eprintln!(
+            "'{}': {} bytes written",
+            self.path.display(),
+            file.metadata()?.len()
+        );
+        Ok(())
+    }
+}
+
+impl Write for Target {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Parts of the datetime, from the year to the fraction of the second,
+/// along with the windows which need them to be told apart: those shorter than the part lasts at most
+const PARTS: [(&str, Timestamp); 7] = [
+    ("year", Timestamp::MAX),
+    ("month", 366 * 86_400_000_000_000),
+    ("day", 31 * 86_400_000_000_000),
+    ("hour", 86_400_000_000_000),
+    ("minute", 3_600_000_000_000),
+    ("second", 60_000_000_000),
+    ("fraction of the second", 1_000_000_000),
+];
+
+///
+/// The part of the datetime the pattern lacks to give every window its own file, if it lacks any
+fn lacking_part(pattern: &str, window: Timestamp) -> Option<&'static str> {
+    use chrono::format::{Fixed, Item, Numeric};
+    // the parts of PARTS the pattern has
+    let mut has = [false; PARTS.len()];
+    let (mut hour12, mut am_pm) = (false, false);
+    for item in chrono::format::StrftimeItems::new(pattern) {
+        let parts: std::ops::Range<usize> = match item {
+            Item::Numeric(Numeric::Year | Numeric::YearMod100, _) => 0..1,
+            Item::Numeric(Numeric::Month, _)
+            | Item::Fixed(Fixed::ShortMonthName | Fixed::LongMonthName) => 1..2,
+            Item::Numeric(Numeric::Day, _) => 2..3,
+            // the day of the year tells the month as well
+            Item::Numeric(Numeric::Ordinal, _) => 1..3,
+            Item::Numeric(Numeric::Hour, _) => 3..4,
+            Item::Numeric(Numeric::Minute, _) => 4..5,
+            Item::Numeric(Numeric::Second, _) => 5..6,
+            Item::Numeric(Numeric::Nanosecond, _)
+            | Item::Fixed(
+                Fixed::Nanosecond | Fixed::Nanosecond3 | Fixed::Nanosecond6 | Fixed::Nanosecond9,
+            ) => 6..7,
+            Item::Numeric(Numeric::Timestamp, _) | Item::Fixed(Fixed::RFC2822) => 0..6,
+            Item::Fixed(Fixed::RFC3339) => 0..7,
+            Item::Numeric(Numeric::Hour12, _) => {
+                hour12 = true;
+                continue;
+            }
+            Item::Fixed(Fixed::LowerAmPm | Fixed::UpperAmPm) => {
+                am_pm = true;
+                continue;
+            }
+            _ => continue,
+        };
+        has[parts].fill(true);
+    }
+    has[3] |= hour12 && am_pm;
+    PARTS
+        .iter()
+        .zip(has)
+        .find(|((_, span), has)| window < *span && !has)
+        .map(|((name, _), _)| *name)
+}
+
+///
+/// Files the merged records are split into by time windows, e.g. hourly ones.
+/// A file is complete as soon as a record of a later window comes.
+pub struct Split {
+    /// path of the files, the start of the window in UTC is formatted into it, e.g. `merged-%Y%m%dT%H.json`
+    pattern: String,
+    /// windows are aligned to the epoch
+    window: Timestamp,
+    codec: Option<Codec>,
+    terminator: u8,
+    /// the start of the window being written, along with its file
+    current: Option<(Timestamp, Target)>,
+}
+
+impl Split {
+    pub fn new(
+        pattern: &str,
+        window: Timestamp,
+        codec: Option<Codec>,
+        terminator: u8,
+    ) -> Result<Self, error::MrgError> {
+        if window <= 0 {
+            return Err(error::MrgError {
+                msg: "the window to split the output by must be positive".to_string(),
+            });
+        }
+        if chrono::format::StrftimeItems::new(pattern)
+            .any(|item| item == chrono::format::Item::Error)
+        {
+            return Err(error::MrgError {
+                msg: format!("'{}' is not a valid strftime pattern", pattern),
+            });
+        }
+        // the file of an earlier window would be replaced by the one of a later window
+        if let Some(part) = lacking_part(pattern, window) {
+            return Err(error::MrgError {
+                msg: format!(
+                    "'{}' gives different windows the same file, it lacks the {} to split the output by the window",
+                    pattern, part
+                ),
+            });
+        }
+        Ok(Split {
+            pattern: pattern.to_string(),
+            window,
+            codec,
+            terminator,
+            current: None,
+        })
+    }
+
+    ///
+    /// Path of the file of the window starting at `start`
+    fn path_of(&self, start: Timestamp) -> std::io::Result<PathBuf> {
+        let dt = timestamp::to_datetime(start).ok_or_else(|| {
+            std::io::Error::other(format!("{} is out of range of datetimes", start))
+        })?;
+        Ok(PathBuf::from(dt.format(&self.pattern).to_string()))
+    }
+}
+
+impl Sink for Split {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        let start = ts.div_euclid(self.window) * self.window;
+        // records behind the window being written, e.g. of unordered inputs, go to its file
+        if self
+            .current
+            .as_ref()
+            .is_none_or(|(current, _)| start > *current)
+        {
+            let path = self.path_of(start)?;
+            let target = match self.current.take() {
+                // the pattern may be coarser than the window
+                Some((_, target)) if target.path == path => target,
+                previous => {
+                    if let Some((_, target)) = previous {
+                        target.finish()?;
+                    }
+                    Target::create(&path, self.codec, self.terminator)?
+                }
+            };
+            self.current = Some((start, target));
+        }
+        match &mut self.current {
+            Some((_, target)) => target.write_record(line),
+            None => Ok(()),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        match &mut self.current {
+            Some((_, target)) => target.writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+///
+/// Parses size like `500M`, suffixes `K`, `M`, `G` and `T` stand for powers of 1024
+pub fn parse_size(value: &str) -> Result<u64, error::MrgError> {
+    let invalid = || error::MrgError {
+        msg: format!(
+            "'{}' is not a valid size, expected number of bytes with optional K, M, G or T suffix",
+            value
+        ),
+    };
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, suffix) = value.split_at(split);
+    let multiplier: u64 = match suffix.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .filter(|size| *size > 0)
+        .ok_or_else(invalid)
+}
+
+///
+/// Files of limited size the merged records are split into, e.g. `merged.0001.json`, `merged.0002.json`,
+/// records are never split between files, so a file holds at least one record whatever its size
+pub struct Chunks {
+    /// path of the files, the number of the file is inserted before the extensions
+    path: PathBuf,
+    /// bytes of records in a file before it's compressed
+    size: u64,
+    codec: Option<Codec>,
+    terminator: u8,
+    /// number of files so far
+    count: usize,
+    /// the file being written, along with the bytes of records written to it
+    current: Option<(u64, Target)>,
+}
+
+impl Chunks {
+    pub fn new(path: &str, size: u64, codec: Option<Codec>, terminator: u8) -> Self {
+        Chunks {
+            path: PathBuf::from(path),
+            size,
+            codec,
+            terminator,
+            count: 0,
+            current: None,
+        }
+    }
+
+    ///
+    /// Path of the file with the number, it keeps the extensions so that the compression is inferred the same way
+    fn path_of(&self, number: usize) -> PathBuf {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let numbered = match name.split_once('.') {
+            Some((stem, extensions)) => format!("{}.{:04}.{}", stem, number, extensions),
+            None => format!("{}.{:04}", name, number),
+        };
+        self.path.with_file_name(numbered)
+    }
+}
+
+impl Sink for Chunks {
+    fn emit(&mut self, _: Timestamp, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let full = self
+            .current
+            .as_ref()
+            .is_none_or(|(written, _)| *written > 0 && written + len > self.size);
+        if full {
+            if let Some((_, target)) = self.current.take() {
+                target.finish()?;
+            }
+            self.count += 1;
+            let path = self.path_of(self.count);
+            self.current = Some((0, Target::create(&path, self.codec, self.terminator)?));
+        }
+        match &mut self.current {
+            Some((written, target)) => {
+                *written += len;
+                target.write_record(line)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        match &mut self.current {
+            Some((_, target)) => target.writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+///
+/// Records wrapped into a single JSON array, one of them per line.
+/// The last record is held back since it's not followed by a comma.
+pub struct Array {
+    inner: Output,
+    last: Option<(Timestamp, String)>,
+}
+
+impl Array {
+    pub fn new(inner: Output) -> Self {
+        Array { inner, last: None }
+    }
+
+    ///
+    /// Writes the last record and closes the array, an empty one is written when there are no records
+    fn close(&mut self) -> std::io::Result<()> {
+        match self.last.take() {
+            Some((ts, last)) => self.inner.emit(ts, &last)?,
+            None => self.inner.emit(0, "[")?,
+        }
+        self.inner.emit(0, "]")
+    }
+}
+
+impl Sink for Array {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        // pretty records are followed by empty lines
+        let line = line.trim_end_matches('\n').to_string();
+        match self.last.replace((ts, line)) {
+            Some((ts, last)) => self.inner.emit(ts, &format!("{},", last)),
+            None => self.inner.emit(ts, "["),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.inner.flush_records()
+    }
+}
+
+///
+/// Service the merged records are forwarded to instead of being written out
+#[derive(Clone, Debug, PartialEq)]
+pub enum Remote {
+    /// Loki push API at the address, e.g. `loki=http://host:3100`
+    Loki(String),
+    /// OTLP/HTTP collector at the address, e.g. `otlp=http://host:4318`
+    Otlp(String),
+    /// Graylog GELF input at the address, e.g. `gelf=udp://host:12201`
+    Gelf(String),
+    /// syslog server at the address, e.g. `syslog=udp://host:514`
+    Syslog(String),
+    /// Kafka topic, e.g. `kafka=broker:9092/topic`
+    Kafka(String),
+}
+
+impl Remote {
+    pub fn parse(spec: &str) -> Result<Self, error::MrgError> {
+        match spec.split_once('=') {
+            Some(("loki", url)) if !url.is_empty() => Ok(Remote::Loki(url.to_string())),
+            Some(("otlp", url)) if !url.is_empty() => Ok(Remote::Otlp(url.to_string())),
+            Some(("gelf", url)) if !url.is_empty() => Ok(Remote::Gelf(url.to_string())),
+            Some(("syslog", url)) if !url.is_empty() => Ok(Remote::Syslog(url.to_string())),
+            Some(("kafka", target)) if !target.is_empty() => Ok(Remote::Kafka(target.to_string())),
+            _ => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid sink, expected loki=http://host:3100, otlp=http://host:4318, gelf=udp://host:12201, syslog=udp://host:514 or kafka=broker:9092/topic",
+                    spec
+                ),
+            }),
+        }
+    }
+}
+
+///
+/// Options of a single output, they precede its path like `where=level=error;format=logfmt;-`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scope {
+    /// only the records which pass it are written to the output
+    pub filter: Option<filter::Filter>,
+    /// format of the output instead of the one of the rest of them
+    pub format: Option<OutputFormat>,
+}
+
+///
+/// Output file, `-` stands for the standard output, along with its own options
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    pub path: String,
+    pub scope: Scope,
+}
+
+impl Destination {
+    ///
+    /// Parses `[where=condition;...][any;][format=name;]path`, the options are only recognized
+    /// at the beginning and each of them ends with `;`, the path is the rest, so it can have `;` in it
+    pub fn parse(spec: &str) -> Result<Self, error::MrgError> {
+        let (mut conditions, mut any, mut format) = (Vec::new(), false, None);
+        let mut path = spec;
+        while let Some((option, rest)) = path.split_once(';') {
+            match option.split_once('=') {
+                Some(("where", condition)) => conditions.push(filter::Condition::parse(condition)?),
+                Some(("format", name)) => format = Some(OutputFormat::parse(name)?),
+                None if option == "any" => any = true,
+                _ => break,
+            }
+            path = rest;
+        }
+        if path.is_empty() || (any && conditions.is_empty()) {
+            return Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid output, expected [where=condition;...][any;][format=name;]path",
+                    spec
+                ),
+            });
+        }
+        // the records are rendered once more for the output, the formats which take their own outputs can't be
+        if let Some(format) = format.filter(|format| {
+            !matches!(
+                format,
+                OutputFormat::Ndjson
+                    | OutputFormat::Logfmt
+                    | OutputFormat::Csv
+                    | OutputFormat::Tsv
+                    | OutputFormat::EsBulk
+            )
+        }) {
+            return Err(error::MrgError {
+                msg: format!("{:?} output can't be the format of a single output", format),
+            });
+        }
+        let filter = (!conditions.is_empty()).then_some(filter::Filter { conditions, any });
+        Ok(Destination {
+            path: path.to_string(),
+            scope: Scope { filter, format },
+        })
+    }
+}
+
+///
+/// Output which takes the records passing its filter, rendered in its own format.
+/// The records come to it as JSON objects, the way they are written to the rest of the outputs.
+pub struct Scoped {
+    filter: Option<filter::Filter>,
+    /// renders the records in the format of the output
+    render: transform::Transform,
+    inner: Output,
+}
+
+impl Scoped {
+    ///
+    /// # Arguments
+    ///
+    /// * `scope`: options of the output
+    /// * `columns`: columns of CSV and TSV format
+    /// * `index`: index of es-bulk actions
+    /// * `inner`: the output
+    pub fn new(
+        scope: Scope,
+        columns: Option<Vec<String>>,
+        index: Option<transform::Index>,
+        inner: Output,
+    ) -> Self {
+        Scoped {
+            filter: scope.filter,
+            render: transform::Transform {
+                format: scope.format.unwrap_or_default(),
+                columns: columns.map(transform::Columns::new),
+                index,
+                ..Default::default()
+            },
+            inner,
+        }
+    }
+}
+
+impl Sink for Scoped {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        if self
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !filter.admits(line))
+        {
+            return Ok(());
+        }
+        let line = self.render.apply(ts, "", line);
+        self.inner.emit(ts, &line)
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.inner.flush_records()
+    }
+}
+
+///
+/// Where the merged records go: the standard output, a file, or files split by time windows or by size
+pub enum Output {
+    /// the writer along with the byte every record is followed by
+    Stdout(BufWriter<Encoder<Stdout>>, u8),
+    File(Target),
+    /// S3 object written with multipart upload, along with the byte every record is followed by
+    S3(BufWriter<Encoder<crate::s3::Upload>>, u8),
+    Split(Split),
+    Chunks(Chunks),
+    Array(Box<Array>),
+    Html(Box<crate::html::Html>),
+    /// outputs which get every record, e.g. a file and the standard output
+    Tee(Vec<Output>),
+    Scoped(Box<Scoped>),
+    Loki(Box<crate::loki::Loki>),
+    Otlp(Box<crate::otlp::Otlp>),
+    Gelf(Box<crate::gelf::Gelf>),
+    Syslog(Box<crate::syslog::Syslog>),
+    #[cfg(feature = "kafka")]
+    Kafka(Box<crate::kafka::Publisher>),
+    #[cfg(any(feature = "parquet", feature = "arrow"))]
+    Columnar(Box<crate::columnar::Columnar>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Box<crate::sqlite::Sqlite>),
+}
+
+impl Output {
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: the file or `s3://bucket/key` object to write, the standard output unless given
+    /// * `codec`: compression of the output, it's inferred by the extension of the file unless given
+    /// * `split_by`: the window the output is split by, `path` is the pattern of the files then
+    /// * `split_size`: the size the output is split by, unless it's split by windows
+    /// * `terminator`: byte every record is followed by, a newline unless NUL is asked for
+    pub fn open(
+        path: Option<&str>,
+        codec: Option<Codec>,
+        split_by: Option<Timestamp>,
+        split_size: Option<u64>,
+        terminator: u8,
+    ) -> Result<Self, error::MrgError> {
+        match (path, split_by, split_size) {
+            (Some(pattern), Some(window), _) => Ok(Output::Split(Split::new(
+                pattern, window, codec, terminator,
+            )?)),
+            (Some(path), None, Some(size)) => {
+                Ok(Output::Chunks(Chunks::new(path, size, codec, terminator)))
+            }
+            (Some(url), None, None) if url.starts_with(crate::s3::SCHEME) => {
+                let codec = codec.unwrap_or_else(|| infer(Path::new(url)));
+                let encoder = Encoder::new(codec, crate::s3::Upload::create(url)?)?;
+                Ok(Output::S3(
+                    BufWriter::with_capacity(crate::BUF_SIZE, encoder),
+                    terminator,
+                ))
+            }
+            (Some(path), None, None) => Ok(Output::File(Target::create(
+                Path::new(path),
+                codec,
+                terminator,
+            )?)),
+            (None, _, _) => {
+                let encoder = Encoder::new(codec.unwrap_or(Codec::Plain), std::io::stdout())?;
+                Ok(Output::Stdout(
+                    BufWriter::with_capacity(crate::BUF_SIZE, encoder),
+                    terminator,
+                ))
+            }
+        }
+    }
+
+    ///
+    /// Parquet file or Arrow IPC stream of the records, as long as jmrg is built with the support of the format
+    ///
+    /// # Arguments
+    ///
+    /// * `format`: parquet or arrow
+    /// * `path`: the file to write, the standard output unless given
+    /// * `schema`: JSON file of the columns and their Arrow types, they are inferred from the records unless given
+    #[cfg_attr(
+        not(any(feature = "parquet", feature = "arrow")),
+        allow(unused_variables)
+    )]
+    pub fn columnar(
+        format: crate::transform::OutputFormat,
+        path: Option<&str>,
+        schema: Option<&str>,
+    ) -> Result<Self, error::MrgError> {
+        #[cfg(any(feature = "parquet", feature = "arrow"))]
+        return Ok(Output::Columnar(Box::new(
+            crate::columnar::Columnar::create(
+                format,
+                path,
+                schema.map(crate::columnar::read_schema).transpose()?,
+            )?,
+        )));
+        #[cfg(not(any(feature = "parquet", feature = "arrow")))]
+        Err(error::MrgError {
+            msg: format!(
+                "{:?} output requires jmrg to be built with the corresponding cargo feature",
+                format
+            ),
+        })
+    }
+
+    ///
+    /// SQLite database of the records, as long as jmrg is built with its support
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: the database to write
+    /// * `table`: the table the records are inserted into
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    pub fn sqlite(path: &str, table: &str) -> Result<Self, error::MrgError> {
+        #[cfg(feature = "sqlite")]
+        return Ok(Output::Sqlite(Box::new(crate::sqlite::Sqlite::create(
+            path, table,
+        )?)));
+        #[cfg(not(feature = "sqlite"))]
+        Err(error::MrgError {
+            msg: format!(
+                "'{}' requires jmrg to be built with sqlite cargo feature",
+                path
+            ),
+        })
+    }
+
+    ///
+    /// Kafka topic the records are published to, as long as jmrg is built with its support
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: `broker[,broker...]/topic[?partitions=0]`
+    pub fn kafka(target: &str) -> Result<Self, error::MrgError> {
+        #[cfg(feature = "kafka")]
+        return Ok(Output::Kafka(Box::new(crate::kafka::Publisher::new(
+            target,
+        )?)));
+        #[cfg(not(feature = "kafka"))]
+        Err(error::MrgError {
+            msg: format!(
+                "'{}' requires jmrg to be built with kafka cargo feature",
+                target
+            ),
+        })
+    }
+
+    ///
+    /// Completes the output, files take their places and their paths and sizes are reported on stderr
+    pub fn finish(self) -> Result<(), error::MrgError> {
+        match self {
+            Output::Array(mut array) => {
+                array.close()?;
+                return array.inner.finish();
+            }
+            Output::Html(mut html) => {
+                html.close()?;
+                return html.into_inner().finish();
+            }
+            Output::Tee(outputs) => {
+                // the rest of the outputs are complete even when one of them fails
+                let mut result = Ok(());
+                for output in outputs {
+                    let finished = output.finish();
+                    if result.is_ok() {
+                        result = finished;
+                    }
+                }
+                return result;
+            }
+            Output::Scoped(scoped) => return scoped.inner.finish(),
+            Output::Loki(loki) => return loki.finish(),
+            Output::Otlp(otlp) => return otlp.finish(),
+            Output::Gelf(gelf) => return gelf.finish(),
+            Output::Syslog(syslog) => return syslog.finish(),
+            #[cfg(feature = "kafka")]
+            Output::Kafka(kafka) => return kafka.finish(),
+            #[cfg(any(feature = "parquet", feature = "arrow"))]
+            Output::Columnar(columnar) => return columnar.finish(),
+            #[cfg(feature = "sqlite")]
+            Output::Sqlite(sqlite) => return sqlite.finish(),
+            Output::Stdout(writer, _) => writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .finish()?
+                .flush()?,
+            Output::File(target) => target.finish()?,
+            Output::S3(writer, _) => writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .finish()?
+                .finish()?,
+            Output::Split(split) => {
+                if let Some((_, target)) = split.current {
+                    target.finish()?;
+                }
+            }
+            Output::Chunks(chunks) => {
+                if let Some((_, target)) = chunks.current {
+                    target.finish()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Output {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        match self {
+            Output::Stdout(writer, terminator) => write_record(writer, line, *terminator),
+            Output::File(target) => target.write_record(line),
+            Output::S3(writer, terminator) => write_record(writer, line, *terminator),
+            Output::Split(split) => split.emit(ts, line),
+            Output::Chunks(chunks) => chunks.emit(ts, line),
+            Output::Array(array) => array.emit(ts, line),
+            Output::Html(html) => html.emit(ts, line),
+            Output::Tee(outputs) => outputs
+                .iter_mut()
+                .try_for_each(|output| output.emit(ts, line)),
+            Output::Scoped(scoped) => scoped.emit(ts, line),
+            Output::Loki(loki) => loki.emit(ts, line),
+            Output::Otlp(otlp) => otlp.emit(ts, line),
+            Output::Gelf(gelf) => gelf.emit(ts, line),
+            Output::Syslog(syslog) => syslog.emit(ts, line),
+            #[cfg(feature = "kafka")]
+            Output::Kafka(kafka) => kafka.emit(ts, line),
+            #[cfg(any(feature = "parquet", feature = "arrow"))]
+            Output::Columnar(columnar) => columnar.emit(ts, line),
+            #[cfg(feature = "sqlite")]
+            Output::Sqlite(sqlite) => sqlite.emit(ts, line),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        match self {
+            Output::Stdout(writer, _) => writer.flush(),
+            Output::File(target) => target.writer.flush(),
+            Output::S3(writer, _) => writer.flush(),
+            Output::Split(split) => split.flush_records(),
+            Output::Chunks(chunks) => chunks.flush_records(),
+            Output::Array(array) => array.flush_records(),
+            Output::Html(html) => html.flush_records(),
+            Output::Tee(outputs) => outputs
+                .iter_mut()
+                .try_for_each(|output| output.flush_records()),
+            Output::Scoped(scoped) => scoped.flush_records(),
+            Output::Loki(loki) => loki.flush_records(),
+            Output::Otlp(otlp) => otlp.flush_records(),
+            Output::Gelf(gelf) => gelf.flush_records(),
+            Output::Syslog(syslog) => syslog.flush_records(),
+            #[cfg(feature = "kafka")]
+            Output::Kafka(kafka) => kafka.flush_records(),
+            #[cfg(any(feature = "parquet", feature = "arrow"))]
+            Output::Columnar(columnar) => columnar.flush_records(),
+            #[cfg(feature = "sqlite")]
+            Output::Sqlite(sqlite) => sqlite.flush_records(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::output::Sink;
+
+    #[test]
+    fn file() {
+        let dir = std::env::temp_dir().join(format!("jmrg-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("merged.json");
+        std::fs::write(&path, "previous\n").unwrap();
+
+        let mut output =
+            crate::output::Output::open(path.to_str(), None, None, None, b'\n').unwrap();
+        output.emit(1, "{\"t\":1}").unwrap();
+        drop(output);
+        // the merge didn't complete, the previous content is kept
+        assert_eq!("previous\n", std::fs::read_to_string(&path).unwrap());
+
+        let mut output =
+            crate::output::Output::open(path.to_str(), None, None, None, b'\n').unwrap();
+        output.emit(1, "{\"t\":1}").unwrap();
+        output.finish().unwrap();
+        assert_eq!("{\"t\":1}\n", std::fs::read_to_string(&path).unwrap());
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compressed() {
+        let dir = std::env::temp_dir().join(format!("jmrg-compressed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, codec) in [
+            ("merged.json.gz", None),
+            ("merged.json.zst", None),
+            ("merged.bz2.json", Some(crate::input::Codec::Bzip2)),
+            ("merged.json.xz", None),
+        ] {
+            let path = dir.join(name);
+            let mut output =
+                crate::output::Output::open(path.to_str(), codec, None, None, b'\n').unwrap();
+            output.emit(1, "{\"t\":1}").unwrap();
+            output.finish().unwrap();
+            let mut content = String::new();
+            crate::input::open_file(path.to_str().unwrap(), None, None)
+                .unwrap()
+                .remove(0)
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!("{\"t\":1}\n", content, "{}", name);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn split() {
+        let dir = std::env::temp_dir().join(format!("jmrg-split-{}", std::process::id()));
+        let pattern = dir.join("merged-%Y%m%dT%H%M.json");
+        let minute: i128 = 60_000_000_000;
+        let mut output =
+            crate::output::Output::open(pattern.to_str(), None, Some(minute), None, b'\n').unwrap();
+        output.emit(0, "{\"t\":0}").unwrap();
+        output.emit(minute - 1, "{\"t\":1}").unwrap();
+        output.emit(3 * minute, "{\"t\":2}").unwrap();
+        // behind the window being written
+        output.emit(2 * minute, "{\"t\":3}").unwrap();
+        output.finish().unwrap();
+        assert_eq!(
+            "{\"t\":0}\n{\"t\":1}\n",
+            std::fs::read_to_string(dir.join("merged-19700101T0000.json")).unwrap()
+        );
+        assert_eq!(
+            "{\"t\":2}\n{\"t\":3}\n",
+            std::fs::read_to_string(dir.join("merged-19700101T0003.json")).unwrap()
+        );
+        assert_eq!(2, std::fs::read_dir(&dir).unwrap().count());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(crate::output::Split::new("merged-%Q.json", minute, None, b'\n').is_err());
+        // the hour of different days
+        assert!(crate::output::Split::new("merged-%H.json", 60 * minute, None, b'\n').is_err());
+        assert!(crate::output::Split::new("merged-%m%d.json", 60 * minute, None, b'\n').is_err());
+        for pattern in ["%F-%H", "%Y-%j-%I%p", "%s", "%+"] {
+            assert!(crate::output::Split::new(pattern, 60 * minute, None, b'\n').is_ok());
+        }
+        assert!(crate::output::Split::new("%Y-%m", 31 * 24 * 60 * minute, None, b'\n').is_ok());
+    }
+
+    #[test]
+    fn chunks() {
+        let dir = std::env::temp_dir().join(format!("jmrg-chunks-{}", std::process::id()));
+        let path = dir.join("merged.json.gz");
+        let mut output =
+            crate::output::Output::open(path.to_str(), None, None, Some(16), b'\n').unwrap();
+        for t in [1, 2, 3] {
+            output.emit(t, &format!("{{\"t\":{}}}", t)).unwrap();
+        }
+        output.emit(4, "{\"t\":4, \"long\": true}").unwrap();
+        output.finish().unwrap();
+        let read = |name: &str| {
+            let mut content = String::new();
+            crate::input::open_file(dir.join(name).to_str().unwrap(), None, None)
+                .unwrap()
+                .remove(0)
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        assert_eq!("{\"t\":1}\n{\"t\":2}\n", read("merged.0001.json.gz"));
+        assert_eq!("{\"t\":3}\n", read("merged.0002.json.gz"));
+        assert_eq!("{\"t\":4, \"long\": true}\n", read("merged.0003.json.gz"));
+        assert_eq!(3, std::fs::read_dir(&dir).unwrap().count());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn array() {
+        let dir = std::env::temp_dir().join(format!("jmrg-array-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("merged.json");
+        let open = || {
+            let inner =
+                crate::output::Output::open(path.to_str(), None, None, None, b'\n').unwrap();
+            crate::output::Output::Array(Box::new(crate::output::Array::new(inner)))
+        };
+        let mut output = open();
+        output.emit(1, "{\"t\":1}").unwrap();
+        output.emit(2, "{\n  \"t\": 2\n}\n").unwrap();
+        output.finish().unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!("[\n{\"t\":1},\n{\n  \"t\": 2\n}\n]\n", content);
+        assert!(serde_json::from_str::<serde_json::Value>(&content).is_ok());
+        open().finish().unwrap();
+        assert_eq!("[\n]\n", std::fs::read_to_string(&path).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn print0() {
+        let dir = std::env::temp_dir().join(format!("jmrg-print0-{}", std::process::id()));
+        let path = dir.join("merged.json");
+        let mut output =
+            crate::output::Output::open(path.to_str(), None, None, None, b'\0').unwrap();
+        output.emit(1, "{\n  \"t\": 1\n}").unwrap();
+        output.emit(2, "{\"t\":2}").unwrap();
+        output.finish().unwrap();
+        assert_eq!(
+            "{\n  \"t\": 1\n}\0{\"t\":2}\0",
+            std::fs::read_to_string(&path).unwrap()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remote() {
+        assert_eq!(
+            crate::output::Remote::Loki("http://host:3100".to_string()),
+            crate::output::Remote::parse("loki=http://host:3100").unwrap()
+        );
+        assert_eq!(
+            crate::output::Remote::Otlp("http://host:4318".to_string()),
+            crate::output::Remote::parse("otlp=http://host:4318").unwrap()
+        );
+        assert_eq!(
+            crate::output::Remote::Gelf("tcp://host:12201".to_string()),
+            crate::output::Remote::parse("gelf=tcp://host:12201").unwrap()
+        );
+        assert_eq!(
+            crate::output::Remote::Syslog("udp://host:514".to_string()),
+            crate::output::Remote::parse("syslog=udp://host:514").unwrap()
+        );
+        assert_eq!(
+            crate::output::Remote::Kafka("a:9092,b:9092/logs".to_string()),
+            crate::output::Remote::parse("kafka=a:9092,b:9092/logs").unwrap()
+        );
+        assert!(crate::output::Remote::parse("loki=").is_err());
+        assert!(crate::output::Remote::parse("http://host:3100").is_err());
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(500 << 20, crate::output::parse_size("500M").unwrap());
+        assert_eq!(1 << 30, crate::output::parse_size("1g").unwrap());
+        assert_eq!(100, crate::output::parse_size("100").unwrap());
+        assert!(crate::output::parse_size("0").is_err());
+        assert!(crate::output::parse_size("1.5G").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+
+use crate::error;
+use crate::input::{Since, Stream};
+use crate::timestamp::Timestamp;
+
+/// Magic number at the very end of the seekable zstd file
+const SEEKABLE_MAGIC: u32 = 0x8F92EAB1;
+/// Magic number of the skippable frame which holds the seek table
+const SKIPPABLE_MAGIC: u32 = 0x184D2A5E;
+/// Number_Of_Frames(4) + Seek_Table_Descriptor(1) + Seekable_Magic_Number(4)
+const FOOTER_SIZE: u64 = 9;
+/// Skippable_Magic_Number(4) + Frame_Size(4)
+const SKIPPABLE_HEADER_SIZE: u64 = 8;
+/// Bit of the descriptor which tells that every entry of the table has a checksum
+const CHECKSUM_FLAG: u8 = 0x80;
+
+fn read_u32(file: &mut File) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    file.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+///
+/// Reads the seek table of the seekable zstd file, see
+/// https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md
+///
+/// returns: offsets of all compressed frames, or None if the file is a regular zstd file
+fn read_seek_table(file: &mut File) -> Result<Option<Vec<u64>>, error::MrgError> {
+    let len = file.metadata()?.len();
+    if len < FOOTER_SIZE + SKIPPABLE_HEADER_SIZE {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::End(-(FOOTER_SIZE as i64)))?;
+    let num_frames = read_u32(file)? as u64;
+    let mut descriptor = [0u8; 1];
+    file.read_exact(&mut descriptor)?;
+    if read_u32(file)? != SEEKABLE_MAGIC {
+        return Ok(None);
+    }
+    let entry_size: u64 = if descriptor[0] & CHECKSUM_FLAG != 0 {
+        12
+    } else {
+        8
+    };
+    let frame_size = num_frames * entry_size + FOOTER_SIZE;
+    if len < frame_size + SKIPPABLE_HEADER_SIZE {
+        return Err(error::MrgError {
+            msg: "seek table of zstd file is truncated".to_string(),
+        });
+    }
+    file.seek(SeekFrom::End(
+        -((frame_size + SKIPPABLE_HEADER_SIZE) as i64),
+    ))?;
+    if read_u32(file)? != SKIPPABLE_MAGIC || read_u32(file)? as u64 != frame_size {
+        return Err(error::MrgError {
+            msg: "seek table of zstd file is malformed".to_string(),
+        });
+    }
+    let mut table = vec![0u8; (num_frames * entry_size) as usize];
+    file.read_exact(&mut table)?;
+    let mut offsets: Vec<u64> = Vec::with_capacity(num_frames as usize);
+    let mut offset: u64 = 0;
+    for entry in table.chunks(entry_size as usize) {
+        offsets.push(offset);
+        offset += u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) as u64;
+    }
+    Ok(Some(offsets))
+}
+
+///
+/// Opens the decompressed stream starting from the frame at `offset`.
+/// Every frame but the first one most likely starts in the middle of a line,
+/// this partial line is skipped.
+fn open_at(
+    file: &File,
+    offset: u64,
+    skip_partial: bool,
+) -> Result<BufReader<Stream>, error::MrgError> {
+    let mut file = file.try_clone()?;
+    file.seek(SeekFrom::Start(offset))?;
+    let decoder: Stream = Box::new(zstd::stream::read::Decoder::new(file)?);
+    let mut reader = BufReader::new(decoder);
+    if skip_partial {
+        reader.read_until(b'\n', &mut Vec::new())?;
+    }
+    Ok(reader)
+}
+
+///
+/// Timestamp of the first complete line which starts in the frame at `offset`
+fn first_ts(file: &File, path: &str, offset: u64, since: &Since) -> Option<Timestamp> {
+    let mut reader = open_at(file, offset, offset != 0).ok()?;
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    (since.ts_of)(path, line.trim_end())
+}
+
+///
+/// Opens zstd file. If the file is in the seekable format, all frames which
+/// only contain records preceding `since` are skipped without decompression,
+/// the frame to start from is found with binary search over the seek table.
+///
+/// # Arguments
+///
+/// * `file`: opened zstd file
+/// * `path`: path of the file, timestamp keys may depend on it
+/// * `since`: the earliest record the caller is interested in
+///
+/// returns: Result<Stream, MrgError>
+pub fn open(mut file: File, path: &str, since: &Since) -> Result<Stream, error::MrgError> {
+    let offsets: Vec<u64> = match read_seek_table(&mut file)? {
+        Some(offsets) if !offsets.is_empty() => offsets,
+        _ => {
+            file.rewind()?;
+            return Ok(Box::new(zstd::stream::read::Decoder::new(file)?));
+        }
+    };
+    // the largest frame whose first complete record precedes `since`,
+    // everything before this record precedes `since` as well
+    let (mut lo, mut hi) = (0, offsets.len());
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        match first_ts(&file, path, offsets[mid], since) {
+            Some(ts) if ts < since.ts => lo = mid,
+            _ => hi = mid,
+        }
+    }
+    Ok(Box::new(open_at(&file, offsets[lo], lo != 0)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    fn ts_of(_: &str, line: &str) -> Option<crate::timestamp::Timestamp> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        value.get("t")?.as_i64().map(i128::from)
+    }
+
+    fn first_line(since: crate::timestamp::Timestamp) -> String {
+        let file = File::open("tests/data/12.json.zst").unwrap();
+        let since = crate::input::Since {
+            ts: since,
+            ts_of: &ts_of,
+        };
+        let mut r =
+            BufReader::new(crate::seekable::open(file, "tests/data/12.json.zst", &since).unwrap());
+        let mut line = String::new();
+        r.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    #[test]
+    fn read_seek_table() {
+        let mut file = File::open("tests/data/12.json.zst").unwrap();
+        let offsets = crate::seekable::read_seek_table(&mut file)
+            .unwrap()
+            .unwrap();
+        assert_eq!(4, offsets.len());
+        assert_eq!(0, offsets[0]);
+    }
+
+    #[test]
+    fn regular_zstd_file() {
+        let mut file = File::open("tests/data/4.json.zst").unwrap();
+        assert!(crate::seekable::read_seek_table(&mut file)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn skip_frames() {
+        assert_eq!(r#"{"t":100, "add": "100_12"}"#, first_line(0));
+        assert_eq!(r#"{"t":100, "add": "100_12"}"#, first_line(103));
+        assert_eq!(r#"{"t":104, "add": "104_12"}"#, first_line(106));
+        assert_eq!(r#"{"t":110, "add": "110_12"}"#, first_line(1000));
+    }
+}
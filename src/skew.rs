@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::sync::Arc;
+
+use crate::input::{self, Input};
+use crate::timestamp::{self, Timestamp};
+
+/// Width of the intervals records are counted in, skew is estimated with this precision
+const BUCKET: Timestamp = 100 * timestamp::NANOS_PER_MILLI;
+
+///
+/// Number of records in every interval, by the index of the interval
+fn histogram(timestamps: &[Timestamp]) -> HashMap<Timestamp, u64> {
+    let mut counts: HashMap<Timestamp, u64> = HashMap::new();
+    for ts in timestamps {
+        *counts.entry(ts.div_euclid(BUCKET)).or_default() += 1;
+    }
+    counts
+}
+
+///
+/// Estimates how far the clock of one input is behind the clock of another one
+/// by the shift which lines up bursts of their records the best.
+///
+/// # Arguments
+///
+/// * `reference`: timestamps of the input whose clock is taken as the correct one
+/// * `other`: timestamps of the input with skewed clock
+/// * `max`: the largest skew looked for, either way
+///
+/// returns: the shift to add to the timestamps of `other`, the smallest one of equally good shifts,
+/// if records of the inputs overlap at any shift
+pub fn estimate(reference: &[Timestamp], other: &[Timestamp], max: Timestamp) -> Option<Timestamp> {
+    let reference = histogram(reference);
+    let other = histogram(other);
+    let max = max / BUCKET;
+    let mut scores: HashMap<Timestamp, u64> = HashMap::new();
+    for (bucket, count) in &other {
+        for (reference_bucket, reference_count) in &reference {
+            let shift = reference_bucket - bucket;
+            if shift.abs() <= max {
+                *scores.entry(shift).or_default() += count * reference_count;
+            }
+        }
+    }
+    scores
+        .into_iter()
+        .max_by(|(a_shift, a), (b_shift, b)| a.cmp(b).then(b_shift.abs().cmp(&a_shift.abs())))
+        .map(|(shift, _)| shift * BUCKET)
+}
+
+///
+/// Timestamps of the records which are already buffered by the input, nothing is consumed
+fn sample<R: BufRead>(parser: &timestamp::Parser, source: &mut Input<R>) -> Vec<Timestamp> {
+    if source.format == input::Format::Ndjson {
+        parser.sample(&source.path, &mut source.reader);
+    }
+    input::buffered(&mut source.reader)
+        .lines()
+        .filter_map(|line| {
+            crate::parse_line(line.to_string(), parser, &source.path, source.format).ok()
+        })
+        .map(|(ts, _)| ts)
+        .collect()
+}
+
+///
+/// Estimates clock skew of every input against the input with the most records sampled
+/// and shifts timestamps of the input by it, unless the offset of the input is given explicitly.
+/// Estimations are reported on stderr.
+///
+/// # Arguments
+///
+/// * `parser`: parser the inputs are merged with, the offsets are added to it
+/// * `sources`: inputs, only the records which are already buffered are looked at
+/// * `max`: the largest skew looked for, either way
+pub fn apply<R: BufRead>(parser: &mut timestamp::Parser, sources: &mut [Input<R>], max: Timestamp) {
+    // entries of an archive share the path, and so the clock
+    let mut samples: Vec<(Arc<str>, Vec<Timestamp>)> = Vec::new();
+    for source in sources.iter_mut() {
+        let timestamps = sample(parser, source);
+        match samples.iter_mut().find(|(path, _)| *path == source.path) {
+            Some((_, sampled)) => sampled.extend(timestamps),
+            None => samples.push((source.path.clone(), timestamps)),
+        }
+    }
+    let (reference_path, reference) = match samples.iter().max_by_key(|(_, sampled)| sampled.len())
+    {
+        Some(reference) => reference,
+        None => return,
+    };
+    for (path, sampled) in &samples {
+        if path == reference_path || parser.offsets.contains_key(path.as_ref()) {
+            continue;
+        }
+        match estimate(reference, sampled, max) {
+            Some(shift) => {
+                eprintln!(
+                    "'{}': estimated clock skew against '{}' is {:+}ms",
+                    path,
+                    reference_path,
+                    shift as f64 / timestamp::NANOS_PER_MILLI as f64
+                );
+                parser.offsets.insert(path.to_string(), shift);
+            }
+            None => eprintln!(
+                "'{}': clock skew is not estimated, records don't overlap with '{}'",
+                path, reference_path
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::skew::estimate;
+    use crate::timestamp::NANOS_PER_MILLI;
+
+    /// bursts of records at the given milliseconds, the number of records in every burst
+    fn bursts(bursts: &[(i128, usize)], shift: i128) -> Vec<i128> {
+        bursts
+            .iter()
+            .flat_map(|(ms, count)| {
+                (0..*count).map(move |i| (ms + shift + i as i128) * NANOS_PER_MILLI)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines_up_bursts() {
+        let events = [(1_000, 10), (5_000, 3), (12_000, 7), (12_500, 1)];
+        let reference = bursts(&events, 0);
+        let behind = bursts(&events, -2_300);
+        assert_eq!(
+            Some(2_300 * NANOS_PER_MILLI),
+            estimate(&reference, &behind, 60_000 * NANOS_PER_MILLI)
+        );
+        let ahead = bursts(&events, 700);
+        assert_eq!(
+            Some(-700 * NANOS_PER_MILLI),
+            estimate(&reference, &ahead, 60_000 * NANOS_PER_MILLI)
+        );
+    }
+
+    #[test]
+    fn no_overlap() {
+        let reference = bursts(&[(1_000, 5)], 0);
+        let other = bursts(&[(1_000, 5)], -90_000);
+        assert_eq!(None, estimate(&reference, &other, 60_000 * NANOS_PER_MILLI));
+        assert_eq!(None, estimate(&reference, &[], 60_000 * NANOS_PER_MILLI));
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use crate::error;
+use crate::output::{Sink, TempFile};
+use crate::timestamp::Timestamp;
+use crate::transform::LEVEL_KEYS;
+
+/// Records inserted in a transaction
+const BATCH_SIZE: usize = 10_000;
+
+fn other(e: rusqlite::Error) -> std::io::Error {
+    std::io::Error::other(format!("cannot write SQLite database: {}", e))
+}
+
+///
+/// Name of the table quoted as SQL identifier
+fn quote(table: &str) -> String {
+    format!("\"{}\"", table.replace('"', "\"\""))
+}
+
+///
+/// Level of the record under any of the level keys, numbers are written as they are
+fn level_of(line: &str) -> Option<String> {
+    let record = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line).ok()?;
+    match LEVEL_KEYS.iter().find_map(|key| record.get(*key))? {
+        serde_json::Value::String(level) => Some(level.clone()),
+        serde_json::Value::Number(level) => Some(level.to_string()),
+        _ => None,
+    }
+}
+
+///
+/// SQLite database the records are inserted into, along with their timestamps and levels.
+/// It's written under a temporary name next to it and renamed once it's complete.
+pub struct Sqlite {
+    connection: rusqlite::Connection,
+    table: String,
+    temp: TempFile,
+    path: PathBuf,
+    /// records which are not inserted yet, along with their timestamps
+    rows: Vec<(Timestamp, String)>,
+}
+
+impl Sqlite {
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: the database to write, it's replaced once the merge is complete
+    /// * `table`: the table the records are inserted into
+    pub fn create(path: &str, table: &str) -> Result<Self, error::MrgError> {
+        let path = Path::new(path);
+        let temp = TempFile::next_to(path)?;
+        // the database of the previous run which failed
+        let _ = std::fs::remove_file(&temp.0);
+        let connection = rusqlite::Connection::open(&temp.0)?;
+        connection.execute_batch(&format!(
+            "PRAGMA journal_mode = OFF;
+             PRAGMA synchronous = OFF;
+             CREATE TABLE {} (ts INTEGER NOT NULL, level TEXT, record TEXT NOT NULL);",
+            quote(table)
+        ))?;
+        Ok(Sqlite {
+            connection,
+            table: table.to_string(),
+            temp,
+            path: path.to_path_buf(),
+            rows: Vec::new(),
+        })
+    }
+
+    ///
+    /// Inserts the records gathered so far in a transaction
+    fn insert_rows(&mut self) -> Result<(), rusqlite::Error> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare(&format!(
+                "INSERT INTO {} (ts, level, record) VALUES (?1, ?2, ?3)",
+                quote(&self.table)
+            ))?;
+            for (ts, line) in self.rows.drain(..) {
+                // nanoseconds fit 64 bits up to the year 2262
+                let ts = i64::try_from(ts).unwrap_or(if ts < 0 { i64::MIN } else { i64::MAX });
+                insert.execute(rusqlite::params![ts, level_of(&line), line])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    ///
+    /// Inserts the rest of the records and indexes them by timestamps, the database takes its place
+    pub fn finish(mut self) -> Result<(), error::MrgError> {
+        self.insert_rows()?;
+        self.connection.execute_batch(&format!(
+            "CREATE INDEX {} ON {} (ts);",
+            quote(&format!("{}_ts", self.table)),
+            quote(&self.table)
+        ))?;
+        self.connection.close().map_err(|(_, e)| e)?;
+        std::fs::rename(&self.temp.0, &self.path)?;
+        eprintln!(
+            "'{}': {} bytes written",
+            self.path.display(),
+            std::fs::metadata(&self.path)?.len()
+        );
+        Ok(())
+    }
+}
+
+impl Sink for Sqlite {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        self.rows.push((ts, line.to_string()));
+        match self.rows.len() >= BATCH_SIZE {
+            true => self.insert_rows().map_err(other),
+            false => Ok(()),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        self.insert_rows().map_err(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::Sink;
+
+    #[test]
+    fn sqlite() {
+        let dir = std::env::temp_dir().join(format!("jmrg-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("merged.sqlite");
+        let mut output = crate::sqlite::Sqlite::create(path.to_str().unwrap(), "my logs").unwrap();
+        output
+            .emit(1_000, r#"{"ts":1,"level":"info","msg":"a"}"#)
+            .unwrap();
+        output.emit(2_000, r#"{"ts":2,"lvl":30}"#).unwrap();
+        output.emit(3_000, "not json").unwrap();
+        output.finish().unwrap();
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let rows = connection
+            .prepare("SELECT ts, level, record FROM \"my logs\" ORDER BY ts")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                (
+                    1_000,
+                    Some("info".to_string()),
+                    r#"{"ts":1,"level":"info","msg":"a"}"#.to_string()
+                ),
+                (
+                    2_000,
+                    Some("30".to_string()),
+                    r#"{"ts":2,"lvl":30}"#.to_string()
+                ),
+                (3_000, None, "not json".to_string()),
+            ],
+            rows
+        );
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error;
+use crate::input::Stream;
+
+pub const SCHEME: &str = "ssh://";
+
+///
+/// Quotes the argument for the remote shell, which runs the command passed to ssh
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+///
+/// Builds `ssh` invocation printing the remote file addressed by `ssh://[user@]host[:port]/path`
+fn command(url: &str) -> Result<std::process::Command, error::MrgError> {
+    let invalid = || error::MrgError {
+        msg: format!(
+            "'{}' is not a valid SSH URL, expected ssh://[user@]host[:port]/path",
+            url
+        ),
+    };
+    let (authority, path) = url
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.find('/').map(|slash| rest.split_at(slash)))
+        .filter(|(authority, path)| !authority.is_empty() && path.len() > 1)
+        .ok_or_else(invalid)?;
+    let (user, host, port) = split_authority(authority);
+    // anything else could be taken by ssh for an option, e.g. `-oProxyCommand=...`
+    let valid = user.is_none_or(|user| is_name(user, "._-"))
+        && is_name(host, ".-_:%")
+        && port.is_none_or(|port| port.parse::<u16>().is_ok());
+    if !valid {
+        return Err(invalid());
+    }
+    let mut command = std::process::Command::new("ssh");
+    if let Some(port) = port {
+        command.args(["-p", port]);
+    }
+    let destination = match user {
+        Some(user) => format!("{}@{}", user, host),
+        None => host.to_string(),
+    };
+    command.args([destination.as_str(), "--", "cat", &quote(path)]);
+    Ok(command)
+}
+
+///
+/// Checks the user or the host name has nothing but letters, digits and the given symbols,
+/// and doesn't start with `-`
+fn is_name(name: &str, symbols: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || symbols.contains(c))
+}
+
+///
+/// Splits the authority into user, host and port, IPv6 hosts are in brackets like `[::1]:2222`,
+/// which are dropped as ssh takes the address as it is
+fn split_authority(authority: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (user, host) = match authority.rsplit_once('@') {
+        Some((user, host)) => (Some(user), host),
+        None => (None, authority),
+    };
+    let (host, port) = match host.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        Some((address, rest)) => (address, rest.strip_prefix(':')),
+        None => match host.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host, None),
+        },
+    };
+    (user, host, port)
+}
+
+///
+/// Streams the remote file through `ssh`, which takes care of authentication and host keys
+pub fn open(url: &str) -> Result<Stream, error::MrgError> {
+    crate::input::spawn(command(url)?)
+}
+
+#[cfg(test)]
+mod tests {
+    fn args(url: &str) -> Vec<String> {
+        crate::ssh::command(url)
+            .unwrap()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn command() {
+        assert_eq!(
+            vec![
+                "-p",
+                "2222",
+                "me@host",
+                "--",
+                "cat",
+                "'/var/log/app.json.gz'"
+            ],
+            args("ssh://me@host:2222/var/log/app.json.gz")
+        );
+        assert_eq!(
+            vec!["host", "--", "cat", r"'/logs/it'\''s.json'"],
+            args("ssh://host/logs/it's.json")
+        );
+        assert_eq!(
+            vec!["-p", "2222", "me@::1", "--", "cat", "'/var/log/x'"],
+            args("ssh://me@[::1]:2222/var/log/x")
+        );
+        assert_eq!(
+            vec!["fe80::1", "--", "cat", "'/var/log/x'"],
+            args("ssh://[fe80::1]/var/log/x")
+        );
+        assert!(crate::ssh::command("ssh://host").is_err());
+        assert!(crate::ssh::command("ssh:///path").is_err());
+    }
+
+    #[test]
+    fn options_rejected() {
+        assert!(crate::ssh::command("ssh://-oProxyCommand=sh%20-c%20id/x").is_err());
+        assert!(crate::ssh::command("ssh://-oProxyCommand=sh -c id/x").is_err());
+        assert!(crate::ssh::command("ssh://-l@host/x").is_err());
+        assert!(crate::ssh::command("ssh://me@-host/x").is_err());
+        assert!(crate::ssh::command("ssh://me@[-oProxyCommand=id]/x").is_err());
+        assert!(crate::ssh::command("ssh://host:-p/x").is_err());
+        assert!(crate::ssh::command("ssh://me@host:/x").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::io::{BufWriter, Write};
+use std::net::{TcpStream, UdpSocket};
+
+use crate::error;
+use crate::output::Sink;
+use crate::timestamp::{self, Timestamp};
+use crate::transform::{Severity, HOST_KEYS};
+
+/// Facility of the messages, user-level ones
+const FACILITY: u8 = 1;
+/// App name of the messages
+const APP_NAME: &str = "jmrg";
+/// Value of the header fields which are unknown
+const NIL: &str = "-";
+/// Byte order mark MSG encoded as UTF-8 starts with
+const BOM: &str = "\u{feff}";
+
+///
+/// Host of the record if it has one, header fields are restricted to printable ASCII of 255 characters at most
+fn host_of(record: Option<&serde_json::Map<String, serde_json::Value>>) -> String {
+    let host = record.and_then(|record| {
+        HOST_KEYS
+            .iter()
+            .find_map(|key| record.get(*key).and_then(serde_json::Value::as_str))
+    });
+    match host {
+        Some(host) if !host.is_empty() => host
+            .chars()
+            .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+            .take(255)
+            .collect(),
+        _ => NIL.to_string(),
+    }
+}
+
+///
+/// RFC 5424 message of the record, e.g. `<14>1 2024-05-01T12:00:00.000000Z web-1 jmrg - - - {"msg":"a"}`,
+/// its level becomes the severity and the record itself is the message
+fn message(ts: Timestamp, line: &str) -> String {
+    let record = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line).ok();
+    let severity = Severity::of(line).unwrap_or(Severity::Info).syslog();
+    let timestamp = timestamp::to_datetime(ts)
+        .map(|datetime| datetime.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string())
+        .unwrap_or_else(|| NIL.to_string());
+    format!(
+        "<{}>1 {} {} {} {} {} {} {}{}",
+        FACILITY * 8 + severity,
+        timestamp,
+        host_of(record.as_ref()),
+        APP_NAME,
+        NIL,
+        NIL,
+        NIL,
+        if line.is_ascii() { "" } else { BOM },
+        line
+    )
+}
+
+///
+/// Syslog server the records are forwarded to as RFC 5424 messages, `udp://host:port` or `tcp://host:port`
+pub enum Syslog {
+    /// a message per datagram
+    Udp(UdpSocket),
+    /// messages are preceded by their lengths, as RFC 6587 octet counting does
+    Tcp(BufWriter<TcpStream>),
+}
+
+impl Syslog {
+    pub fn connect(url: &str) -> Result<Self, error::MrgError> {
+        match url.split_once("://") {
+            Some(("udp", address)) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(address)?;
+                Ok(Syslog::Udp(socket))
+            }
+            Some(("tcp", address)) => Ok(Syslog::Tcp(BufWriter::new(TcpStream::connect(address)?))),
+            _ => Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid syslog address, expected udp://host:port or tcp://host:port",
+                    url
+                ),
+            }),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), error::MrgError> {
+        match self {
+            Syslog::Udp(_) => Ok(()),
+            Syslog::Tcp(mut writer) => Ok(writer.flush()?),
+        }
+    }
+}
+
+impl Sink for Syslog {
+    fn emit(&mut self, ts: Timestamp, line: &str) -> std::io::Result<()> {
+        let message = message(ts, line);
+        match self {
+            Syslog::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Syslog::Tcp(writer) => write!(writer, "{} {}", message.len(), message),
+        }
+    }
+
+    fn flush_records(&mut self) -> std::io::Result<()> {
+        match self {
+            Syslog::Udp(_) => Ok(()),
+            Syslog::Tcp(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::output::Sink;
+
+    #[test]
+    fn message() {
+        assert_eq!(
+            r#"<11>1 2024-05-01T12:00:00.123456Z web-1 jmrg - - - {"level":"error","host":"web-1"}"#,
+            crate::syslog::message(
+                1_714_564_800_123_456_789,
+                r#"{"level":"error","host":"web-1"}"#
+            )
+        );
+        assert_eq!(
+            "<14>1 1970-01-01T00:00:00.000000Z - jmrg - - - \u{feff}не json",
+            crate::syslog::message(0, "не json")
+        );
+    }
+
+    #[test]
+    fn udp() {
+        let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = format!("udp://{}", server.local_addr().unwrap());
+        let mut syslog = crate::syslog::Syslog::connect(&url).unwrap();
+        syslog.emit(0, r#"{"level":"warn"}"#).unwrap();
+        syslog.finish().unwrap();
+        let mut datagram = [0u8; 1024];
+        let len = server.recv(&mut datagram).unwrap();
+        assert_eq!(
+            br#"<12>1 1970-01-01T00:00:00.000000Z - jmrg - - - {"level":"warn"}"#,
+            &datagram[..len]
+        );
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = String::new();
+            stream.read_to_string(&mut received).unwrap();
+            received
+        });
+        let mut syslog = crate::syslog::Syslog::connect(&url).unwrap();
+        syslog.emit(0, "a").unwrap();
+        syslog.emit(0, "b").unwrap();
+        syslog.finish().unwrap();
+        assert_eq!(
+            "48 <14>1 1970-01-01T00:00:00.000000Z - jmrg - - - a48 <14>1 1970-01-01T00:00:00.000000Z - jmrg - - - b",
+            server.join().unwrap()
+        );
+        assert!(crate::syslog::Syslog::connect("syslog://host:514").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::timestamp::{self, Timestamp};
+
+/// Label of the moment TAI64 seconds are counted from, the beginning of 1970 TAI
+const EPOCH_LABEL: u64 = 1 << 62;
+
+///
+/// Differences between TAI and UTC in seconds, along with UTC seconds since epoch they are in effect from.
+/// TAI was ahead by 10 seconds before the first leap second.
+const LEAP_SECONDS: [(i64, i64); 27] = [
+    (78796800, 11),
+    (94694400, 12),
+    (126230400, 13),
+    (157766400, 14),
+    (189302400, 15),
+    (220924800, 16),
+    (252460800, 17),
+    (283996800, 18),
+    (315532800, 19),
+    (362793600, 20),
+    (394329600, 21),
+    (425865600, 22),
+    (489024000, 23),
+    (567993600, 24),
+    (631152000, 25),
+    (662688000, 26),
+    (709948800, 27),
+    (741484800, 28),
+    (773020800, 29),
+    (820454400, 30),
+    (867715200, 31),
+    (915148800, 32),
+    (1136073600, 33),
+    (1230768000, 34),
+    (1341100800, 35),
+    (1435708800, 36),
+    (1483228800, 37),
+];
+
+///
+/// Parses TAI64N label without `@`, e.g. `4000000065aa1234075bcd15`, as written by daemontools multilog and s6.
+///
+/// returns: Result<Timestamp, String>
+pub fn parse(label: &str) -> Result<Timestamp, String> {
+    let invalid = || format!("'@{}' is not a TAI64N label", label);
+    if label.len() != 24 || !label.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let seconds = u64::from_str_radix(&label[..16], 16).map_err(|_| invalid())?;
+    let nanos = u32::from_str_radix(&label[16..], 16).map_err(|_| invalid())?;
+    if nanos >= 1_000_000_000 {
+        return Err(invalid());
+    }
+    let tai =
+        i64::try_from(i128::from(seconds) - i128::from(EPOCH_LABEL)).map_err(|_| invalid())?;
+    let leap = LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(since, leap)| tai - leap >= *since)
+        .map_or(10, |(_, leap)| *leap);
+    let utc = chrono::DateTime::from_timestamp(tai - leap, nanos).ok_or_else(invalid)?;
+    Ok(timestamp::from_datetime(&utc))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse() {
+        // 2024-01-02T15:04:05.123456789Z
+        assert_eq!(
+            1704207845123456789,
+            crate::tai64::parse("400000006594260a075bcd15").unwrap()
+        );
+        // 1970-01-01T00:00:00Z
+        assert_eq!(0, crate::tai64::parse("400000000000000a00000000").unwrap());
+        assert!(crate::tai64::parse("400000006594260a").is_err());
+        assert!(crate::tai64::parse("400000006594260affffffff").is_err());
+    }
+}
@@ -0,0 +1,1583 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+
+use serde::Deserializer;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+
+use crate::error;
+use crate::locale::Locale;
+
+///
+/// Nanoseconds since epoch, the key records are ordered by.
+/// It's wide enough for any datetime and for epoch timestamps of 64 bits in any unit.
+pub type Timestamp = i128;
+
+pub const NANOS_PER_SECOND: Timestamp = 1_000_000_000;
+pub const NANOS_PER_MILLI: Timestamp = 1_000_000;
+
+///
+/// Timestamp of the moment given in milliseconds since epoch, e.g. in the command line
+pub fn from_millis(millis: i64) -> Timestamp {
+    Timestamp::from(millis) * NANOS_PER_MILLI
+}
+
+///
+/// Timestamp of the time of the day `days` away from the day of the anchor in the zone,
+/// the earlier one of ambiguous local times is taken
+fn day_at<Tz: TimeZone>(
+    tz: &Tz,
+    anchor: Timestamp,
+    days: i64,
+    time: NaiveTime,
+) -> Option<Timestamp> {
+    let date = to_datetime(anchor)?
+        .with_timezone(tz)
+        .date_naive()
+        .checked_add_signed(chrono::TimeDelta::try_days(days)?)?;
+    let dt = date
+        .and_time(time)
+        .and_local_timezone(tz.clone())
+        .earliest()?;
+    Some(from_datetime(&dt))
+}
+
+///
+/// Parses the bound of the timestamps given in the command line: milliseconds since epoch,
+/// RFC 3339 datetime like `2024-05-01T12:00:00Z`, or a time relative to the anchor. Durations like `2h` go back
+/// from the anchor and signed ones like `+30m` go either way, `now`, `today`, `yesterday` and `tomorrow`
+/// may be followed by the time of the day in the zone like `yesterday 14:00`, which is today's time on its own.
+pub fn parse_bound(
+    value: &str,
+    anchor: Timestamp,
+    zone: Zone,
+) -> Result<Timestamp, error::MrgError> {
+    let invalid = || error::MrgError {
+        msg: format!(
+            "'{}' is not a valid time, expected milliseconds since epoch, RFC 3339 datetime, \
+            duration like 2h or relative time like 'yesterday 14:00'",
+            value
+        ),
+    };
+    let value = value.trim();
+    if let Ok(millis) = value.parse::<i64>() {
+        return Ok(from_millis(millis));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(from_datetime(&dt));
+    }
+    // days are only relative ones, durations of other options are shorter
+    let days = value
+        .strip_suffix('d')
+        .and_then(|number| number.parse::<f64>().ok())
+        .filter(|days| days.abs() < 1e8)
+        .map(|days| (days * 86_400.0 * NANOS_PER_SECOND as f64).round() as Timestamp);
+    if let Some(duration) = days.or_else(|| parse_duration(value).ok()) {
+        return match value.starts_with(['+', '-']) {
+            true => Ok(anchor + duration),
+            false => Ok(anchor - duration),
+        };
+    }
+    let (day, time) = value.split_once(' ').unwrap_or((value, ""));
+    let (days, time) = match day.to_ascii_lowercase().as_str() {
+        "now" if time.is_empty() => return Ok(anchor),
+        "today" => (0, time),
+        "yesterday" => (-1, time),
+        "tomorrow" => (1, time),
+        _ if time.is_empty() => (0, value),
+        _ => return Err(invalid()),
+    };
+    let time = match time.trim() {
+        "" => NaiveTime::MIN,
+        time => NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .map_err(|_| invalid())?,
+    };
+    match zone {
+        Zone::Utc => day_at(&chrono::Utc, anchor, days, time),
+        Zone::Local => day_at(&chrono::Local, anchor, days, time),
+        Zone::Fixed(offset) => day_at(&offset, anchor, days, time),
+        Zone::Named(tz) => day_at(&tz, anchor, days, time),
+    }
+    .ok_or_else(invalid)
+}
+
+///
+/// Bounds of the timestamps of the records which are merged, the lower one is inclusive and the upper one is exclusive
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub since: Option<Timestamp>,
+    pub until: Option<Timestamp>,
+}
+
+impl Bounds {
+    pub fn contains(&self, ts: Timestamp) -> bool {
+        self.since.is_none_or(|since| ts >= since) && !self.passed(ts)
+    }
+
+    ///
+    /// The record is past the upper bound, so are the records which follow it in a sorted input
+    pub fn passed(&self, ts: Timestamp) -> bool {
+        self.until.is_some_and(|until| ts >= until)
+    }
+}
+
+///
+/// Timestamp of the datetime
+pub fn from_datetime<Tz: TimeZone>(dt: &DateTime<Tz>) -> Timestamp {
+    // leap seconds, whose nanoseconds go beyond a second, are clamped to the end of the preceding second
+    let nanos = dt.timestamp_subsec_nanos().min(999_999_999);
+    Timestamp::from(dt.timestamp()) * NANOS_PER_SECOND + Timestamp::from(nanos)
+}
+
+///
+/// Datetime of the timestamp in UTC, unless it's out of range of datetimes
+pub fn to_datetime(ts: Timestamp) -> Option<DateTime<chrono::Utc>> {
+    let seconds = i64::try_from(ts.div_euclid(NANOS_PER_SECOND)).ok()?;
+    let nanos = ts.rem_euclid(NANOS_PER_SECOND) as u32;
+    DateTime::from_timestamp(seconds, nanos)
+}
+
+///
+/// RFC 3339 datetime of the timestamp in UTC, with as many fractional digits as needed
+pub fn to_rfc3339(ts: Timestamp) -> Option<String> {
+    to_rfc3339_in(ts, Zone::Utc)
+}
+
+///
+/// RFC 3339 datetime of the timestamp in the zone, with as many fractional digits as needed
+pub fn to_rfc3339_in(ts: Timestamp, zone: Zone) -> Option<String> {
+    let utc = to_datetime(ts)?;
+    let precision = chrono::SecondsFormat::AutoSi;
+    Some(match zone {
+        Zone::Utc => utc.to_rfc3339_opts(precision, true),
+        Zone::Local => utc
+            .with_timezone(&chrono::Local)
+            .to_rfc3339_opts(precision, false),
+        Zone::Fixed(offset) => utc.with_timezone(&offset).to_rfc3339_opts(precision, false),
+        Zone::Named(tz) => utc.with_timezone(&tz).to_rfc3339_opts(precision, false),
+    })
+}
+
+///
+/// Parses signed duration like `+2.5s`, `-300ms` or `1h`, units are `ns`, `us`, `ms`, `s`, `m` and `h`
+pub fn parse_duration(value: &str) -> Result<Timestamp, error::MrgError> {
+    let invalid = || error::MrgError {
+        msg: format!(
+            "'{}' is not a valid duration, expected number with unit like +2.5s, -300ms or 1h",
+            value
+        ),
+    };
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .ok_or_else(invalid)?;
+    let (number, unit) = value.split_at(split);
+    let nanos: f64 = match unit {
+        "ns" => 1.0,
+        "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        "m" => 60e9,
+        "h" => 3600e9,
+        _ => return Err(invalid()),
+    };
+    let number = number.parse::<f64>().map_err(|_| invalid())?;
+    let duration = (number * nanos).round();
+    if !duration.is_finite() || duration.abs() >= Timestamp::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(duration as Timestamp)
+}
+
+/// Layouts of datetimes without offset, `T` or space separates the date and the time,
+/// Java loggers like Log4j separate milliseconds with comma, e.g. `2024-01-02 15:04:05,123`
+const NAIVE_LAYOUTS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S,%3f",
+    "%Y-%m-%dT%H:%M:%S,%3f",
+];
+
+/// Layouts of datetimes written the way locales write them, e.g. `2. Jan 2024 15:04:05`,
+/// they're only tried once the localized names are translated
+const DAY_MONTH_LAYOUTS: [&str; 4] = [
+    "%d. %b %Y %H:%M:%S%.f",
+    "%d %b %Y %H:%M:%S%.f",
+    "%a, %d. %b %Y %H:%M:%S%.f",
+    "%a, %d %b %Y %H:%M:%S%.f",
+];
+
+/// Layout of classic syslog timestamps like `Jan  2 15:04:05` prefixed with the year they lack
+const SYSLOG_LAYOUT: &str = "%Y %b %e %H:%M:%S%.f";
+
+///
+/// Time zone naive datetimes are interpreted in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+    Named(chrono_tz::Tz),
+}
+
+impl Zone {
+    ///
+    /// Parses `UTC`, `local`, an offset like `+02:00` or IANA name like `Europe/Berlin`
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        if name.eq_ignore_ascii_case("utc") {
+            return Ok(Zone::Utc);
+        }
+        if name.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        if let Ok(offset) = name.parse::<FixedOffset>() {
+            return Ok(Zone::Fixed(offset));
+        }
+        name.parse::<chrono_tz::Tz>()
+            .map(Zone::Named)
+            .map_err(|_| error::MrgError {
+                msg: format!(
+                    "unknown time zone '{}', expected UTC, local, offset like +02:00 or name like Europe/Berlin",
+                    name
+                ),
+            })
+    }
+
+    ///
+    /// Timestamp of the naive datetime in this zone.
+    /// The earliest of ambiguous times is taken, e.g. when clocks are turned back.
+    fn timestamp(self, naive: &NaiveDateTime) -> Result<Timestamp, String> {
+        let missing = || format!("'{}' doesn't exist in {:?}", naive, self);
+        match self {
+            Zone::Utc => Ok(from_datetime(&naive.and_utc())),
+            Zone::Local => Ok(from_datetime(
+                &chrono::Local
+                    .from_local_datetime(naive)
+                    .earliest()
+                    .ok_or_else(missing)?,
+            )),
+            Zone::Fixed(offset) => Ok(from_datetime(
+                &offset
+                    .from_local_datetime(naive)
+                    .earliest()
+                    .ok_or_else(missing)?,
+            )),
+            Zone::Named(tz) => Ok(from_datetime(
+                &tz.from_local_datetime(naive)
+                    .earliest()
+                    .ok_or_else(missing)?,
+            )),
+        }
+    }
+}
+
+///
+/// Unit of epoch timestamps, they are normalized to nanoseconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+    /// Windows FILETIME, 100 nanosecond intervals since 1601
+    Filetime,
+    /// .NET ticks, 100 nanosecond intervals since the beginning of the year 1
+    Ticks,
+    /// one of seconds, milliseconds, microseconds and nanoseconds, whichever the magnitude suggests
+    Auto,
+}
+
+/// 100 nanosecond intervals between the beginning of 1601 and the epoch
+const FILETIME_EPOCH: Timestamp = 116444736000000000;
+/// 100 nanosecond intervals between the beginning of the year 1 and the epoch
+const TICKS_EPOCH: Timestamp = 621355968000000000;
+
+impl Unit {
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "s" => Ok(Unit::Seconds),
+            "ms" => Ok(Unit::Millis),
+            "us" => Ok(Unit::Micros),
+            "ns" => Ok(Unit::Nanos),
+            "filetime" => Ok(Unit::Filetime),
+            "ticks" => Ok(Unit::Ticks),
+            "auto" => Ok(Unit::Auto),
+            _ => Err(error::MrgError {
+                msg: format!(
+                    "unknown unit '{}', expected one of s, ms, us, ns, filetime, ticks, auto",
+                    name
+                ),
+            }),
+        }
+    }
+
+    ///
+    /// Unit of the epoch timestamp judging by its magnitude, assuming it's within years 1973 to 5138
+    fn of_magnitude(value: f64) -> Self {
+        match value.abs() {
+            v if v < 1e11 => Unit::Seconds,
+            v if v < 1e14 => Unit::Millis,
+            v if v < 1e17 => Unit::Micros,
+            _ => Unit::Nanos,
+        }
+    }
+
+    ///
+    /// Integer timestamps, the ones beyond the range of timestamps, e.g. quoted numbers of 30 digits, are rejected
+    fn normalize(self, value: Timestamp) -> Option<Timestamp> {
+        match self {
+            Unit::Seconds => value.checked_mul(NANOS_PER_SECOND),
+            Unit::Millis => value.checked_mul(NANOS_PER_MILLI),
+            Unit::Micros => value.checked_mul(1000),
+            Unit::Nanos => Some(value),
+            Unit::Filetime => value.checked_sub(FILETIME_EPOCH)?.checked_mul(100),
+            Unit::Ticks => value.checked_sub(TICKS_EPOCH)?.checked_mul(100),
+            Unit::Auto => Unit::of_magnitude(value as f64).normalize(value),
+        }
+    }
+
+    ///
+    /// Fractional timestamps, e.g. seconds with milliseconds after the point
+    fn normalize_f64(self, value: f64) -> Option<Timestamp> {
+        let nanos = match self {
+            Unit::Seconds => value * 1e9,
+            Unit::Millis => value * 1e6,
+            Unit::Micros => value * 1e3,
+            Unit::Nanos => value,
+            Unit::Filetime => (value - FILETIME_EPOCH as f64) * 100.0,
+            Unit::Ticks => (value - TICKS_EPOCH as f64) * 100.0,
+            Unit::Auto => return Unit::of_magnitude(value).normalize_f64(value),
+        }
+        .round();
+        // `as` saturates, the values beyond the range of timestamps are rejected instead
+        (nanos.is_finite() && nanos.abs() < Timestamp::MAX as f64).then_some(nanos as Timestamp)
+    }
+
+    ///
+    /// Epoch timestamp in the unit, the inverse of `normalize`,
+    /// it's fractional when the timestamp is more precise than the unit
+    pub fn denormalize(self, ts: Timestamp) -> Option<serde_json::Number> {
+        let (nanos, epoch): (Timestamp, Timestamp) = match self {
+            Unit::Seconds => (NANOS_PER_SECOND, 0),
+            Unit::Millis => (NANOS_PER_MILLI, 0),
+            Unit::Micros => (1000, 0),
+            Unit::Nanos | Unit::Auto => (1, 0),
+            Unit::Filetime => (100, FILETIME_EPOCH),
+            Unit::Ticks => (100, TICKS_EPOCH),
+        };
+        if ts % nanos == 0 {
+            if let Ok(value) = i64::try_from(ts / nanos + epoch) {
+                return Some(serde_json::Number::from(value));
+            }
+        }
+        serde_json::Number::from_f64(ts as f64 / nanos as f64 + epoch as f64)
+    }
+}
+
+///
+/// Where the timestamp of the records at the beginning of the input comes from, when they lack the keys
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fallback {
+    /// modification time of the file
+    Mtime,
+}
+
+impl Fallback {
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "mtime" => Ok(Fallback::Mtime),
+            _ => Err(error::MrgError {
+                msg: format!("unknown fallback '{}', expected mtime", name),
+            }),
+        }
+    }
+
+    ///
+    /// returns: the timestamp of the input at `path`, if it's a file in the filesystem
+    pub fn of(self, path: &str) -> Option<Timestamp> {
+        match self {
+            Fallback::Mtime => {
+                let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+                Some(from_datetime(&DateTime::<chrono::Utc>::from(modified)))
+            }
+        }
+    }
+}
+
+///
+/// Describes how datetimes are parsed, in particular the ones lacking offset or year
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Calendar {
+    /// zone of datetimes without offset
+    pub zone: Option<Zone>,
+    /// year of syslog timestamps, it's inferred unless given
+    pub year: Option<i32>,
+    /// language of month and weekday names, English ones are always accepted
+    pub locale: Option<Locale>,
+    /// leap seconds and `24:00:00` are rejected instead of being mapped to valid times
+    pub strict: bool,
+}
+
+impl Calendar {
+    ///
+    /// Parses the datetime, the one with month or weekday names in the language of the locale included
+    pub fn parse(&self, value: &str) -> Result<Timestamp, String> {
+        self.parse_english(value).or_else(|e| {
+            match self.locale.and_then(|locale| locale.translate(value)) {
+                Some(translated) => self
+                    .parse_english(&translated)
+                    .or_else(|_| self.parse_day_month(&translated)),
+                None => Err(e),
+            }
+        })
+    }
+
+    ///
+    /// Parses translated datetime with the day before the month, e.g. `2. Jan 2024 15:04:05 +0100`,
+    /// the offset is optional
+    fn parse_day_month(&self, value: &str) -> Result<Timestamp, String> {
+        for layout in DAY_MONTH_LAYOUTS {
+            if let Ok(dt) = DateTime::parse_from_str(value, &format!("{} %z", layout)) {
+                self.check_leap(value, dt.timestamp_subsec_nanos())?;
+                return Ok(from_datetime(&dt));
+            }
+        }
+        let naive = DAY_MONTH_LAYOUTS
+            .iter()
+            .find_map(|layout| NaiveDateTime::parse_from_str(value, layout).ok())
+            .ok_or_else(|| format!("'{}' is not a datetime", value))?;
+        self.in_zone(value, &naive)
+    }
+
+    ///
+    /// Parses ISO8601 or RFC 2822 datetime, e.g. `Tue, 02 Jan 2024 15:04:05 +0000`, or TAI64N label.
+    /// Datetimes without offset are only accepted when the zone to interpret them in is given.
+    fn parse_english(&self, value: &str) -> Result<Timestamp, String> {
+        if let Some(label) = value.strip_prefix('@') {
+            return crate::tai64::parse(label);
+        }
+        let parsed =
+            DateTime::parse_from_rfc3339(value).or_else(|_| DateTime::parse_from_rfc2822(value));
+        if let Ok(dt) = parsed {
+            self.check_leap(value, dt.timestamp_subsec_nanos())?;
+            return Ok(from_datetime(&dt));
+        }
+        let naive = NAIVE_LAYOUTS
+            .iter()
+            .find_map(|layout| NaiveDateTime::parse_from_str(value, layout).ok())
+            .or_else(|| parse_syslog(value, self.year));
+        let Some(naive) = naive else {
+            return self
+                .parse_end_of_day(value)
+                .unwrap_or_else(|| Err(format!("'{}' is not a datetime", value)));
+        };
+        self.in_zone(value, &naive)
+    }
+
+    ///
+    /// Interprets the datetime without offset in the zone, if it's given
+    fn in_zone(&self, value: &str, naive: &NaiveDateTime) -> Result<Timestamp, String> {
+        self.check_leap(value, naive.nanosecond())?;
+        let zone = self
+            .zone
+            .ok_or_else(|| format!("'{}' has no offset, use --assume-tz to interpret it", value))?;
+        zone.timestamp(naive)
+    }
+
+    ///
+    /// Leap seconds like `23:59:60.5` are taken as the last moment of the preceding second unless it's strict
+    fn check_leap(&self, value: &str, nanos: u32) -> Result<(), String> {
+        if self.strict && nanos >= 1_000_000_000 {
+            return Err(format!("'{}' is a leap second", value));
+        }
+        Ok(())
+    }
+
+    ///
+    /// `24:00:00`, the end of the day in ISO8601, is taken as the beginning of the next day unless it's strict
+    fn parse_end_of_day(&self, value: &str) -> Option<Result<Timestamp, String>> {
+        let date = value.get(..11).filter(|date| date.ends_with(['T', ' ']))?;
+        let rest = value[11..].strip_prefix("24:00:00")?;
+        // zero fraction is the only one allowed
+        let rest = rest
+            .strip_prefix('.')
+            .map_or(rest, |fraction| fraction.trim_start_matches('0'));
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        if self.strict {
+            return Some(Err(format!("'{}' is the end of the day", value)));
+        }
+        Some(
+            self.parse_english(&format!("{}23:59:59{}", date, rest))
+                .map(|ts| ts + NANOS_PER_SECOND),
+        )
+    }
+}
+
+///
+/// Parses classic syslog timestamp, e.g. `Jan  2 15:04:05`.
+/// Unless the year is given, the records are assumed to be from the last twelve months.
+fn parse_syslog(value: &str, year: Option<i32>) -> Option<NaiveDateTime> {
+    let parse = |year: i32| {
+        NaiveDateTime::parse_from_str(&format!("{} {}", year, value), SYSLOG_LAYOUT).ok()
+    };
+    if let Some(year) = year {
+        return parse(year);
+    }
+    let now = chrono::Utc::now().naive_utc();
+    // a day of slack for the zones ahead of UTC
+    match parse(now.year()) {
+        Some(naive) if naive <= now + chrono::Duration::days(1) => Some(naive),
+        _ => parse(now.year() - 1),
+    }
+}
+
+///
+/// Extracts the timestamp out of free text of the field, e.g. `[2024-01-02 15:04:05] started`
+pub struct TsRegex {
+    pub field: String,
+    /// the timestamp is captured by the group named `ts` or, if there is no such group, by the first one
+    pub re: regex::Regex,
+}
+
+impl TsRegex {
+    ///
+    /// Parses `field=<field>;re=<regex>`, e.g. `field=msg;re=^\[(?P<ts>[^\]]+)\]`
+    pub fn parse(spec: &str) -> Result<Self, error::MrgError> {
+        let invalid = || error::MrgError {
+            msg: format!(
+                "'{}' is not a valid timestamp regex, expected field=<field>;re=<regex>",
+                spec
+            ),
+        };
+        let (field, re) = spec.split_once(';').ok_or_else(invalid)?;
+        let field = field.strip_prefix("field=").ok_or_else(invalid)?;
+        let re = regex::Regex::new(re.strip_prefix("re=").ok_or_else(invalid)?)?;
+        if re.captures_len() < 2 {
+            return Err(error::MrgError {
+                msg: format!("'{}' doesn't capture the timestamp", re),
+            });
+        }
+        Ok(TsRegex {
+            field: field.to_string(),
+            re,
+        })
+    }
+
+    ///
+    /// The captured timestamp, if the text matches
+    fn capture<'t>(&self, text: &'t str) -> Option<&'t str> {
+        let captures = self.re.captures(text)?;
+        captures
+            .name("ts")
+            .or_else(|| captures.get(1))
+            .map(|m| m.as_str())
+    }
+}
+
+///
+/// Extracts timestamps out of the records, which are either numbers or datetimes
+pub struct Parser {
+    /// keys the timestamp is looked for, the first one found is used unless `priority` says otherwise
+    pub keys: HashSet<String>,
+    /// ranks of the keys which take precedence over the rest, the lower the better
+    pub priority: HashMap<String, usize>,
+    pub calendar: Calendar,
+    /// unit of epoch timestamps
+    pub unit: Unit,
+    /// units of the keys which differ from `unit`
+    pub units: HashMap<String, Unit>,
+    /// keys of the inputs which differ from `keys`, by path of the input
+    pub keys_for: HashMap<String, HashSet<String>>,
+    /// shifts of the timestamps of the inputs with skewed clocks, by path of the input
+    pub offsets: HashMap<String, Timestamp>,
+    /// fields whose values are joined with spaces into a datetime, e.g. date and time,
+    /// the records which have all of them are ordered by it instead of the keys
+    pub compose: Vec<String>,
+    /// the records whose field matches it are ordered by the captured timestamp instead of the keys
+    pub ts_regex: Option<TsRegex>,
+    /// number of records the timestamp key of every input is detected by, if it's detected at all
+    pub auto_key: Option<usize>,
+    /// records whose timestamp is behind the preceding one of the input get the timestamp of that one
+    pub monotonic: bool,
+    /// records which lack the keys get the timestamp of the preceding record of the input
+    pub inherit_ts: bool,
+    /// timestamp of the records lacking the keys before the first record with them
+    pub fallback: Option<Fallback>,
+    /// the timestamps are rewritten as RFC 3339 datetimes in the zone, if they are rewritten
+    pub humanize: Option<Zone>,
+    /// records which don't pass it are skipped, it's checked while the timestamps are parsed
+    pub filter: Option<crate::filter::Filter>,
+    /// keys detected in the inputs, by path of the input, none of them when detection failed
+    detected: RefCell<HashMap<String, Option<HashSet<String>>>>,
+}
+
+impl Parser {
+    pub fn new(keys: HashSet<String>) -> Self {
+        Parser {
+            keys,
+            priority: HashMap::new(),
+            calendar: Calendar::default(),
+            unit: Unit::Millis,
+            units: HashMap::new(),
+            keys_for: HashMap::new(),
+            offsets: HashMap::new(),
+            compose: Vec::new(),
+            ts_regex: None,
+            auto_key: None,
+            monotonic: false,
+            inherit_ts: false,
+            fallback: None,
+            humanize: None,
+            filter: None,
+            detected: RefCell::new(HashMap::new()),
+        }
+    }
+
+    ///
+    /// Detects the timestamp key of the input at `path` by the records at its beginning,
+    /// only the records which are already buffered are looked at, nothing is consumed.
+    /// Keys given for the input explicitly take precedence over the detected one.
+    pub fn sample<R: BufRead>(&self, path: &str, reader: &mut R) {
+        let lines = match self.auto_key {
+            Some(lines) if !self.keys_for.contains_key(path) => lines,
+            _ => return,
+        };
+        // inputs are sampled once, even if they are looked at before merging
+        if self.detected.borrow().contains_key(path) {
+            return;
+        }
+        let sampled = crate::input::buffered(reader);
+        let key = crate::detect::key_of(sampled.lines().take(lines));
+        match &key {
+            Some(key) => eprintln!("'{}': using '{}' as the timestamp key", path, key),
+            None => eprintln!(
+                "'{}': no timestamp key detected, looking for the configured keys",
+                path
+            ),
+        }
+        self.detected
+            .borrow_mut()
+            .insert(path.to_string(), key.map(|key| HashSet::from([key])));
+    }
+
+    ///
+    /// How much the timestamps of the input at `path` are shifted
+    pub fn offset(&self, path: &str) -> Timestamp {
+        self.offsets.get(path).copied().unwrap_or_default()
+    }
+
+    ///
+    /// Parses the value of the key
+    fn seed(&self, key: &str) -> ValueSeed {
+        ValueSeed {
+            calendar: self.calendar,
+            unit: *self.units.get(key).unwrap_or(&self.unit),
+        }
+    }
+
+    ///
+    /// Rank of the key if it's one of the keys, the keys without priority rank the lowest
+    fn rank(&self, keys: &HashSet<String>, key: &str) -> Option<usize> {
+        keys.contains(key)
+            .then(|| self.priority.get(key).copied().unwrap_or(usize::MAX))
+    }
+
+    ///
+    /// Checks the filter against the payload of the formats which wrap the records,
+    /// NDJSON records are checked while their timestamps are parsed
+    pub fn check(&self, payload: &str) -> Result<(), serde_json::Error> {
+        match &self.filter {
+            Some(filter) if !filter.admits(payload) => Err(serde::de::Error::custom(FILTERED)),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Extracts the timestamp out of the record of the input at `path`
+    pub fn parse(&self, raw_line: &str, path: &str) -> Result<Timestamp, serde_json::Error> {
+        self.with_keys(path, |keys| {
+            self.parse_keys(raw_line, keys).map(|(ts, _)| ts)
+        })
+    }
+
+    ///
+    /// Replaces the timestamp of the record of the input at `path` with `ts`, it's written
+    /// the same way as the replaced one: a number in the unit of the key, quoted or not,
+    /// or RFC 3339 datetime, in the zone of `humanize` or in UTC.
+    ///
+    /// returns: the record, unless its timestamp is joined from several fields or captured out of text
+    pub fn rewrite(&self, raw_line: &str, path: &str, ts: Timestamp) -> Option<String> {
+        self.replace(raw_line, path, |key, like| self.format(key, ts, like))
+    }
+
+    ///
+    /// Replaces the timestamp of the record of the input at `path` with RFC 3339 datetime
+    /// in the zone of `humanize`, whichever way it's written.
+    ///
+    /// returns: the record, unless its timestamp is joined from several fields or captured out of text
+    pub fn humanize(&self, raw_line: &str, path: &str, ts: Timestamp) -> Option<String> {
+        let zone = self.humanize.unwrap_or(Zone::Utc);
+        self.replace(raw_line, path, |_, _| {
+            to_rfc3339_in(ts, zone).map(serde_json::Value::String)
+        })
+    }
+
+    ///
+    /// Replaces the value of the key the timestamp of the record is taken from with the result of `f`,
+    /// which is given the key and the value
+    fn replace(
+        &self,
+        raw_line: &str,
+        path: &str,
+        f: impl FnOnce(&str, &serde_json::Value) -> Option<serde_json::Value>,
+    ) -> Option<String> {
+        let key: String = self.with_keys(path, |keys| {
+            self.parse_keys(raw_line, keys)
+                .ok()
+                .and_then(|(_, key)| key.map(String::from))
+        })?;
+        let mut record: serde_json::Value = serde_json::from_str(raw_line).ok()?;
+        let value = if split_pointer(&key).is_some() && record.pointer(&key).is_some() {
+            record.pointer_mut(&key)?
+        } else {
+            record.get_mut(&key)?
+        };
+        *value = f(&key, value)?;
+        serde_json::to_string(&record).ok()
+    }
+
+    ///
+    /// Writes the timestamp the same way as `like`, the value of the key
+    fn format(
+        &self,
+        key: &str,
+        ts: Timestamp,
+        like: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        match like {
+            serde_json::Value::Number(number) => {
+                let unit = self.seed(key).unit_of(number.as_f64()?);
+                unit.denormalize(ts).map(serde_json::Value::Number)
+            }
+            serde_json::Value::String(s) => match s.parse::<f64>() {
+                Ok(number) if number.is_finite() => {
+                    let unit = self.seed(key).unit_of(number);
+                    unit.denormalize(ts)
+                        .map(|number| serde_json::Value::String(number.to_string()))
+                }
+                _ => to_rfc3339_in(ts, self.humanize.unwrap_or(Zone::Utc))
+                    .map(serde_json::Value::String),
+            },
+            _ => None,
+        }
+    }
+
+    ///
+    /// Calls `f` with the keys of the input at `path`
+    fn with_keys<T>(&self, path: &str, f: impl FnOnce(&HashSet<String>) -> T) -> T {
+        if let Some(keys) = self.keys_for.get(path) {
+            return f(keys);
+        }
+        if let Some(Some(keys)) = self.detected.borrow().get(path) {
+            return f(keys);
+        }
+        f(&self.keys)
+    }
+
+    ///
+    /// returns: the timestamp and the key it's taken from, unless it's joined or captured
+    fn parse_keys<'k>(
+        &self,
+        raw_line: &str,
+        keys: &'k HashSet<String>,
+    ) -> Result<(Timestamp, Option<&'k str>), serde_json::Error> {
+        let mut des = serde_json::de::Deserializer::from_str(raw_line);
+        des.deserialize_map(EntryVisitor { parser: self, keys })
+    }
+}
+
+/// Error of the records which have none of the keys
+const NO_KEYS: &str = "no fields of the provided set";
+/// Error of the records which don't pass the filter
+const FILTERED: &str = "the record is filtered out";
+
+///
+/// Whether the record couldn't be parsed because it has none of the keys, unlike the malformed ones
+pub fn is_missing_key(e: &serde_json::Error) -> bool {
+    e.is_data() && e.to_string().starts_with(NO_KEYS)
+}
+
+///
+/// Whether the record is skipped because it doesn't pass the filter, it's not worth reporting
+pub fn is_filtered(e: &serde_json::Error) -> bool {
+    e.is_data() && e.to_string().starts_with(FILTERED)
+}
+
+///
+/// Splits JSON pointer key like `/records/0/eventTime` into the field of the record, `records`,
+/// and the pointer into its value, `/0/eventTime`
+fn split_pointer(key: &str) -> Option<(Cow<'_, str>, &str)> {
+    let pointer = key.strip_prefix('/')?;
+    let (root, rest) = pointer.split_at(pointer.find('/').unwrap_or(pointer.len()));
+    let root = if root.contains('~') {
+        Cow::Owned(root.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(root)
+    };
+    Some((root, rest))
+}
+
+struct EntryVisitor<'a, 'k> {
+    parser: &'a Parser,
+    /// keys of the input the record comes from
+    keys: &'k HashSet<String>,
+}
+
+impl<'de, 'k> serde::de::Visitor<'de> for EntryVisitor<'_, 'k> {
+    /// the timestamp and the key it's taken from
+    type Value = (Timestamp, Option<&'k str>);
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "map with keys from provided set")
+    }
+
+    #[inline]
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: serde::de::MapAccess<'de>,
+    {
+        // rank of the key the timestamp is taken from
+        let mut found: Option<(usize, Timestamp, &'k str)> = None;
+        let mut parts: Vec<Option<String>> = vec![None; self.parser.compose.len()];
+        let mut text: Option<String> = None;
+        // conditions of the filter the record meets
+        let mut met: Vec<crate::filter::Met> = match &self.parser.filter {
+            Some(filter) => vec![crate::filter::Met::Missing; filter.conditions.len()],
+            None => Vec::new(),
+        };
+
+        while let Some(k) = map.next_key::<&str>()? {
+            let filter = self.parser.filter.as_ref().filter(|filter| filter.wants(k));
+            if let Some(part) = self.parser.compose.iter().position(|field| field == k) {
+                // only strings are composed, the keys are looked at when the part is anything else
+                let value: serde_json::Value = map.next_value()?;
+                if let Some(filter) = filter {
+                    filter.check(k, &value, &mut met);
+                }
+                if let serde_json::Value::String(part_value) = value {
+                    parts[part] = Some(part_value);
+                }
+                continue;
+            }
+            if let Some(ts_regex) = self.parser.ts_regex.as_ref().filter(|r| r.field == k) {
+                // the regex is run on strings only, the keys are looked at when the field is anything else
+                let value: serde_json::Value = map.next_value()?;
+                if let Some(filter) = filter {
+                    filter.check(k, &value, &mut met);
+                }
+                text = match value {
+                    serde_json::Value::String(field_text) if ts_regex.re.is_match(&field_text) => {
+                        Some(field_text)
+                    }
+                    _ => None,
+                };
+                continue;
+            }
+            let pointers: Vec<(&str, &str)> = self
+                .keys
+                .iter()
+                .filter_map(|key| split_pointer(key).map(|(root, rest)| (key.as_str(), root, rest)))
+                .filter(|(_, root, _)| root == k)
+                .map(|(key, _, rest)| (key, rest))
+                .collect();
+            if !pointers.is_empty() {
+                // the timestamp is nested, the whole value has to be looked at
+                let value: serde_json::Value = map.next_value()?;
+                if let Some(filter) = filter {
+                    filter.check(k, &value, &mut met);
+                }
+                let mut nested: Vec<(&str, &serde_json::Value)> = pointers
+                    .into_iter()
+                    .filter_map(|(key, rest)| value.pointer(rest).map(|nested| (key, nested)))
+                    .collect();
+                if let Some(key) = self.keys.get(k) {
+                    nested.push((key, &value));
+                }
+                for (key, nested) in nested {
+                    match self.parser.rank(self.keys, key) {
+                        Some(rank) if found.is_none_or(|(best, _, _)| rank < best) => {
+                            let ts = serde::de::DeserializeSeed::deserialize(
+                                self.parser.seed(key),
+                                nested,
+                            )
+                            .map_err(serde::de::Error::custom)?;
+                            found = Some((rank, ts, key));
+                        }
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            let key = self.keys.get(k).map(String::as_str);
+            match key.and_then(|key| self.parser.rank(self.keys, key).map(|rank| (rank, key))) {
+                Some((rank, key)) if found.is_none_or(|(best, _, _)| rank < best) => {
+                    let ts = match filter {
+                        Some(filter) => {
+                            let value: serde_json::Value = map.next_value()?;
+                            filter.check(k, &value, &mut met);
+                            serde::de::DeserializeSeed::deserialize(self.parser.seed(key), &value)
+                                .map_err(serde::de::Error::custom)?
+                        }
+                        None => map.next_value_seed(self.parser.seed(key))?,
+                    };
+                    found = Some((rank, ts, key));
+                }
+                _ => match filter {
+                    Some(filter) => {
+                        let value: serde_json::Value = map.next_value()?;
+                        filter.check(k, &value, &mut met);
+                    }
+                    None => {
+                        map.next_value::<serde::de::IgnoredAny>()?;
+                    }
+                },
+            }
+        }
+
+        if let Some(filter) = &self.parser.filter {
+            if !filter.passes(&met) {
+                return Err(serde::de::Error::custom(FILTERED));
+            }
+        }
+
+        if let (Some(ts_regex), Some(text)) = (&self.parser.ts_regex, text) {
+            return serde::de::Visitor::visit_str(
+                self.parser.seed(&ts_regex.field),
+                ts_regex.capture(&text).unwrap_or_default(),
+            )
+            .map(|ts| (ts, None));
+        }
+        if !parts.is_empty() && parts.iter().all(Option::is_some) {
+            let composed: Vec<String> = parts.into_iter().flatten().collect();
+            return self
+                .parser
+                .calendar
+                .parse(&composed.join(" "))
+                .map(|ts| (ts, None))
+                .map_err(serde::de::Error::custom);
+        }
+        found
+            .map(|(_, ts, key)| (ts, Some(key)))
+            .ok_or(serde::de::Error::custom(NO_KEYS))
+    }
+}
+
+///
+/// Value of the timestamp field
+struct ValueSeed {
+    calendar: Calendar,
+    unit: Unit,
+}
+
+impl ValueSeed {
+    fn unit_of(&self, value: f64) -> Unit {
+        if self.unit != Unit::Auto {
+            return self.unit;
+        }
+        // the ranges of the units don't overlap, so every value tells its own unit,
+        // the inputs merged under the same key don't have to agree on it
+        Unit::of_magnitude(value)
+    }
+}
+
+impl<'de> serde::de::DeserializeSeed<'de> for ValueSeed {
+    type Value = Timestamp;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl serde::de::Visitor<'_> for ValueSeed {
+    type Value = Timestamp;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "number or datetime")
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.visit_i128(i128::from(v))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.visit_i128(i128::from(v))
+    }
+
+    fn visit_i128<E: serde::de::Error>(self, v: i128) -> Result<Self::Value, E> {
+        self.unit_of(v as f64)
+            .normalize(v)
+            .ok_or_else(|| E::custom(format!("{} is out of range of timestamps", v)))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        self.unit_of(v)
+            .normalize_f64(v)
+            .ok_or_else(|| E::custom(format!("{} is out of range of timestamps", v)))
+    }
+
+    ///
+    /// Numbers are sometimes quoted, e.g. by shell scripts, they are taken the same way as unquoted ones
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if let Ok(number) = v.parse::<i128>() {
+            return self.visit_i128(number);
+        }
+        match v.parse::<f64>() {
+            Ok(number) if number.is_finite() => self.visit_f64(number),
+            _ => self.calendar.parse(v).map_err(E::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timestamp::Zone;
+
+    fn parser(zone: Option<Zone>) -> crate::timestamp::Parser {
+        crate::timestamp::Parser {
+            calendar: crate::timestamp::Calendar {
+                zone,
+                ..Default::default()
+            },
+            ..crate::timestamp::Parser::new(std::collections::HashSet::from([String::from("t")]))
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        use crate::timestamp::{parse_bound, Zone};
+        // 2024-05-01T12:00:00Z
+        let anchor = 1_714_564_800_000_000_000;
+        let hour = 3_600_000_000_000;
+        assert_eq!(
+            anchor,
+            parse_bound("2024-05-01T14:00:00+02:00", 0, Zone::Utc).unwrap()
+        );
+        assert_eq!(anchor, parse_bound("1714564800000", 0, Zone::Utc).unwrap());
+        assert_eq!(anchor, parse_bound("now", anchor, Zone::Utc).unwrap());
+        assert_eq!(
+            anchor - 2 * hour,
+            parse_bound("2h", anchor, Zone::Utc).unwrap()
+        );
+        assert_eq!(
+            anchor - 36 * hour,
+            parse_bound("1.5d", anchor, Zone::Utc).unwrap()
+        );
+        assert_eq!(
+            anchor + hour / 2,
+            parse_bound("+30m", anchor, Zone::Utc).unwrap()
+        );
+        assert_eq!(
+            anchor - 22 * hour,
+            parse_bound("yesterday 14:00", anchor, Zone::Utc).unwrap()
+        );
+        assert_eq!(
+            anchor - 24 * hour,
+            parse_bound("Yesterday 14:00", anchor, Zone::parse("+02:00").unwrap()).unwrap()
+        );
+        assert_eq!(
+            anchor - 12 * hour,
+            parse_bound("today", anchor, Zone::Utc).unwrap()
+        );
+        assert_eq!(
+            anchor + 12 * hour,
+            parse_bound("tomorrow", anchor, Zone::Utc).unwrap()
+        );
+        assert_eq!(
+            anchor - hour,
+            parse_bound("11:00", anchor, Zone::Utc).unwrap()
+        );
+        assert!(parse_bound("last week", anchor, Zone::Utc).is_err());
+        assert!(parse_bound("yesterday noon", anchor, Zone::Utc).is_err());
+        let bounds = crate::timestamp::Bounds {
+            since: Some(10),
+            until: Some(20),
+        };
+        assert!(!bounds.contains(9));
+        assert!(bounds.contains(10));
+        assert!(!bounds.contains(20));
+        assert!(bounds.passed(20));
+        assert!(crate::timestamp::Bounds::default().contains(i128::MIN));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            15_000_000,
+            parser(None).parse(r#"{"x":1, "t":15}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1704164645600000000,
+            parser(None)
+                .parse(r#"{"t":"2024-01-02T03:04:05.6Z"}"#, "-")
+                .unwrap()
+        );
+        assert!(parser(None).parse(r#"{"x":15}"#, "-").is_err());
+    }
+
+    #[test]
+    fn priority() {
+        let mut parser = parser(None);
+        let line = r#"{"ts":1, "t":2, "@timestamp":3}"#;
+        parser
+            .keys
+            .extend(["ts".to_string(), "@timestamp".to_string()]);
+        assert_eq!(1_000_000, parser.parse(line, "-").unwrap());
+        parser.priority =
+            std::collections::HashMap::from([("@timestamp".to_string(), 0), ("t".to_string(), 1)]);
+        assert_eq!(3_000_000, parser.parse(line, "-").unwrap());
+        assert_eq!(2_000_000, parser.parse(r#"{"ts":1, "t":2}"#, "-").unwrap());
+    }
+
+    #[test]
+    fn keys_for() {
+        let mut parser = parser(None);
+        parser.keys_for = std::collections::HashMap::from([(
+            "nginx.json".to_string(),
+            std::collections::HashSet::from(["time".to_string()]),
+        )]);
+        let line = r#"{"t":1, "time":2}"#;
+        assert_eq!(1_000_000, parser.parse(line, "app.json").unwrap());
+        assert_eq!(2_000_000, parser.parse(line, "nginx.json").unwrap());
+        assert!(parser.parse(r#"{"t":1}"#, "nginx.json").is_err());
+    }
+
+    #[test]
+    fn auto_key() {
+        let mut parser = parser(None);
+        parser.auto_key = Some(2);
+        let mut reader = std::io::BufReader::new(stringreader::StringReader::new(
+            "{\"t\":1, \"ts\":1713212345123}\n{\"t\":2, \"ts\":1713212345124}\n{\"t\":3, \"ts\":1}\n",
+        ));
+        parser.sample("app.json", &mut reader);
+        let line = r#"{"t":1, "ts":1713212345123}"#;
+        assert_eq!(1713212345123000000, parser.parse(line, "app.json").unwrap());
+        assert_eq!(1_000_000, parser.parse(line, "other.json").unwrap());
+        // nothing is consumed
+        assert_eq!(3, std::io::BufRead::lines(reader).count());
+    }
+
+    #[test]
+    fn rfc2822() {
+        assert_eq!(
+            1704207845000000000,
+            parser(None)
+                .parse(r#"{"t":"Tue, 02 Jan 2024 15:04:05 +0000"}"#, "-")
+                .unwrap()
+        );
+        assert_eq!(
+            1704200645000000000,
+            parser(None)
+                .parse(r#"{"t":"Tue, 2 Jan 2024 15:04:05 +0200"}"#, "-")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn log4j() {
+        let parser = parser(Some(Zone::Utc));
+        assert_eq!(
+            1704207845123000000,
+            parser
+                .parse(r#"{"t":"2024-01-02 15:04:05,123"}"#, "-")
+                .unwrap()
+        );
+        assert_eq!(
+            1704207845123000000,
+            parser
+                .parse(r#"{"t":"2024-01-02T15:04:05,123"}"#, "-")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn tai64n() {
+        assert_eq!(
+            1704207845123456789,
+            parser(None)
+                .parse(r#"{"t":"@400000006594260a075bcd15"}"#, "-")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn compose() {
+        let mut parser = parser(Some(Zone::Utc));
+        parser.compose = vec!["date".to_string(), "time".to_string()];
+        assert_eq!(
+            1704207845123000000,
+            parser
+                .parse(
+                    r#"{"time":"15:04:05.123", "t":1, "date":"2024-01-02"}"#,
+                    "-"
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            1_000_000,
+            parser
+                .parse(r#"{"time":"15:04:05.123", "t":1}"#, "-")
+                .unwrap()
+        );
+        assert!(parser
+            .parse(r#"{"time":"noon", "date":"2024-01-02"}"#, "-")
+            .is_err());
+        assert_eq!(
+            1_000_000,
+            parser
+                .parse(r#"{"time":"15:04:05.123", "t":1, "date":20240102}"#, "-")
+                .unwrap()
+        );
+        assert_eq!(
+            1_000_000,
+            parser
+                .parse(r#"{"time":null, "t":1, "date":"2024-01-02"}"#, "-")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn ts_regex() {
+        let mut parser = parser(Some(Zone::Utc));
+        parser.ts_regex =
+            Some(crate::timestamp::TsRegex::parse(r"field=msg;re=^\[(?P<ts>[^\]]+)\]").unwrap());
+        assert_eq!(
+            1704207845000000000,
+            parser
+                .parse(r#"{"msg":"[2024-01-02 15:04:05] started", "t":1}"#, "-")
+                .unwrap()
+        );
+        assert_eq!(
+            1_000_000,
+            parser.parse(r#"{"msg":"started", "t":1}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1_000_000,
+            parser
+                .parse(r#"{"msg":{"text":"started"}, "t":1}"#, "-")
+                .unwrap()
+        );
+        assert_eq!(
+            1_000_000,
+            parser.parse(r#"{"msg":42, "t":1}"#, "-").unwrap()
+        );
+        assert!(crate::timestamp::TsRegex::parse("field=msg;re=^\\[").is_err());
+        assert!(crate::timestamp::TsRegex::parse("field=msg;re=^\\[.+\\]").is_err());
+        assert!(crate::timestamp::TsRegex::parse("msg").is_err());
+    }
+
+    #[test]
+    fn json_pointer() {
+        let mut parser = parser(None);
+        parser.keys.insert("/records/0/eventTime".to_string());
+        parser.keys.insert("/a~1b".to_string());
+        assert_eq!(
+            1704207845000000000,
+            parser
+                .parse(
+                    r#"{"records":[{"eventTime":"2024-01-02T15:04:05Z"}], "x":1}"#,
+                    "-"
+                )
+                .unwrap()
+        );
+        assert_eq!(2_000_000, parser.parse(r#"{"a/b":2}"#, "-").unwrap());
+        assert_eq!(
+            3_000_000,
+            parser.parse(r#"{"records":[], "t":3}"#, "-").unwrap()
+        );
+        assert!(parser.parse(r#"{"records":[]}"#, "-").is_err());
+    }
+
+    #[test]
+    fn rewrite() {
+        let mut parser = parser(None);
+        parser.keys.insert("/meta/time".to_string());
+        parser
+            .units
+            .insert("s".to_string(), crate::timestamp::Unit::Seconds);
+        parser.keys.insert("s".to_string());
+        assert_eq!(
+            Some(r#"{"t":5,"msg":"a"}"#.to_string()),
+            parser.rewrite(r#"{"t":7, "msg":"a"}"#, "-", 5_000_000)
+        );
+        assert_eq!(
+            Some(r#"{"s":"1.5"}"#.to_string()),
+            parser.rewrite(r#"{"s":"3"}"#, "-", 1_500_000_000)
+        );
+        assert_eq!(
+            Some(r#"{"meta":{"time":"2024-01-02T15:04:05.500Z"}}"#.to_string()),
+            parser.rewrite(
+                r#"{"meta":{"time":"2024-01-02T16:00:00+01:00"}}"#,
+                "-",
+                1704207845500000000
+            )
+        );
+        assert_eq!(None, parser.rewrite(r#"{"msg":"a"}"#, "-", 0));
+    }
+
+    #[test]
+    fn humanize() {
+        let mut parser = parser(None);
+        assert_eq!(
+            Some(r#"{"t":"2024-01-02T15:04:05.500Z","msg":"a"}"#.to_string()),
+            parser.humanize(
+                r#"{"t":1704207845500, "msg":"a"}"#,
+                "-",
+                1704207845500000000
+            )
+        );
+        parser.humanize = Some(Zone::parse("Europe/Berlin").unwrap());
+        assert_eq!(
+            Some(r#"{"t":"2024-01-02T16:04:05+01:00"}"#.to_string()),
+            parser.humanize(r#"{"t":"1704207845000"}"#, "-", 1704207845000000000)
+        );
+        assert_eq!(None, parser.humanize(r#"{"msg":"a"}"#, "-", 0));
+    }
+
+    #[test]
+    fn fallback() {
+        let path = std::env::temp_dir().join(format!("jmrg-fallback-{}", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_millis(1500))
+            .unwrap();
+        let mtime = crate::timestamp::Fallback::Mtime.of(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Some(1_500_000_000), mtime);
+        assert_eq!(None, crate::timestamp::Fallback::Mtime.of("-"));
+    }
+
+    #[test]
+    fn locale() {
+        let mut parser = parser(Some(Zone::Utc));
+        parser.calendar.year = Some(2024);
+        let line = r#"{"t":"Di, 02 Jan 2024 15:04:05 +0000"}"#;
+        assert!(parser.parse(line, "-").is_err());
+        parser.calendar.locale = Some(crate::locale::Locale::De);
+        assert_eq!(1704207845000000000, parser.parse(line, "-").unwrap());
+        parser.calendar.locale = Some(crate::locale::Locale::Fr);
+        assert_eq!(
+            1704207845000000000,
+            parser.parse(r#"{"t":"janv.  2 15:04:05"}"#, "-").unwrap()
+        );
+    }
+
+    #[test]
+    fn leap_second() {
+        let mut parser = parser(Some(Zone::Utc));
+        assert_eq!(
+            1483228799999999999,
+            parser
+                .parse(r#"{"t":"2016-12-31T23:59:60.5Z"}"#, "-")
+                .unwrap()
+        );
+        assert_eq!(
+            1483228799999999999,
+            parser.parse(r#"{"t":"2016-12-31 23:59:60"}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1483228800000000000,
+            parser
+                .parse(r#"{"t":"2016-12-31T24:00:00.000Z"}"#, "-")
+                .unwrap()
+        );
+        assert!(parser
+            .parse(r#"{"t":"2016-12-31T24:00:00.5Z"}"#, "-")
+            .is_err());
+        parser.calendar.strict = true;
+        let err = parser
+            .parse(r#"{"t":"2016-12-31T23:59:60Z"}"#, "-")
+            .unwrap_err();
+        assert!(err.to_string().contains("leap second"));
+        assert!(parser
+            .parse(r#"{"t":"2016-12-31T24:00:00Z"}"#, "-")
+            .is_err());
+    }
+
+    #[test]
+    fn duration() {
+        use crate::timestamp::parse_duration;
+        assert_eq!(2_500_000_000, parse_duration("+2.5s").unwrap());
+        assert_eq!(-300_000_000, parse_duration("-300ms").unwrap());
+        assert_eq!(3_600_000_000_000, parse_duration("1h").unwrap());
+        assert_eq!(15, parse_duration("15ns").unwrap());
+        assert!(parse_duration("15").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn syslog() {
+        let line = r#"{"t":"Jan  2 15:04:05"}"#;
+        assert!(parser(None).parse(line, "-").is_err());
+        let mut parser = parser(Some(Zone::Utc));
+        parser.calendar.year = Some(2024);
+        assert_eq!(1704207845000000000, parser.parse(line, "-").unwrap());
+        assert_eq!(
+            1704207845000000000,
+            parser.parse(r#"{"t":"Jan 02 15:04:05"}"#, "-").unwrap()
+        );
+        // the year is inferred, the timestamp is never far in the future
+        parser.calendar.year = None;
+        let ts = parser.parse(line, "-").unwrap();
+        let now = crate::timestamp::from_datetime(&chrono::Utc::now());
+        assert!(ts <= now + 86_400_000_000_000);
+        assert!(ts > now - 367 * 86_400_000_000_000);
+    }
+
+    #[test]
+    fn naive_datetime() {
+        let line = r#"{"t":"2024-01-02 03:04:05.6"}"#;
+        let err = parser(None).parse(line, "-").unwrap_err();
+        assert!(err.to_string().contains("use --assume-tz"));
+        assert_eq!(
+            1704164645600000000,
+            parser(Some(Zone::Utc)).parse(line, "-").unwrap()
+        );
+        let berlin = Zone::parse("Europe/Berlin").unwrap();
+        assert_eq!(
+            1704161045600000000,
+            parser(Some(berlin)).parse(line, "-").unwrap()
+        );
+        let offset = Zone::parse("-01:00").unwrap();
+        assert_eq!(
+            1704168245600000000,
+            parser(Some(offset)).parse(line, "-").unwrap()
+        );
+    }
+
+    #[test]
+    fn units() {
+        let mut parser = parser(None);
+        parser.unit = crate::timestamp::Unit::Seconds;
+        parser.keys.insert("ns".to_string());
+        parser
+            .units
+            .insert("ns".to_string(), crate::timestamp::Unit::Nanos);
+        assert_eq!(15_000_000_000, parser.parse(r#"{"t":15}"#, "-").unwrap());
+        assert_eq!(
+            1_500_000_000,
+            parser.parse(r#"{"ns":1500000000}"#, "-").unwrap()
+        );
+        assert_eq!(-1, parser.parse(r#"{"ns":-1}"#, "-").unwrap());
+    }
+
+    #[test]
+    fn windows_units() {
+        let mut parser = parser(None);
+        parser.unit = crate::timestamp::Unit::Filetime;
+        // 2024-01-02T15:04:05.1234567Z
+        assert_eq!(
+            1704207845123456700,
+            parser.parse(r#"{"t":133486814451234567}"#, "-").unwrap()
+        );
+        parser.unit = crate::timestamp::Unit::Ticks;
+        assert_eq!(
+            1704207845123456700,
+            parser.parse(r#"{"t":638398046451234567}"#, "-").unwrap()
+        );
+    }
+
+    #[test]
+    fn auto_unit() {
+        let mut parser = parser(None);
+        parser.unit = crate::timestamp::Unit::Auto;
+        parser.keys.extend(["ms".to_string(), "ns".to_string()]);
+        assert_eq!(
+            1713212345000000000,
+            parser.parse(r#"{"t":1713212345}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1713212345500000000,
+            parser.parse(r#"{"t":1713212345.5}"#, "-").unwrap()
+        );
+        // every value has its own unit, even under the same key of the same input
+        assert_eq!(
+            1704207846000000000,
+            parser.parse(r#"{"t":1704207846000}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1704207845000000000,
+            parser.parse(r#"{"t":1704207845}"#, "other").unwrap()
+        );
+        assert_eq!(
+            1704207846500000000,
+            parser.parse(r#"{"t":1704207846500000}"#, "other").unwrap()
+        );
+        assert_eq!(
+            1713212345500000000,
+            parser.parse(r#"{"t":"1713212345.5"}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1713212345123000000,
+            parser.parse(r#"{"ms":1713212345123}"#, "-").unwrap()
+        );
+        assert_eq!(
+            1713212345123456789,
+            parser.parse(r#"{"ns":1713212345123456789}"#, "-").unwrap()
+        );
+    }
+
+    #[test]
+    fn wide() {
+        let mut parser = parser(None);
+        assert_eq!(
+            32503680000000000000,
+            parser
+                .parse(r#"{"t":"3000-01-01T00:00:00Z"}"#, "-")
+                .unwrap()
+        );
+        parser.unit = crate::timestamp::Unit::Nanos;
+        assert_eq!(
+            18446744073709551615,
+            parser.parse(r#"{"t":18446744073709551615}"#, "-").unwrap()
+        );
+        parser.unit = crate::timestamp::Unit::Seconds;
+        assert!(parser
+            .parse(r#"{"t":"100000000000000000000000000000000000"}"#, "-")
+            .is_err());
+    }
+
+    #[test]
+    fn fractional() {
+        let mut parser = parser(None);
+        assert_eq!(15_900_000, parser.parse(r#"{"t":15.9}"#, "-").unwrap());
+        parser.unit = crate::timestamp::Unit::Seconds;
+        assert_eq!(1_250_000_000, parser.parse(r#"{"t":1.25}"#, "-").unwrap());
+        assert_eq!(-500_000_000, parser.parse(r#"{"t":-0.5}"#, "-").unwrap());
+        assert!(parser.parse(r#"{"t":1e300}"#, "-").is_err());
+    }
+
+    #[test]
+    fn quoted_numbers() {
+        let parser = parser(None);
+        assert_eq!(
+            1713212345678000000,
+            parser.parse(r#"{"t":"1713212345678"}"#, "-").unwrap()
+        );
+        assert_eq!(1_500_000, parser.parse(r#"{"t":"1.5"}"#, "-").unwrap());
+        assert!(parser.parse(r#"{"t":"NaN"}"#, "-").is_err());
+    }
+
+    #[test]
+    fn zone() {
+        assert_eq!(Zone::Utc, Zone::parse("UTC").unwrap());
+        assert_eq!(Zone::Local, Zone::parse("local").unwrap());
+        assert!(Zone::parse("Mars/Olympus").is_err());
+    }
+}
@@ -0,0 +1,1490 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error;
+use crate::timestamp::{self, Timestamp};
+
+///
+/// How the timestamp is written into the record
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TsFormat {
+    /// RFC 3339 datetime in UTC, as precise as the timestamp
+    Iso8601,
+    /// epoch timestamp in the unit
+    Epoch(timestamp::Unit),
+}
+
+impl TsFormat {
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "iso8601" => Ok(TsFormat::Iso8601),
+            // the unit of the output has to be definite
+            "auto" => Err(error::MrgError {
+                msg: "timestamps can't be written in 'auto' unit".to_string(),
+            }),
+            unit => timestamp::Unit::parse(unit)
+                .map(TsFormat::Epoch)
+                .map_err(|_| error::MrgError {
+                    msg: format!(
+                        "'{}' is not a valid timestamp format, expected iso8601 or a unit like ms",
+                        name
+                    ),
+                }),
+        }
+    }
+
+    fn format(self, ts: Timestamp) -> Option<serde_json::Value> {
+        match self {
+            TsFormat::Iso8601 => timestamp::to_rfc3339(ts).map(serde_json::Value::String),
+            TsFormat::Epoch(unit) => unit.denormalize(ts).map(serde_json::Value::Number),
+        }
+    }
+}
+
+///
+/// Field the timestamp the record is ordered by is written into
+#[derive(Clone, Debug, PartialEq)]
+pub struct Normalize {
+    pub field: String,
+    pub format: TsFormat,
+}
+
+impl Normalize {
+    ///
+    /// Parses `field=format`, the format is `iso8601` unless given
+    pub fn parse(spec: &str) -> Result<Self, error::MrgError> {
+        let (field, format) = match spec.rsplit_once('=') {
+            Some((field, format)) => (field, TsFormat::parse(format)?),
+            None => (spec, TsFormat::Iso8601),
+        };
+        if field.is_empty() {
+            return Err(error::MrgError {
+                msg: format!(
+                    "'{}' is not a valid timestamp field, expected field=format",
+                    spec
+                ),
+            });
+        }
+        Ok(Normalize {
+            field: field.to_string(),
+            format,
+        })
+    }
+}
+
+///
+/// How the records are written to the output
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// JSON objects, the records are emitted as they are unless they are changed
+    #[default]
+    Ndjson,
+    /// `key=value` pairs separated by spaces
+    Logfmt,
+    /// values of the columns separated by commas, quoted when needed
+    Csv,
+    /// values of the columns separated by tabs, tabs and line breaks in them are escaped
+    Tsv,
+    /// rows of a Parquet file, the records are passed to it as JSON objects
+    Parquet,
+    /// record batches of an Arrow IPC stream, the records are passed to it as JSON objects
+    Arrow,
+    /// rows of a table of SQLite database, the records are inserted into it as JSON
+    Sqlite,
+    /// Elasticsearch bulk requests, every record is preceded by its `index` action
+    EsBulk,
+    /// GELF messages of Graylog
+    Gelf,
+    /// table of an HTML document, the records are passed to it as JSON objects
+    Html,
+}
+
+impl OutputFormat {
+    fn separator(self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Csv => Some(","),
+            OutputFormat::Tsv => Some("\t"),
+            OutputFormat::Ndjson
+            | OutputFormat::Logfmt
+            | OutputFormat::Parquet
+            | OutputFormat::Arrow
+            | OutputFormat::Sqlite
+            | OutputFormat::EsBulk
+            | OutputFormat::Gelf
+            | OutputFormat::Html => None,
+        }
+    }
+
+    ///
+    /// The records are written as JSON objects, they are re-serialized only if they are changed
+    fn is_json(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Ndjson
+                | OutputFormat::Parquet
+                | OutputFormat::Arrow
+                | OutputFormat::Sqlite
+                | OutputFormat::Html
+        )
+    }
+
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "logfmt" => Ok(OutputFormat::Logfmt),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "parquet" => Ok(OutputFormat::Parquet),
+            "arrow" => Ok(OutputFormat::Arrow),
+            "sqlite" => Ok(OutputFormat::Sqlite),
+            "es-bulk" => Ok(OutputFormat::EsBulk),
+            "gelf" => Ok(OutputFormat::Gelf),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(error::MrgError {
+                msg: format!("'{}' is not a valid output format", name),
+            }),
+        }
+    }
+}
+
+///
+/// Fields of the records written as columns of CSV and TSV output,
+/// names of nested fields are joined with dots like `k8s.pod`
+pub struct Columns {
+    names: Vec<String>,
+    /// the header is written already
+    header: bool,
+}
+
+impl Columns {
+    pub fn new(names: Vec<String>) -> Self {
+        Columns {
+            names,
+            header: false,
+        }
+    }
+}
+
+///
+/// Elasticsearch index the records go to, the timestamp of the record in UTC is formatted into it,
+/// e.g. `logs-%Y.%m.%d` for daily indices
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index(String);
+
+impl Index {
+    pub fn parse(pattern: &str) -> Result<Self, error::MrgError> {
+        if chrono::format::StrftimeItems::new(pattern)
+            .any(|item| item == chrono::format::Item::Error)
+        {
+            return Err(error::MrgError {
+                msg: format!("'{}' is not a valid strftime pattern", pattern),
+            });
+        }
+        Ok(Index(pattern.to_string()))
+    }
+
+    fn name(&self, ts: Timestamp) -> Option<String> {
+        timestamp::to_datetime(ts).map(|dt| dt.format(&self.0).to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    /// value of the field, strings are written without quotes
+    Field(String),
+}
+
+///
+/// Text the fields of the record are put into, e.g. `{ts} [{level}] {msg}`,
+/// names of nested fields are joined with dots and braces are doubled to be written as they are
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template(Vec<Segment>);
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, error::MrgError> {
+        let invalid = || {
+            error::MrgError {
+            msg: format!(
+                "'{}' is not a valid template, expected fields in braces like '{{ts}} [{{level}}] {{msg}}'",
+                template
+            ),
+        }
+        };
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' if chars.as_str().starts_with(c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .filter(|(name, _)| !name.is_empty() && !name.contains('{'))
+                        .ok_or_else(invalid)?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(name.to_string()));
+                    chars = rest.chars();
+                }
+                '}' => return Err(invalid()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Template(segments))
+    }
+
+    fn render(&self, record: &serde_json::Map<String, serde_json::Value>) -> String {
+        let mut rendered = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Field(name) => match lookup(record, name) {
+                    None | Some(serde_json::Value::Null) => {}
+                    Some(serde_json::Value::String(s)) => rendered.push_str(s),
+                    Some(value) => rendered.push_str(&value.to_string()),
+                },
+            }
+        }
+        rendered
+    }
+}
+
+///
+/// When the output is colored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// when the standard output is a terminal, unless `NO_COLOR` is set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(name: &str) -> Result<Self, error::MrgError> {
+        match name {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(error::MrgError {
+                msg: format!("'{}' is not a valid color choice", name),
+            }),
+        }
+    }
+
+    ///
+    /// Whether the output is colored, given whether it's a terminal
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Fields the level of the record is looked for in, in the order of precedence
+pub const LEVEL_KEYS: [&str; 4] = ["level", "severity", "lvl", "loglevel"];
+/// ANSI code of red foreground
+const RED: u8 = 31;
+/// ANSI code of yellow foreground
+const YELLOW: u8 = 33;
+
+///
+/// Severity of the record by its level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Severity {
+    ///
+    /// Severity of the record by its level field, numeric levels are the ones of pino and bunyan,
+    /// 10 is trace, 20 is debug, 30 is info, 40 is a warning, 50 is an error and 60 and above are fatal
+    pub fn of(line: &str) -> Option<Self> {
+        let record =
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line).ok()?;
+        Severity::of_record(&record)
+    }
+
+    fn of_record(record: &serde_json::Map<String, serde_json::Value>) -> Option<Self> {
+        match LEVEL_KEYS.iter().find_map(|key| record.get(*key))? {
+            serde_json::Value::String(level) => match level.to_ascii_lowercase().as_str() {
+                "trace" => Some(Severity::Trace),
+                "debug" | "dbg" => Some(Severity::Debug),
+                "info" | "information" | "notice" => Some(Severity::Info),
+                "warn" | "warning" => Some(Severity::Warn),
+                "error" | "err" => Some(Severity::Error),
+                "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg" | "emergency" => {
+                    Some(Severity::Fatal)
+                }
+                _ => None,
+            },
+            serde_json::Value::Number(level) => match level.as_f64()? {
+                level if level >= 60.0 => Some(Severity::Fatal),
+                level if level >= 50.0 => Some(Severity::Error),
+                level if level >= 40.0 => Some(Severity::Warn),
+                level if level >= 30.0 => Some(Severity::Info),
+                level if level >= 20.0 => Some(Severity::Debug),
+                _ => Some(Severity::Trace),
+            },
+            _ => None,
+        }
+    }
+
+    ///
+    /// Severity of syslog, which GELF uses as well
+    pub fn syslog(self) -> u8 {
+        match self {
+            Severity::Fatal => 2,
+            Severity::Error => 3,
+            Severity::Warn => 4,
+            Severity::Info => 6,
+            Severity::Debug | Severity::Trace => 7,
+        }
+    }
+}
+
+///
+/// Color of the record by its level: errors are red and warnings are yellow
+fn level_color(line: &str) -> Option<u8> {
+    match Severity::of(line)? {
+        Severity::Error | Severity::Fatal => Some(RED),
+        Severity::Warn => Some(YELLOW),
+        _ => None,
+    }
+}
+
+/// ANSI codes of the colors of the inputs: cyan, green, magenta, blue and their bright variants,
+/// red and yellow are left for the levels
+const SOURCE_COLORS: [u8; 8] = [36, 32, 35, 34, 96, 92, 95, 94];
+
+///
+/// Colors of the inputs, they are assigned in the order the inputs show up in the output
+#[derive(Default)]
+pub struct Palette(HashMap<String, u8>);
+
+impl Palette {
+    fn color_of(&mut self, path: &str) -> u8 {
+        let assigned = self.0.len();
+        *self
+            .0
+            .entry(path.to_string())
+            .or_insert(SOURCE_COLORS[assigned % SOURCE_COLORS.len()])
+    }
+}
+
+fn paint(color: u8, text: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", color, text)
+}
+
+///
+/// Field the number of the line in the output is written into
+pub struct Seq {
+    field: String,
+    /// lines emitted so far
+    count: u64,
+}
+
+impl Seq {
+    pub fn new(field: String) -> Self {
+        Seq { field, count: 0 }
+    }
+}
+
+///
+/// Values of the fields are replaced with their keyed hashes, so equal values stay equal
+pub struct Pseudonymize {
+    fields: Vec<String>,
+    mac: Hmac<Sha256>,
+}
+
+impl Pseudonymize {
+    pub fn new(fields: Vec<String>, salt: &str) -> Self {
+        let mac =
+            Hmac::<Sha256>::new_from_slice(salt.as_bytes()).expect("HMAC accepts keys of any size");
+        Pseudonymize { fields, mac }
+    }
+
+    ///
+    /// Hex-encoded HMAC-SHA256 of the value, strings are hashed by their text and the rest by their JSON
+    fn hash(&self, value: &serde_json::Value) -> String {
+        let mut mac = self.mac.clone();
+        match value {
+            serde_json::Value::String(s) => mac.update(s.as_bytes()),
+            value => mac.update(value.to_string().as_bytes()),
+        }
+        let digest = mac.finalize().into_bytes();
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+///
+/// Changes made to the records on their way to the output,
+/// the records are emitted intact unless any of them is asked for
+#[derive(Default)]
+pub struct Transform {
+    /// the timestamp is written into the field of every record
+    pub normalize: Option<Normalize>,
+    /// the label of the input is written into the field of every record
+    pub tag_source: Option<String>,
+    /// labels of the inputs which differ from their paths, by path of the input
+    pub labels: HashMap<String, String>,
+    /// lines are prefixed with the label of the input, grep-style
+    pub prefix: bool,
+    /// the number of the line in the output is written into every record
+    pub seq: Option<Seq>,
+    /// records are indented, each of them is followed by an empty line
+    pub pretty: bool,
+    /// records are re-serialized without insignificant whitespace even when they are not changed
+    pub compact: bool,
+    /// fields are ordered by their names, the given fields of the record go first
+    pub canonical: Option<Vec<String>>,
+    pub format: OutputFormat,
+    /// columns of CSV and TSV output
+    pub columns: Option<Columns>,
+    /// records are rendered through the template instead of the format
+    pub template: Option<Template>,
+    /// records are highlighted by their level with ANSI escape codes
+    pub color: bool,
+    /// labels of the prefixes are colored by the input
+    pub palette: Option<Palette>,
+    /// only these fields of the records are kept
+    pub select: Option<Vec<String>>,
+    /// these fields are removed from the records
+    pub drop: Vec<String>,
+    /// fields of nested objects are moved up to the records, their names are joined with the separator
+    pub flatten: Option<String>,
+    /// fields added to every record
+    pub fields: Vec<(String, serde_json::Value)>,
+    /// fields added to the records of the input, by path of the input
+    pub fields_for: HashMap<String, Vec<(String, serde_json::Value)>>,
+    /// values of these fields are replaced
+    pub redact: Vec<String>,
+    /// text matching these patterns is replaced in the string values
+    pub redact_patterns: Vec<regex::Regex>,
+    /// values of the fields are replaced with their keyed hashes
+    pub pseudonymize: Option<Pseudonymize>,
+    /// string values longer than this are truncated
+    pub max_field_len: Option<usize>,
+    /// index of es-bulk actions, the index of the request is used unless it's given
+    pub index: Option<Index>,
+}
+
+impl Transform {
+    fn rewrites(&self) -> bool {
+        self.select.is_some()
+            || !self.drop.is_empty()
+            || self.flatten.is_some()
+            || !self.fields.is_empty()
+            || !self.fields_for.is_empty()
+            || !self.redact.is_empty()
+            || !self.redact_patterns.is_empty()
+            || self.pseudonymize.is_some()
+            || self.max_field_len.is_some()
+            || self.normalize.is_some()
+            || self.tag_source.is_some()
+            || self.seq.is_some()
+            || self.pretty
+            || self.compact
+            || self.canonical.is_some()
+            || !self.format.is_json()
+            || self.template.is_some()
+    }
+
+    ///
+    /// Label the input is known by in the output, its path unless it's given
+    pub fn label<'p>(&'p self, path: &'p str) -> &'p str {
+        self.labels.get(path).map_or(path, String::as_str)
+    }
+
+    ///
+    /// Applies the changes to the record, the lines which are not JSON objects are left intact.
+    /// The records are expected in the order they are emitted in.
+    ///
+    /// # Arguments
+    ///
+    /// * `ts`: the timestamp the record is ordered by, offsets and clamping included
+    /// * `path`: path of the input the record comes from
+    /// * `line`: the record
+    ///
+    /// returns: the line to emit
+    pub fn apply<'l>(&mut self, ts: Timestamp, path: &str, line: &'l str) -> Cow<'l, str> {
+        if let Some(seq) = &mut self.seq {
+            seq.count += 1;
+        }
+        let color = Some(line).filter(|_| self.color).and_then(level_color);
+        let line = self.rewrite(ts, path, line);
+        let line = match color {
+            Some(color) => Cow::Owned(paint(color, &line)),
+            None => line,
+        };
+        let line = match self.prefix {
+            true => {
+                let color = self.palette.as_mut().map(|palette| palette.color_of(path));
+                let label = self.label(path);
+                match color {
+                    Some(color) => Cow::Owned(format!("{}: {}", paint(color, label), line)),
+                    None => Cow::Owned(format!("{}: {}", label, line)),
+                }
+            }
+            false => line,
+        };
+        let line = match self.pretty {
+            true => Cow::Owned(format!("{}\n", line)),
+            false => line,
+        };
+        match self.header() {
+            Some(header) => Cow::Owned(format!("{}\n{}", header, line)),
+            None => line,
+        }
+    }
+
+    ///
+    /// Header of CSV and TSV output, it's returned once, ahead of the first record
+    fn header(&mut self) -> Option<String> {
+        let separator = self.format.separator()?;
+        let columns = self.columns.as_mut().filter(|columns| !columns.header)?;
+        columns.header = true;
+        let names = columns.names.iter().map(|name| cell(name, separator));
+        Some(names.collect::<Vec<Cow<str>>>().join(separator))
+    }
+
+    ///
+    /// Changes the fields of the record, it's re-serialized only if any of them is asked for
+    fn rewrite<'l>(&self, ts: Timestamp, path: &str, line: &'l str) -> Cow<'l, str> {
+        if !self.rewrites() {
+            return Cow::Borrowed(line);
+        }
+        let mut record =
+            match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(line) {
+                Ok(record) => record,
+                Err(_) => return Cow::Borrowed(line),
+            };
+        // the fields are picked out of the record before any of them is added
+        if let Some(fields) = &self.select {
+            record = select(&record, fields);
+        }
+        for field in &self.drop {
+            let path = path_of(&record, field);
+            remove_path(&mut record, &path);
+        }
+        for field in &self.redact {
+            let path = path_of(&record, field);
+            if get_path(&record, &path).is_some() {
+                insert_path(&mut record, &path, REDACTED.into());
+            }
+        }
+        if !self.redact_patterns.is_empty() {
+            record
+                .values_mut()
+                .for_each(|value| redact_matches(value, &self.redact_patterns));
+        }
+        if let Some(pseudonymize) = &self.pseudonymize {
+            for field in &pseudonymize.fields {
+                let path = path_of(&record, field);
+                if let Some(value) = get_path(&record, &path) {
+                    let hash = pseudonymize.hash(value);
+                    insert_path(&mut record, &path, hash.into());
+                }
+            }
+        }
+        if let Some(max_len) = self.max_field_len {
+            record
+                .values_mut()
+                .for_each(|value| truncate(value, max_len));
+        }
+        let fields_for = self.fields_for.get(path).into_iter().flatten();
+        for (field, value) in self.fields.iter().chain(fields_for) {
+            let path = path_of(&record, field);
+            insert_path(&mut record, &path, value.clone());
+        }
+        if let Some(normalize) = &self.normalize {
+            if let Some(value) = normalize.format.format(ts) {
+                record.insert(normalize.field.clone(), value);
+            }
+        }
+        if let Some(field) = &self.tag_source {
+            record.insert(field.clone(), self.label(path).into());
+        }
+        if let Some(seq) = &self.seq {
+            record.insert(seq.field.clone(), seq.count.into());
+        }
+        if let Some(separator) = &self.flatten {
+            record = flatten(record, separator);
+        }
+        if let Some(first) = &self.canonical {
+            canonicalize(&mut record, first);
+        }
+        let serialized = match (&self.template, self.format) {
+            (Some(template), _) => Ok(template.render(&record)),
+            (None, OutputFormat::Logfmt) => Ok(logfmt(&record)),
+            (None, OutputFormat::Csv) | (None, OutputFormat::Tsv) => Ok(row(
+                &record,
+                self.columns.as_ref().map_or(&[], |columns| &columns.names),
+                self.format.separator().unwrap_or(","),
+            )),
+            (None, OutputFormat::Ndjson) if self.pretty => serde_json::to_string_pretty(&record),
+            (None, OutputFormat::Gelf) => {
+                serde_json::to_string(&gelf(record, ts, self.label(path)))
+            }
+            (None, OutputFormat::EsBulk) => {
+                let index = self.index.as_ref().and_then(|index| index.name(ts));
+                let action = match index {
+                    Some(index) => serde_json::json!({"index": {"_index": index}}),
+                    None => serde_json::json!({"index": {}}),
+                };
+                serde_json::to_string(&record).map(|source| format!("{}\n{}", action, source))
+            }
+            (
+                None,
+                OutputFormat::Ndjson
+                | OutputFormat::Parquet
+                | OutputFormat::Arrow
+                | OutputFormat::Sqlite
+                | OutputFormat::Html,
+            ) => serde_json::to_string(&record),
+        };
+        serialized.map_or(Cow::Borrowed(line), Cow::Owned)
+    }
+}
+
+/// Replacement of redacted values
+const REDACTED: &str = "[REDACTED]";
+
+///
+/// Replaces the text matching any of the patterns in the string values, nested ones included
+fn redact_matches(value: &mut serde_json::Value, patterns: &[regex::Regex]) {
+    match value {
+        serde_json::Value::String(s) => {
+            for pattern in patterns {
+                if let Cow::Owned(redacted) = pattern.replace_all(s, REDACTED) {
+                    *s = redacted;
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| redact_matches(item, patterns)),
+        serde_json::Value::Object(object) => object
+            .values_mut()
+            .for_each(|nested| redact_matches(nested, patterns)),
+        _ => {}
+    }
+}
+
+/// Marker of truncated values
+const ELLIPSIS: char = '…';
+
+///
+/// Cuts the string values longer than `max_len` characters, nested ones included, and marks them with an ellipsis
+fn truncate(value: &mut serde_json::Value, max_len: usize) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some((end, _)) = s.char_indices().nth(max_len) {
+                s.truncate(end);
+                s.push(ELLIPSIS);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            items.iter_mut().for_each(|item| truncate(item, max_len))
+        }
+        serde_json::Value::Object(object) => object
+            .values_mut()
+            .for_each(|nested| truncate(nested, max_len)),
+        _ => {}
+    }
+}
+
+///
+/// Moves the fields of nested objects up to the record, their names are joined with the separator.
+/// Empty objects and arrays are left as they are.
+fn flatten(
+    record: serde_json::Map<String, serde_json::Value>,
+    separator: &str,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut flat = serde_json::Map::with_capacity(record.len());
+    flatten_into(&mut flat, "", record, separator);
+    flat
+}
+
+fn flatten_into(
+    flat: &mut serde_json::Map<String, serde_json::Value>,
+    prefix: &str,
+    object: serde_json::Map<String, serde_json::Value>,
+    separator: &str,
+) {
+    for (key, value) in object {
+        let key = match prefix {
+            "" => key,
+            prefix => format!("{}{}{}", prefix, separator, key),
+        };
+        match value {
+            serde_json::Value::Object(nested) if !nested.is_empty() => {
+                flatten_into(flat, &key, nested, separator)
+            }
+            value => {
+                flat.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Fields the message of the record is looked for in, in the order of precedence
+const MESSAGE_KEYS: [&str; 3] = ["short_message", "message", "msg"];
+/// Fields the host of the record is looked for in, in the order of precedence
+pub const HOST_KEYS: [&str; 2] = ["host", "hostname"];
+
+///
+/// GELF message of the record, its message and host are taken from their fields, the label of the input is the host
+/// unless the record has one. The rest of the fields are additional ones, names of nested ones are joined with underscores.
+fn gelf(
+    mut record: serde_json::Map<String, serde_json::Value>,
+    ts: Timestamp,
+    source: &str,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut take = |keys: &[&str]| {
+        let key = keys
+            .iter()
+            .find(|key| record.get(**key).is_some_and(serde_json::Value::is_string))?;
+        match record.shift_remove(*key) {
+            Some(serde_json::Value::String(value)) => Some(value),
+            _ => None,
+        }
+    };
+    let short_message = take(&MESSAGE_KEYS);
+    let host = take(&HOST_KEYS).unwrap_or_else(|| source.to_string());
+    let level = Severity::of_record(&record).unwrap_or(Severity::Info);
+    let short_message =
+        short_message.unwrap_or_else(|| serde_json::Value::Object(record.clone()).to_string());
+    let mut message = serde_json::Map::new();
+    message.insert("version".to_string(), "1.1".into());
+    message.insert("host".to_string(), host.into());
+    message.insert("short_message".to_string(), short_message.into());
+    // seconds with milliseconds
+    let millis = ts.div_euclid(1_000_000) as f64;
+    if let Some(timestamp) = serde_json::Number::from_f64(millis / 1000.0) {
+        message.insert("timestamp".to_string(), timestamp.into());
+    }
+    message.insert("level".to_string(), level.syslog().into());
+    for (key, value) in flatten(record, "_") {
+        let value = match value {
+            serde_json::Value::String(_) | serde_json::Value::Number(_) => value,
+            serde_json::Value::Null | serde_json::Value::Object(_) => continue,
+            value => value.to_string().into(),
+        };
+        let name: String = key
+            .chars()
+            .map(
+                |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    true => c,
+                    false => '_',
+                },
+            )
+            .collect();
+        // `_id` is reserved
+        let name = match name.as_str() {
+            "id" => "_id_".to_string(),
+            _ => format!("_{}", name),
+        };
+        message.insert(name, value);
+    }
+    message
+}
+
+///
+/// Orders the fields of the object and of the objects nested in it by their names,
+/// the `first` fields of the object go ahead of the rest in the given order
+fn canonicalize(object: &mut serde_json::Map<String, serde_json::Value>, first: &[String]) {
+    let rank = |key: &str| first.iter().position(|f| f == key).unwrap_or(first.len());
+    let mut fields: Vec<(String, serde_json::Value)> = std::mem::take(object).into_iter().collect();
+    fields.sort_by(|(a, _), (b, _)| rank(a).cmp(&rank(b)).then(a.cmp(b)));
+    for (_, value) in &mut fields {
+        canonicalize_nested(value);
+    }
+    *object = fields.into_iter().collect();
+}
+
+fn canonicalize_nested(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => canonicalize(object, &[]),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(canonicalize_nested),
+        _ => {}
+    }
+}
+
+///
+/// Writes the record as `key=value` pairs, the fields of nested objects are joined with dots
+fn logfmt(record: &serde_json::Map<String, serde_json::Value>) -> String {
+    let mut pairs: Vec<String> = Vec::with_capacity(record.len());
+    logfmt_pairs("", record, &mut pairs);
+    pairs.join(" ")
+}
+
+fn logfmt_pairs(
+    prefix: &str,
+    object: &serde_json::Map<String, serde_json::Value>,
+    pairs: &mut Vec<String>,
+) {
+    for (key, value) in object {
+        // keys can't be quoted, the characters which separate pairs are replaced
+        let key: String = key
+            .chars()
+            .map(|c| match c {
+                c if c <= ' ' || c == '=' || c == '"' => '_',
+                c => c,
+            })
+            .collect();
+        let key = match prefix {
+            "" => key,
+            prefix => format!("{}.{}", prefix, key),
+        };
+        match value {
+            serde_json::Value::Object(nested) => logfmt_pairs(&key, nested, pairs),
+            serde_json::Value::Null => pairs.push(format!("{}=", key)),
+            serde_json::Value::String(s) => pairs.push(format!("{}={}", key, logfmt_value(s))),
+            value => pairs.push(format!("{}={}", key, logfmt_value(&value.to_string()))),
+        }
+    }
+}
+
+///
+/// Values which are empty or contain spaces, quotes or `=` are quoted the same way as in JSON
+fn logfmt_value(value: &str) -> Cow<'_, str> {
+    match value.is_empty() || value.chars().any(|c| c <= ' ' || c == '=' || c == '"') {
+        true => Cow::Owned(serde_json::Value::from(value).to_string()),
+        false => Cow::Borrowed(value),
+    }
+}
+
+///
+/// Values of the columns of the record, missing fields and nulls are empty
+fn row(
+    record: &serde_json::Map<String, serde_json::Value>,
+    columns: &[String],
+    separator: &str,
+) -> String {
+    let cells = columns.iter().map(|column| match lookup(record, column) {
+        None | Some(serde_json::Value::Null) => Cow::Borrowed(""),
+        Some(serde_json::Value::String(s)) => cell(s, separator),
+        Some(value) => Cow::Owned(cell(&value.to_string(), separator).into_owned()),
+    });
+    cells.collect::<Vec<Cow<str>>>().join(separator)
+}
+
+///
+/// Field of the record, a name with dots is looked up in the nested objects unless the record has it
+fn lookup<'r>(
+    record: &'r serde_json::Map<String, serde_json::Value>,
+    name: &str,
+) -> Option<&'r serde_json::Value> {
+    get_path(record, &path_of(record, name))
+}
+
+///
+/// Names of the field and of the objects it's nested in, a name with dots stands for a nested field
+/// unless the record has it
+fn path_of<'n>(record: &serde_json::Map<String, serde_json::Value>, name: &'n str) -> Vec<&'n str> {
+    match record.contains_key(name) {
+        true => vec![name],
+        false => name.split('.').collect(),
+    }
+}
+
+fn get_path<'r>(
+    object: &'r serde_json::Map<String, serde_json::Value>,
+    path: &[&str],
+) -> Option<&'r serde_json::Value> {
+    let (name, rest) = path.split_first()?;
+    let value = object.get(*name)?;
+    match rest.is_empty() {
+        true => Some(value),
+        false => get_path(value.as_object()?, rest),
+    }
+}
+
+///
+/// Sets the field, the objects it's nested in are created unless they exist
+fn insert_path(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    path: &[&str],
+    value: serde_json::Value,
+) {
+    match path {
+        [] => {}
+        [name] => {
+            object.insert(name.to_string(), value);
+        }
+        [name, rest @ ..] => {
+            let nested = object
+                .entry(name.to_string())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            if let serde_json::Value::Object(nested) = nested {
+                insert_path(nested, rest, value);
+            }
+        }
+    }
+}
+
+///
+/// Removes the field, the order of the rest is kept
+fn remove_path(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    path: &[&str],
+) -> Option<serde_json::Value> {
+    match path {
+        [] => None,
+        [name] => object.shift_remove(*name),
+        [name, rest @ ..] => remove_path(object.get_mut(*name)?.as_object_mut()?, rest),
+    }
+}
+
+///
+/// Record with only the fields, in their order, nested fields keep their objects
+fn select(
+    record: &serde_json::Map<String, serde_json::Value>,
+    fields: &[String],
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut selected = serde_json::Map::new();
+    for field in fields {
+        let path = path_of(record, field);
+        if let Some(value) = get_path(record, &path) {
+            insert_path(&mut selected, &path, value.clone());
+        }
+    }
+    selected
+}
+
+///
+/// Value of the cell, quoted as in RFC 4180 in CSV, tabs and line breaks are escaped in TSV
+fn cell<'v>(value: &'v str, separator: &str) -> Cow<'v, str> {
+    match separator {
+        "\t" if value.contains(['\t', '\n', '\r', '\\']) => Cow::Owned(
+            value
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+        ),
+        "," if value.contains([',', '"', '\n', '\r']) => {
+            Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+        }
+        _ => Cow::Borrowed(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::timestamp::Unit;
+    use crate::transform::{
+        Columns, Normalize, OutputFormat, Palette, Seq, Severity, Template, Transform, TsFormat,
+    };
+
+    #[test]
+    fn normalize() {
+        let mut transform = Transform {
+            normalize: Some(Normalize::parse("@timestamp").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":1500,"@timestamp":"2024-04-15T20:19:05.123Z"}"#,
+            transform.apply(1_713_212_345_123_000_000, "-", r#"{"t":1500}"#)
+        );
+        let mut transform = Transform {
+            normalize: Some(Normalize::parse("t=s").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":1.5,"msg":"a"}"#,
+            transform.apply(1_500_000_000, "-", r#"{"t":"x", "msg":"a"}"#)
+        );
+        assert_eq!("not json", transform.apply(1_500_000_000, "-", "not json"));
+        assert_eq!(
+            r#"{"t": 15}"#,
+            Transform::default().apply(1_500_000_000, "-", r#"{"t": 15}"#)
+        );
+    }
+
+    #[test]
+    fn tag_source() {
+        let mut transform = Transform {
+            tag_source: Some("file".to_string()),
+            labels: HashMap::from([("b.json.gz".to_string(), "web-1".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":15,"file":"a.json"}"#,
+            transform.apply(15_000_000, "a.json", r#"{"t":15}"#)
+        );
+        assert_eq!(
+            r#"{"t":16,"file":"web-1"}"#,
+            transform.apply(16_000_000, "b.json.gz", r#"{"t":16, "file":"x"}"#)
+        );
+    }
+
+    #[test]
+    fn prefix() {
+        let mut transform = Transform {
+            labels: HashMap::from([("b.json.gz".to_string(), "web-1".to_string())]),
+            prefix: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"a.json: {"t": 15}"#,
+            transform.apply(15_000_000, "a.json", r#"{"t": 15}"#)
+        );
+        assert_eq!(
+            "web-1: not json",
+            transform.apply(16_000_000, "b.json.gz", "not json")
+        );
+    }
+
+    #[test]
+    fn seq() {
+        let mut transform = Transform {
+            seq: Some(Seq::new("seq".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":15,"seq":1}"#,
+            transform.apply(15_000_000, "-", r#"{"t":15}"#)
+        );
+        assert_eq!("not json", transform.apply(15_000_000, "-", "not json"));
+        assert_eq!(
+            r#"{"t":14,"seq":3}"#,
+            transform.apply(14_000_000, "-", r#"{"t":14, "seq":1}"#)
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        let mut transform = Transform {
+            pretty: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "{\n  \"t\": 15,\n  \"k8s\": {\n    \"pod\": \"x\"\n  }\n}\n",
+            transform.apply(15_000_000, "-", r#"{"t":15,"k8s":{"pod":"x"}}"#)
+        );
+        assert_eq!("not json\n", transform.apply(15_000_000, "-", "not json"));
+    }
+
+    #[test]
+    fn compact() {
+        let mut transform = Transform {
+            compact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":15,"msg":"a b","k8s":{"pod":"x"}}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                "{ \"t\" : 15,\t\"msg\": \"a b\", \"k8s\": { \"pod\": \"x\" } }"
+            )
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        let mut transform = Transform {
+            canonical: Some(Vec::new()),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"a":[{"x":1,"y":2}],"b":{"c":3,"d":4},"t":15}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"t":15,"b":{"d":4,"c":3},"a":[{"y":2,"x":1}]}"#
+            )
+        );
+        let mut transform = Transform {
+            canonical: Some(vec![
+                "t".to_string(),
+                "msg".to_string(),
+                "level".to_string(),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":15,"msg":"a","b":1,"z":2}"#,
+            transform.apply(15_000_000, "-", r#"{"z":2,"msg":"a","b":1,"t":15}"#)
+        );
+    }
+
+    #[test]
+    fn logfmt() {
+        let mut transform = Transform {
+            format: OutputFormat::Logfmt,
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"ts=2024-01-02T03:04:05Z level=info msg="user logged in" k8s.pod=api-1 tags="[\"a\",\"b\"]" ok=true err= n=1.5 e="""#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"ts":"2024-01-02T03:04:05Z","level":"info","msg":"user logged in","k8s":{"pod":"api-1"},"tags":["a","b"],"ok":true,"err":null,"n":1.5,"e":""}"#
+            )
+        );
+        assert_eq!(
+            r#"a_b=1 c="x=\"y\"""#,
+            transform.apply(15_000_000, "-", r#"{"a b":1,"c":"x=\"y\""}"#)
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let mut transform = Transform {
+            format: OutputFormat::Csv,
+            columns: Some(Columns::new(vec![
+                "ts".to_string(),
+                "level".to_string(),
+                "k8s.pod".to_string(),
+                "msg".to_string(),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(
+            "ts,level,k8s.pod,msg\n15,info,api-1,\"a, \"\"b\"\"\"",
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"ts":15,"level":"info","k8s":{"pod":"api-1"},"msg":"a, \"b\""}"#
+            )
+        );
+        assert_eq!(
+            "16,,,\"{\"\"a\"\":1}\"",
+            transform.apply(16_000_000, "-", r#"{"ts":16,"level":null,"msg":{"a":1}}"#)
+        );
+        let mut transform = Transform {
+            format: OutputFormat::Tsv,
+            columns: Some(Columns::new(vec!["ts".to_string(), "msg".to_string()])),
+            ..Default::default()
+        };
+        assert_eq!(
+            "ts\tmsg\n15\ta\\tb\\nc",
+            transform.apply(15_000_000, "-", r#"{"ts":15,"msg":"a\tb\nc"}"#)
+        );
+    }
+
+    #[test]
+    fn template() {
+        let mut transform = Transform {
+            template: Some(Template::parse("{ts} [{level}] {k8s.pod}: {msg} {{{n}}}").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            "2024-01-02T03:04:05Z [info] api-1: user logged in {1.5}",
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"ts":"2024-01-02T03:04:05Z","level":"info","k8s":{"pod":"api-1"},"msg":"user logged in","n":1.5}"#
+            )
+        );
+        assert_eq!(
+            " [] : a {}",
+            transform.apply(15_000_000, "-", r#"{"msg":"a"}"#)
+        );
+        assert!(Template::parse("{ts").is_err());
+        assert!(Template::parse("ts}").is_err());
+        assert!(Template::parse("{} {ts}").is_err());
+    }
+
+    #[test]
+    fn es_bulk() {
+        let mut transform = Transform {
+            format: OutputFormat::EsBulk,
+            index: Some(crate::transform::Index::parse("logs-%Y.%m.%d").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            "{\"index\":{\"_index\":\"logs-2024.01.02\"}}\n{\"ts\":\"2024-01-02T03:04:05Z\",\"msg\":\"a\"}",
+            transform.apply(
+                1_704_164_645_000_000_000,
+                "-",
+                r#"{"ts":"2024-01-02T03:04:05Z", "msg":"a"}"#
+            )
+        );
+        transform.index = None;
+        assert_eq!(
+            "{\"index\":{}}\n{\"msg\":\"b\"}",
+            transform.apply(0, "-", r#"{"msg":"b"}"#)
+        );
+        assert!(crate::transform::Index::parse("logs-%Q").is_err());
+    }
+
+    #[test]
+    fn gelf() {
+        let mut transform = Transform {
+            format: OutputFormat::Gelf,
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"version":"1.1","host":"web-1","short_message":"user logged in","timestamp":1704164645.123,"level":4,"_level":"warn","_k8s_pod":"api-1","_tags":"[\"a\"]","_ok":"true","_id_":7}"#,
+            transform.apply(
+                1_704_164_645_123_456_789,
+                "app.log",
+                r#"{"level":"warn","msg":"user logged in","host":"web-1","k8s":{"pod":"api-1"},"tags":["a"],"ok":true,"id":7,"none":null}"#
+            )
+        );
+        assert_eq!(
+            r#"{"version":"1.1","host":"app.log","short_message":"{\"n\":1}","timestamp":0.0,"level":6,"_n":1}"#,
+            transform.apply(0, "app.log", r#"{"n":1}"#)
+        );
+    }
+
+    #[test]
+    fn color() {
+        let mut transform = Transform {
+            color: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "\x1b[31m{\"level\":\"ERROR\"}\x1b[0m",
+            transform.apply(15_000_000, "-", r#"{"level":"ERROR"}"#)
+        );
+        assert_eq!(
+            "\x1b[33m{\"severity\":\"warning\"}\x1b[0m",
+            transform.apply(15_000_000, "-", r#"{"severity":"warning"}"#)
+        );
+        assert_eq!(
+            "\x1b[31m{\"level\":60}\x1b[0m",
+            transform.apply(15_000_000, "-", r#"{"level":60}"#)
+        );
+        assert_eq!(
+            r#"{"level":"info"}"#,
+            transform.apply(15_000_000, "-", r#"{"level":"info"}"#)
+        );
+        assert_eq!("error", transform.apply(15_000_000, "-", "error"));
+    }
+
+    #[test]
+    fn severity() {
+        assert_eq!(Some(Severity::Fatal), Severity::of(r#"{"level":"CRIT"}"#));
+        assert_eq!(Some(Severity::Info), Severity::of(r#"{"lvl":"notice"}"#));
+        assert_eq!(Some(Severity::Debug), Severity::of(r#"{"level":20}"#));
+        assert_eq!(Some(Severity::Trace), Severity::of(r#"{"level":10}"#));
+        assert_eq!(None, Severity::of(r#"{"level":"verbose"}"#));
+        assert_eq!(None, Severity::of(r#"{"msg":"a"}"#));
+    }
+
+    #[test]
+    fn palette() {
+        let mut transform = Transform {
+            prefix: true,
+            palette: Some(Palette::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            "\x1b[36mb.json\x1b[0m: {}",
+            transform.apply(15_000_000, "b.json", "{}")
+        );
+        assert_eq!(
+            "\x1b[32ma.json\x1b[0m: {}",
+            transform.apply(15_000_000, "a.json", "{}")
+        );
+        assert_eq!(
+            "\x1b[36mb.json\x1b[0m: {}",
+            transform.apply(16_000_000, "b.json", "{}")
+        );
+    }
+
+    #[test]
+    fn select() {
+        let mut transform = Transform {
+            select: Some(vec![
+                "msg".to_string(),
+                "k8s.pod".to_string(),
+                "k8s.ns".to_string(),
+                "a.b".to_string(),
+                "missing.field".to_string(),
+            ]),
+            seq: Some(Seq::new("seq".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"msg":"a","k8s":{"pod":"x","ns":"y"},"a.b":1,"seq":1}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"ts":15,"k8s":{"ns":"y","pod":"x","node":"z"},"msg":"a","a.b":1,"a":{"b":2}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn drop() {
+        let mut transform = Transform {
+            drop: vec![
+                "stacktrace".to_string(),
+                "kubernetes.labels".to_string(),
+                "missing.field".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"ts":15,"kubernetes":{"pod":"x"},"msg":"a"}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"ts":15,"stacktrace":"...","kubernetes":{"labels":{"app":"api"},"pod":"x"},"msg":"a"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn flatten() {
+        let mut transform = Transform {
+            flatten: Some(".".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"ts":15,"k8s.pod":"x","k8s.labels.app":"api","k8s.annotations":{},"tags":[{"a":1}]}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"ts":15,"k8s":{"pod":"x","labels":{"app":"api"},"annotations":{}},"tags":[{"a":1}]}"#
+            )
+        );
+        let mut transform = Transform {
+            flatten: Some("_".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"k8s_pod":"x"}"#,
+            transform.apply(15_000_000, "-", r#"{"k8s":{"pod":"x"}}"#)
+        );
+    }
+
+    #[test]
+    fn add_field() {
+        let mut transform = Transform {
+            fields: vec![
+                ("env".to_string(), "prod".into()),
+                ("k8s.cluster".to_string(), "eu-1".into()),
+            ],
+            fields_for: HashMap::from([(
+                "a.json".to_string(),
+                vec![("env".to_string(), "staging".into())],
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"t":15,"k8s":{"pod":"x","cluster":"eu-1"},"env":"prod"}"#,
+            transform.apply(15_000_000, "b.json", r#"{"t":15,"k8s":{"pod":"x"}}"#)
+        );
+        assert_eq!(
+            r#"{"t":15,"env":"staging","k8s":{"cluster":"eu-1"}}"#,
+            transform.apply(15_000_000, "a.json", r#"{"t":15}"#)
+        );
+    }
+
+    #[test]
+    fn redact() {
+        let mut transform = Transform {
+            redact: vec![
+                "password".to_string(),
+                "headers.authorization".to_string(),
+                "missing".to_string(),
+            ],
+            redact_patterns: vec![regex::Regex::new(r"\b\d{4}-\d{4}-\d{4}-\d{4}\b").unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"password":"[REDACTED]","headers":{"authorization":"[REDACTED]","accept":"*/*"},"msg":"card [REDACTED] declined","cards":["[REDACTED]"]}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"password":{"a":1},"headers":{"authorization":"Bearer x","accept":"*/*"},"msg":"card 1234-5678-9012-3456 declined","cards":["1234-5678-9012-3456"]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn pseudonymize() {
+        let mut transform = Transform {
+            pseudonymize: Some(crate::transform::Pseudonymize::new(
+                vec!["user_id".to_string(), "user.email".to_string()],
+                "salt",
+            )),
+            ..Default::default()
+        };
+        let first = transform
+            .apply(
+                15_000_000,
+                "-",
+                r#"{"user_id":"u1","user":{"email":"a@b.c"},"msg":"a"}"#,
+            )
+            .into_owned();
+        let second = transform
+            .apply(16_000_000, "-", r#"{"user_id":"u1","msg":"b"}"#)
+            .into_owned();
+        let first: serde_json::Value = serde_json::from_str(&first).unwrap();
+        let second: serde_json::Value = serde_json::from_str(&second).unwrap();
+        assert_eq!(64, first["user_id"].as_str().unwrap().len());
+        assert_eq!(first["user_id"], second["user_id"]);
+        assert_ne!(first["user_id"], first["user"]["email"]);
+        assert_eq!("a", first["msg"]);
+        let mut salted = Transform {
+            pseudonymize: Some(crate::transform::Pseudonymize::new(
+                vec!["user_id".to_string()],
+                "pepper",
+            )),
+            ..Default::default()
+        };
+        let third = salted
+            .apply(15_000_000, "-", r#"{"user_id":"u1"}"#)
+            .into_owned();
+        let third: serde_json::Value = serde_json::from_str(&third).unwrap();
+        assert_ne!(first["user_id"], third["user_id"]);
+    }
+
+    #[test]
+    fn max_field_len() {
+        let mut transform = Transform {
+            max_field_len: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"payload":"aGVs…","msg":"ok","stack":["at f…"],"nested":{"text":"прив…"}}"#,
+            transform.apply(
+                15_000_000,
+                "-",
+                r#"{"payload":"aGVsbG8=","msg":"ok","stack":["at foo"],"nested":{"text":"привет"}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Normalize {
+                field: "ts".to_string(),
+                format: TsFormat::Epoch(Unit::Millis),
+            },
+            Normalize::parse("ts=ms").unwrap()
+        );
+        assert!(Normalize::parse("ts=auto").is_err());
+        assert!(Normalize::parse("ts=week").is_err());
+        assert!(Normalize::parse("=ms").is_err());
+    }
+}
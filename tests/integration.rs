@@ -24,3 +24,212 @@ fn normal_run() -> Result<(), Box<dyn std::error::Error>> {
         .stderr(predicates::str::is_empty());
     Ok(())
 }
+
+#[test]
+fn stdin_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t"])
+        .arg("-")
+        .arg("./tests/data/2.json.gz")
+        .write_stdin("{\"t\":15, \"add\": \"15_s\"}\n{\"t\":18, \"add\": \"18_s\"}\n");
+
+    let pred = predicates::str::is_match(
+        "\\{\"t\":15, \"add\": \"15_s\"\\}\
+        \n\\{\"t\":16, \"add\": \"16_2\"\\}\
+        \n\\{\"t\":17, \"add\": \"17_2\"\\}\
+        \n\\{\"t\":18, \"add\": \"18_s\"\\}",
+    )
+    .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(pred)
+        .stderr(predicates::str::is_empty());
+    Ok(())
+}
+
+#[test]
+fn files_from_stdin_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "--files-from", "-"])
+        .arg("./tests/data/1.json")
+        .write_stdin("./tests/data/2.json.gz\n");
+
+    let pred = predicates::str::is_match(
+        "\\{\"t\":15, \"add\": \"15_1\"\\}\
+        \n\\{\"t\":16, \"add\": \"16_2\"\\}\
+        \n\\{\"t\":16, \"add\": \"16_1\"\\}\
+        \n\\{\"t\":17, \"add\": \"17_2\"\\}\
+        \n\\{\"t\":18, \"add\": \"18_1\"\\}",
+    )
+    .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(pred)
+        .stderr(predicates::str::is_empty());
+    Ok(())
+}
+
+#[test]
+fn input_format_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "--input-format", "gzip", "-"])
+        .write_stdin(std::fs::read("./tests/data/2.json.gz")?);
+
+    let pred = predicates::str::is_match(
+        "\\{\"t\":16, \"add\": \"16_2\"\\}\
+        \n\\{\"t\":17, \"add\": \"17_2\"\\}",
+    )
+    .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(pred)
+        .stderr(predicates::str::is_empty());
+    Ok(())
+}
+
+#[test]
+fn truncated_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t"])
+        .arg("./tests/data/1.json")
+        .arg("./tests/data/13.json.gz");
+
+    let pred = predicates::str::is_match(
+        "\\{\"t\":15, \"add\": \"15_1\"\\}\
+        \n\\{\"t\":16, \"add\": \"16_1\"\\}\
+        \n\\{\"t\":18, \"add\": \"18_1\"\\}\
+        \n\\{\"t\":20, \"add\": \"20_13\"\\}",
+    )
+    .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(pred)
+        .stderr(predicates::str::contains("skipping the rest of the input").count(1));
+    Ok(())
+}
+
+#[test]
+fn offset_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "--offset", "./tests/data/1.json=+1.5ms"])
+        .arg("./tests/data/1.json")
+        .arg("./tests/data/2.json.gz");
+
+    let pred = predicates::str::is_match(
+        "\\{\"t\":16, \"add\": \"16_2\"\\}\
+        \n\\{\"t\":15, \"add\": \"15_1\"\\}\
+        \n\\{\"t\":17, \"add\": \"17_2\"\\}\
+        \n\\{\"t\":16, \"add\": \"16_1\"\\}\
+        \n\\{\"t\":18, \"add\": \"18_1\"\\}",
+    )
+    .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(pred)
+        .stderr(predicates::str::is_empty());
+    Ok(())
+}
+
+#[test]
+fn output_run() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("jmrg-output-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("merged.json");
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "-o"])
+        .arg(&path)
+        .arg("./tests/data/1.json")
+        .arg("./tests/data/2.json.gz");
+
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::is_empty())
+        .stderr(predicates::str::contains("merged.json': 120 bytes written"));
+    let merged = std::fs::read_to_string(&path)?;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(5, merged.lines().count());
+    assert!(merged.starts_with("{\"t\":15, \"add\": \"15_1\"}\n"));
+    Ok(())
+}
+
+#[test]
+fn tee_run() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("jmrg-tee-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("merged.json.gz");
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "-o"])
+        .arg(&path)
+        .args(vec!["-o", "-"])
+        .arg("./tests/data/1.json")
+        .arg("./tests/data/2.json.gz");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output)?;
+    let mut merged = String::new();
+    std::io::Read::read_to_string(
+        &mut flate2::read::GzDecoder::new(std::fs::File::open(&path)?),
+        &mut merged,
+    )?;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(5, stdout.lines().count());
+    assert_eq!(stdout, merged);
+    Ok(())
+}
+
+#[test]
+fn scoped_tee_run() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("jmrg-scoped-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("merged.json.gz");
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "-o"])
+        .arg(&path)
+        .args(vec!["-o", "where=t>=17;format=logfmt;-"])
+        .arg("./tests/data/1.json")
+        .arg("./tests/data/2.json.gz");
+
+    cmd.assert()
+        .success()
+        .stdout("t=17 add=17_2\nt=18 add=18_1\n");
+    let mut merged = String::new();
+    std::io::Read::read_to_string(
+        &mut flate2::read::GzDecoder::new(std::fs::File::open(&path)?),
+        &mut merged,
+    )?;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(5, merged.lines().count());
+    Ok(())
+}
+
+#[test]
+fn where_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec![
+        "-k", "t", "--where", "add=15_1", "--where", "t=17", "--any",
+    ])
+    .arg("./tests/data/1.json")
+    .arg("./tests/data/2.json.gz");
+
+    cmd.assert()
+        .success()
+        .stdout("{\"t\":15, \"add\": \"15_1\"}\n{\"t\":17, \"add\": \"17_2\"}\n")
+        .stderr("");
+    Ok(())
+}
+
+#[test]
+fn html_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = std::process::Command::cargo_bin("jmrg")?;
+    cmd.args(vec!["-k", "t", "--output-format", "html"])
+        .arg("./tests/data/1.json")
+        .arg("./tests/data/2.json.gz");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let html = String::from_utf8(output)?;
+    assert!(html.starts_with("<!DOCTYPE html>\n"));
+    assert!(html.ends_with("</html>\n"));
+    assert!(html.contains("\n\"{\\\"t\\\":15, \\\"add\\\": \\\"15_1\\\"}\",\n"));
+    assert_eq!(5, html.lines().filter(|line| line.ends_with("\",")).count());
+    Ok(())
+}